@@ -1,266 +1,236 @@
+pub use jazz_vm::{array::Array, map::Map};
 use jazz_vm::{
-    function::Function,
+    bigint::BigInt,
+    error::VmError,
+    iterator::{self, Iter},
     machine::Machine,
-    object::{Object, ObjectAddon},
-    object_pool::ObjectPool,
+    object::ObjectAddon,
+    range::Range,
     value::Value,
 };
 
-use std::{cell::RefCell, io::stdin};
+use crate::{class::Class, enums::EnumValue};
+use std::{convert::TryFrom, io::stdin};
 
-#[derive(Clone)]
-pub struct Array {
-    elements: RefCell<Vec<Value>>,
+pub fn new_array(m: &mut Machine, args: &[Value]) -> Value {
+    Array::allocate(m, args[1..].to_vec())
 }
 
-pub fn array_pop(m: &mut Machine, args: Vec<Value>) -> Value {
-    if let Value::Object(id) = &args[1] {
-        let obj = m.pool.get(*id).as_any();
-
-        let array = if let Some(array) = obj.downcast_ref::<Array>() {
-            array
-        } else {
-            panic!("Not a Array object!");
-        };
-
-        let v = array.pop();
-        return v;
-    } else {
-        panic!("Array::pop expects Array as argument!");
-    }
-}
-
-pub fn array_push(m: &mut Machine, args: Vec<Value>) -> Value {
-    if let Value::Object(id) = &args[0] {
-        let obj = m.pool.get(*id).as_any();
-
-        let array = if let Some(array) = obj.downcast_ref::<Array>() {
-            array
-        } else {
-            panic!("Not a Array object!");
-        };
-
-        let v = args[1];
-        array.push(v);
-        return Value::Null;
-    } else {
-        panic!("Array::push expects Array as argument!");
-    }
-}
-
-pub fn array_size(m: &mut Machine, args: Vec<Value>) -> Value {
-    if let Value::Object(id) = &args[0] {
-        let obj = m.pool.get(*id).as_any();
-
-        let array = if let Some(array) = obj.downcast_ref::<Array>() {
-            array
-        } else {
-            panic!("Not a Array object!");
-        };
-        return Value::Int(array.elements.borrow().len() as i32);
-    } else {
-        panic!("Array::size expects Array as argument!");
+/// `__new_map__(k1, v1, k2, v2, ...)`, used for map literals
+pub fn new_map(m: &mut Machine, args: &[Value]) -> Value {
+    let map = Map::new();
+    for pair in args[1..].chunks(2) {
+        map.set(m, pair[0], pair[1]);
     }
+    Value::Object(m.pool.allocate(Box::new(map)))
 }
 
-pub fn array_get(m: &mut Machine, args: Vec<Value>) -> Value {
-    if let Value::Object(id) = &args[0] {
-        let obj = m.pool.get(*id).as_any();
-        let idx = match &args[1] {
-            Value::Int(integer) => *integer as usize,
-            Value::Long(long) => *long as usize,
-            _ => panic!("Array::get expects Long or Int value as index"),
-        };
-        let array = if let Some(array) = obj.downcast_ref::<Array>() {
-            array
-        } else {
-            panic!("Not a Array object!");
-        };
-        return array.get(idx);
-    } else {
-        panic!("Array::get expects Array as argument!");
+pub fn concat(m: &mut Machine, args: &[Value]) -> Value {
+    let mut buffer = String::new();
+    for arg in &args[1..] {
+        buffer.push_str(&arg.to_String(m));
     }
+    Value::Object(m.pool.allocate(Box::new(buffer)))
 }
 
-pub fn array_set(m: &mut Machine, args: Vec<Value>) -> Value {
-    if let Value::Object(id) = &args[0] {
-        let obj = m.pool.get(*id).as_any();
-        let idx = match &args[1] {
-            Value::Int(integer) => *integer as usize,
-            Value::Long(long) => *long as usize,
-            _ => panic!("Array::set expects Long or Int value as index"),
+/// `__interpolate__(parts...)`, used for interpolated string literals
+///
+/// Instances of classes which define `toString` are converted by calling it, other values by `to_String`.
+pub fn interpolate(m: &mut Machine, args: &[Value]) -> Value {
+    let mut buffer = String::new();
+    for &arg in &args[1..] {
+        let to_string = match arg {
+            Value::Object(id) => m
+                .pool
+                .get_direct_typed::<Class>(id)
+                .and_then(|class| unsafe { (*class.fields.get()).get("toString").copied() }),
+            _ => None,
         };
-        let array = if let Some(array) = obj.downcast_ref::<Array>() {
-            array
-        } else {
-            panic!("Not a Array object!");
+        let part = match to_string {
+            Some(method) => match m.invoke(method, &[arg]) {
+                Ok(v) => v,
+                Err(e) => {
+                    m.raise(e);
+                    return Value::Null;
+                }
+            },
+            None => arg,
         };
-
-        return array.get(idx);
-    } else {
-        panic!("Array::set expects Array as argument!");
+        buffer.push_str(&part.to_String(m));
     }
+    Value::Object(m.pool.allocate(Box::new(buffer)))
 }
 
-impl Array {
-    pub fn new() -> Array {
-        Array {
-            elements: RefCell::new(Vec::new()),
+/// `__match_size__(v, n, variant)`, used by array and enum variant patterns of `match`
+///
+/// True if `v` is an array of `n` elements. If `variant` is true, `v` is known to be a value of variant
+/// already, and it's an error if the variant has other number of associated values.
+pub fn match_size(m: &mut Machine, args: &[Value]) -> Value {
+    let (value, size) = (args[1], args[2].to_long(m) as usize);
+    let id = match value {
+        Value::Object(id) => id,
+        _ => return Value::Bool(false),
+    };
+    if let Value::Bool(false) = args[3] {
+        let array = m.pool.get_direct_typed::<Array>(id);
+        return Value::Bool(array.is_some_and(|array| array.len() == size));
+    }
+    if let Some(variant) = m.pool.get_direct_typed::<EnumValue>(id) {
+        if variant.values.len() != size {
+            let e = format!(
+                "pattern of {} has {} values, variant has {}",
+                variant.variant.typename(),
+                size,
+                variant.values.len()
+            );
+            m.raise(VmError::RuntimeError(e));
         }
+        return Value::Bool(true);
     }
-
-    pub fn push(&self, v: Value) {
-        self.elements.borrow_mut().push(v);
-    }
-
-    pub fn pop(&self) -> Value {
-        let mut elements = self.elements.borrow_mut();
-        let value = {
-            let value = elements.pop();
-            if value.is_some() {
-                value.unwrap()
-            } else {
-                Value::Null
-            }
-        };
-        value
-    }
-
-    pub fn set(&self, idx: usize, v: Value) {
-        self.elements.borrow_mut()[idx] = v;
-    }
-
-    pub fn get(&self, idx: usize) -> Value {
-        self.elements.borrow()[idx]
-    }
+    Value::Bool(false)
 }
 
-use std::any::Any;
-
-
-impl ObjectAddon for Array {
-    
-
-    fn to_String(&self, m: &mut Machine) -> String {
-        let elements = self.elements.borrow();
-
-        let mut string = String::new();
-        string.push_str("[");
-        let mut i = 0;
-        while i < elements.len() {
-            string.push_str(&elements[i].to_String(m));
-            if i != elements.len() - 1 {
-                string.push_str(",");
-            }
-            i += 1;
-        }
-        string.push_str("]");
+/// `__no_match__(v)`, raises error when none of arms of `match` matches `v`
+pub fn no_match(m: &mut Machine, args: &[Value]) -> Value {
+    let e = format!("no match arm for {}", args[1].to_String(m));
+    m.raise(VmError::RuntimeError(e));
+    Value::Null
+}
 
-        string
-    }
+/// Result of native method, errors are raised
+fn raise_err(m: &mut Machine, result: Result<Value, VmError>) -> Value {
+    result.unwrap_or_else(|e| {
+        m.raise(e);
+        Value::Null
+    })
 }
 
-impl Object for Array {
-    fn initialize(&mut self, _: &mut ObjectPool) {}
-    fn as_any(&self) -> &dyn Any {
-        self as &dyn Any
-    }
+/// `__range__(start, end, inclusive)`, used for `start..end` and `start..=end`
+pub fn range(m: &mut Machine, args: &[Value]) -> Value {
+    let inclusive = matches!(args[3], Value::Bool(true));
+    let range = Range::allocate(m, args[1], args[2], inclusive);
+    raise_err(m, range)
+}
 
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        self as &mut dyn Any
-    }
+/// `__iter__(v)`, iterator of `for x in v` loop
+pub fn iter(m: &mut Machine, args: &[Value]) -> Value {
+    let iter = Iter::allocate(m, args[1]);
+    raise_err(m, iter)
+}
 
-    fn get_children(&self) -> Vec<usize> {
-        Vec::new()
-    }
+/// `__next__(it)`, moves iterator to the next element, false if there is none
+pub fn next(m: &mut Machine, args: &[Value]) -> Value {
+    let next = iterator::advance(m, args[1]).map(Value::Bool);
+    raise_err(m, next)
+}
 
-    fn load_at(&self, m: &mut Machine, args: Vec<Value>, rindex: usize) {
-        let _this = args[0];
-        if let Value::Object(id) = &args[1] {
-            let str: &str = &m.pool.get(*id).to_String(m);
+/// `__value__(it)`, element iterator is at
+pub fn value(m: &mut Machine, args: &[Value]) -> Value {
+    let value = iterator::current(m, args[1]);
+    raise_err(m, value)
+}
 
-            match str {
-                "pop" => {
-                    let function = Function::from_native(Box::new(array_pop));
-                    let function_id = Value::Object(m.pool.allocate(Box::new(function)));
-                    m.set(rindex, function_id);
-                }
-                "push" => {
-                    let function = Function::from_native(Box::new(array_push));
-                    let function_id = Value::Object(m.pool.allocate(Box::new(function)));
-                    m.set(rindex, function_id);
-                }
-                "set" => {
-                    let function = Function::from_native(Box::new(array_pop));
-                    let function_id = Value::Object(m.pool.allocate(Box::new(function)));
-                    m.set(rindex, function_id);
+/// `bigint(v)`: `BigInt` with value of integer or decimal string `v`, `null` if `v` is neither
+pub fn bigint(m: &mut Machine, args: &[Value]) -> Value {
+    let big = match args.get(1) {
+        Some(Value::Int(i)) => BigInt::from(i64::from(*i)),
+        Some(Value::Long(l)) => BigInt::from(*l),
+        Some(Value::Object(id)) => {
+            if let Some(big) = m.pool.get_direct_typed::<BigInt>(*id) {
+                big.clone()
+            } else if let Some(s) = m.pool.get_direct_typed::<String>(*id) {
+                match BigInt::parse(s) {
+                    Some(big) => big,
+                    None => return Value::Null,
                 }
-                "get" => {
-                    let function = Function::from_native(Box::new(array_get));
-                    let function_id = Value::Object(m.pool.allocate(Box::new(function)));
-                    m.set(rindex, function_id);
-                }
-                "size" => {
-                    let function = Function::from_native(Box::new(array_size));
-                    let function_id = Value::Object(m.pool.allocate(Box::new(function)));
-                    m.set(rindex, function_id);
-                }
-                v => panic!("{:?}", v),
+            } else {
+                return Value::Null;
             }
-            return;
         }
-        let elements = self.elements.borrow();
-        if let Value::Int(int) = &args[1] {
-            let v = elements.get(*int as usize).unwrap();
+        _ => return Value::Null,
+    };
+    BigInt::allocate(m, big.0)
+}
 
-            m.set(rindex, v.clone());
+/// `long(v)`: `v` converted to `Long`, floats are truncated and `Char` gives its code point
+///
+/// Returns `null` if `v` is not a number or decimal string or its value doesn't fit into `Long`
+pub fn long(m: &mut Machine, args: &[Value]) -> Value {
+    let truncate = |d: f64| {
+        // `i64::MAX as f64` rounds up to 2^63 which doesn't fit anymore
+        if d.is_finite() && d >= i64::MIN as f64 && d < i64::MAX as f64 {
+            Value::Long(d.trunc() as i64)
+        } else {
+            Value::Null
         }
-        if let Value::Long(long) = &args[1] {
-            let v = elements.get(*long as usize).unwrap();
-            m.set(rindex, v.clone());
+    };
+    match args.get(1) {
+        Some(Value::Int(i)) => Value::Long(i64::from(*i)),
+        Some(Value::Long(l)) => Value::Long(*l),
+        Some(Value::Char(c)) => Value::Long(i64::from(u32::from(*c))),
+        Some(Value::Float(f)) => truncate(f64::from(*f)),
+        Some(Value::Double(d)) => truncate(*d),
+        Some(Value::Object(id)) => {
+            let big = match m.pool.get_direct_typed::<BigInt>(*id) {
+                Some(big) => Some(big.clone()),
+                None => m
+                    .pool
+                    .get_direct_typed::<String>(*id)
+                    .and_then(|s| BigInt::parse(s)),
+            };
+            big.and_then(|big| big.to_i64())
+                .map_or(Value::Null, Value::Long)
         }
-    }
-
-    fn store_at(&self, m: &mut Machine, args: Vec<Value>, _rindex: usize) {
-        let idx = args[1];
-        let value = args[2];
-
-        let idx = idx.to_int(m) as usize;
-        self.elements.borrow_mut()[idx] = value;
+        _ => Value::Null,
     }
 }
 
-pub fn new_array(m: &mut Machine, args: Vec<Value>) -> Value {
-    let array = Array::new();
-    for i in 1..args.len() {
-        array.push(args[args.len() - i]);
+/// `char(v)`: `Char` with code point `v` or the only character of string `v`
+///
+/// Returns `null` if `v` is neither valid code point nor string of one character
+pub fn char(m: &mut Machine, args: &[Value]) -> Value {
+    let code = |l: i64| {
+        u32::try_from(l)
+            .ok()
+            .and_then(std::char::from_u32)
+            .map_or(Value::Null, Value::Char)
+    };
+    match args.get(1) {
+        Some(Value::Char(c)) => Value::Char(*c),
+        Some(Value::Int(i)) => code(i64::from(*i)),
+        Some(Value::Long(l)) => code(*l),
+        Some(Value::Object(id)) => match m.pool.get_direct_typed::<String>(*id) {
+            Some(s) => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Value::Char(c),
+                    _ => Value::Null,
+                }
+            }
+            None => Value::Null,
+        },
+        _ => Value::Null,
     }
-    let object = Value::Object(m.pool.allocate(Box::new(array)));
-    object
 }
 
-pub fn concat(m: &mut Machine, args: Vec<Value>) -> Value {
-    let mut buffer = String::new();
-    for i in 1..args.len() {
-        buffer.push_str(&args[i].to_String(m));
-    }
-    let object = Value::Object(m.pool.allocate(Box::new(buffer)));
-    object
+/// `str(v)`: string representation of `v`
+pub fn str(m: &mut Machine, args: &[Value]) -> Value {
+    let s = args.get(1).map_or(Value::Null, |v| *v).to_String(m);
+    Value::Object(m.pool.allocate(Box::new(s)))
 }
 
-pub fn print(m: &mut Machine, args: Vec<Value>) -> Value {
-    for i in 1..args.len() {
-        let str = args[i].to_String(m);
+pub fn print(m: &mut Machine, args: &[Value]) -> Value {
+    for arg in &args[1..] {
+        let str = arg.to_String(m);
 
         print!("{}", str);
     }
-    print!("\n");
+    println!();
     Value::Null
 }
 
-pub fn readln(m: &mut Machine, _args: Vec<Value>) -> Value {
+pub fn readln(m: &mut Machine, _args: &[Value]) -> Value {
     let mut buffer = String::new();
     stdin().read_line(&mut buffer).unwrap();
-    let obj = Value::Object(m.pool.allocate(Box::new(buffer)));
-    obj
+    Value::Object(m.pool.allocate(Box::new(buffer)))
 }
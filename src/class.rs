@@ -1,4 +1,6 @@
 use jazz_vm::{
+    error::VmError,
+    iterator::{self, Iterate},
     machine::Machine,
     object::{Object, ObjectAddon},
     object_pool::ObjectPool,
@@ -31,6 +33,12 @@ impl Clone for Class {
     }
 }
 
+impl Default for Class {
+    fn default() -> Class {
+        Class::new()
+    }
+}
+
 impl Class {
     pub fn new() -> Class {
         Class {
@@ -40,15 +48,32 @@ impl Class {
     }
 }
 
+/// Iterator implemented by class, elements are results of its `next()` until it returns `null`
+///
+/// `null` always ends iteration, class which has to produce `null` elements should return array or generator
+/// from `iter()` instead
+struct Methods {
+    this: Value,
+    next: Value,
+}
+
+impl Iterate for Methods {
+    fn next(&mut self, m: &mut Machine) -> Result<Option<Value>, VmError> {
+        match m.invoke(self.next, &[self.this])? {
+            Value::Null => Ok(None),
+            v => Ok(Some(v)),
+        }
+    }
+}
+
 impl ObjectAddon for Class {
     fn o_clone(&self, m: &mut Machine) -> Value {
         let c = self.clone();
 
-        let v = Value::Object(m.pool.allocate(Box::new(c)));
-        v
+        Value::Object(m.pool.allocate(Box::new(c)))
     }
     fn typename(&self, _: &mut Machine) -> String {
-        return self.name.clone();
+        self.name.clone()
     }
 
     fn to_String(&self, _m: &mut Machine) -> String {
@@ -62,7 +87,7 @@ impl ObjectAddon for Class {
                 v.to_String(_m)
             ));
         }
-        string.push_str("}");
+        string.push('}');
 
         string
     }
@@ -80,7 +105,23 @@ impl Object for Class {
         Vec::new()
     }
 
-    fn call(&self, m: &mut Machine, args: Vec<Value>) -> Value {
+    /// `iter()` gives value to iterate over, object that has `next()` is iterator itself
+    fn iterator(&self, m: &mut Machine, this: Value) -> Result<Box<dyn Iterate>, VmError> {
+        let fields = unsafe { &*self.fields.get() };
+        if let Some(&iter) = fields.get("iter") {
+            let value = m.invoke(iter, &[this])?;
+            match (value, this) {
+                (Value::Object(a), Value::Object(b)) if a == b => (),
+                _ => return iterator::iterator(m, value),
+            }
+        }
+        match fields.get("next") {
+            Some(&next) => Ok(Box::new(Methods { this, next })),
+            None => Err(iterator::not_iterable(self.name.clone())),
+        }
+    }
+
+    fn call(&self, m: &mut Machine, args: &[Value]) -> Value {
         let class = if let Value::Object(id) = args[0] {
             let obj = m.pool.get(id);
 
@@ -89,16 +130,14 @@ impl Object for Class {
             panic!("Expected Value::Object");
         };
 
-        let ret = {
-            let fields = unsafe { &mut *class.fields.get() };
-            let field = fields.get("init").expect("Couldn't find initializer");
-            let mut args = args.clone();
-            args[0] = class.o_clone(m);
-            let v = m.invoke(*field, args);
-            m.stack.pop();
-            v
-        };
-        ret
+        let fields = unsafe { &mut *class.fields.get() };
+        let field = fields.get("init").expect("Couldn't find initializer");
+        let mut args = args.to_vec();
+        args[0] = class.o_clone(m);
+        m.invoke(*field, &args).unwrap_or_else(|e| {
+            m.raise(e);
+            Value::Null
+        })
     }
 
     fn store_at(&self, m: &mut Machine, args: Vec<Value>, _: usize) {
@@ -112,7 +151,9 @@ impl Object for Class {
         if let Value::Object(id) = args[1] {
             let str = m.pool.get(id).to_String(m);
             let fields = unsafe { &*self.fields.get() };
-            let field = fields.get(&str).expect(&format!("No such field {}", str));
+            let field = fields
+                .get(&str)
+                .unwrap_or_else(|| panic!("No such field {}", str));
 
             m.set(rindex, *field);
         }
@@ -121,8 +162,10 @@ impl Object for Class {
             let field = fields
                 .get("__get__")
                 .expect("Class doesn't have __get__ method");
-            let v = m.invoke(*field, args);
-            m.set(rindex, v);
+            match m.invoke(*field, &args) {
+                Ok(v) => m.set(rindex, v),
+                Err(e) => m.raise(e),
+            }
         }
     }
 }
@@ -0,0 +1,225 @@
+//! Enums
+//!
+//! `enum Shape { Circle(r), Rect(w, h), Empty }` defines global `Shape`. `Shape.Circle` is a constructor
+//! of variant with associated values, `Shape.Circle(2)` creates its value. Variant without associated values
+//! has a single value, `Shape.Empty` is that value itself.
+//!
+//! Typename of variant and its values is `Shape.Circle`, so `v ~ Shape.Circle` checks variant of value and
+//! `v ~ Shape` holds for every value of enum. Associated values are fields named by parameters, `c.r`, and
+//! can be loaded by position as well, `c[0]`, but not assigned.
+
+use jazz_vm::{
+    error::VmError,
+    machine::Machine,
+    object::{Object, ObjectAddon},
+    object_pool::ObjectPool,
+    value::Value,
+};
+
+use std::{any::Any, collections::HashMap, convert::TryFrom, sync::Arc};
+
+/// Declaration of variant shared by its constructor and values
+#[derive(Debug)]
+pub struct Variant {
+    pub enum_name: String,
+    pub name: String,
+    pub params: Vec<String>,
+}
+
+impl Variant {
+    /// `Enum.Variant`
+    pub fn typename(&self) -> String {
+        format!("{}.{}", self.enum_name, self.name)
+    }
+}
+
+/// Global defined by `enum` declaration, its fields are constructors and values of variants
+#[derive(Debug)]
+pub struct Enum {
+    pub name: String,
+    pub variants: HashMap<String, Value>,
+}
+
+impl Enum {
+    /// Enum `name` with `variants` given as names and parameters
+    pub fn allocate(m: &mut Machine, name: &str, variants: &[(String, Vec<String>)]) -> Value {
+        let mut values = HashMap::new();
+        for (variant, params) in variants.iter() {
+            let variant = Arc::new(Variant {
+                enum_name: name.to_owned(),
+                name: variant.clone(),
+                params: params.clone(),
+            });
+            let value: Box<dyn Object> = if variant.params.is_empty() {
+                Box::new(EnumValue {
+                    variant: variant.clone(),
+                    values: vec![],
+                })
+            } else {
+                Box::new(Constructor(variant.clone()))
+            };
+            values.insert(variant.name.clone(), Value::Object(m.pool.allocate(value)));
+        }
+        let enumeration = Enum {
+            name: name.to_owned(),
+            variants: values,
+        };
+        Value::Object(m.pool.allocate(Box::new(enumeration)))
+    }
+}
+
+/// Name stored in `key` of `load_at`
+fn key_name(m: &mut Machine, key: Value) -> Option<String> {
+    match key {
+        Value::Object(id) => m.pool.get_direct_typed::<String>(id).cloned(),
+        _ => None,
+    }
+}
+
+impl ObjectAddon for Enum {
+    fn typename(&self, _: &mut Machine) -> String {
+        self.name.clone()
+    }
+
+    fn to_String(&self, _: &mut Machine) -> String {
+        format!("enum {}", self.name)
+    }
+}
+
+impl Object for Enum {
+    fn as_any(&self) -> &dyn Any {
+        self as &dyn Any
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self as &mut dyn Any
+    }
+    fn initialize(&mut self, _: &mut ObjectPool) {}
+
+    fn get_children(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Constructor or value of variant by name
+    fn load_at(&self, m: &mut Machine, args: Vec<Value>, rindex: usize) {
+        let name = key_name(m, args[1]);
+        match name.as_ref().and_then(|name| self.variants.get(name)) {
+            Some(variant) => m.set(rindex, *variant),
+            None => {
+                let key = args[1].to_String(m);
+                let e = format!("{} has no variant `{}`", self.name, key);
+                m.raise(VmError::RuntimeError(e));
+            }
+        }
+    }
+}
+
+/// Constructor of variant with associated values
+#[derive(Debug)]
+pub struct Constructor(pub Arc<Variant>);
+
+impl ObjectAddon for Constructor {
+    fn typename(&self, _: &mut Machine) -> String {
+        self.0.typename()
+    }
+
+    fn to_String(&self, _: &mut Machine) -> String {
+        format!("{}({})", self.0.typename(), self.0.params.join(", "))
+    }
+}
+
+impl Object for Constructor {
+    fn as_any(&self) -> &dyn Any {
+        self as &dyn Any
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self as &mut dyn Any
+    }
+    fn initialize(&mut self, _: &mut ObjectPool) {}
+
+    fn get_children(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Value of variant, every parameter must be given
+    fn call(&self, m: &mut Machine, args: &[Value]) -> Value {
+        let values = &args[1..];
+        if values.len() != self.0.params.len() {
+            let e = format!(
+                "{} expects {} values, found {}",
+                self.0.typename(),
+                self.0.params.len(),
+                values.len()
+            );
+            m.raise(VmError::RuntimeError(e));
+            return Value::Null;
+        }
+        let value = EnumValue {
+            variant: self.0.clone(),
+            values: values.to_vec(),
+        };
+        Value::Object(m.pool.allocate(Box::new(value)))
+    }
+}
+
+/// Value of variant with its associated values in order of parameters
+#[derive(Debug)]
+pub struct EnumValue {
+    pub variant: Arc<Variant>,
+    pub values: Vec<Value>,
+}
+
+impl ObjectAddon for EnumValue {
+    fn typename(&self, _: &mut Machine) -> String {
+        self.variant.typename()
+    }
+
+    fn to_String(&self, m: &mut Machine) -> String {
+        if self.variant.params.is_empty() {
+            return self.variant.typename();
+        }
+        let values: Vec<String> = self.values.iter().map(|v| v.to_String(m)).collect();
+        format!("{}({})", self.variant.typename(), values.join(", "))
+    }
+
+    fn isa(&self, s: String, _: &mut Machine) -> bool {
+        s == self.variant.typename() || s == self.variant.enum_name
+    }
+}
+
+impl Object for EnumValue {
+    fn as_any(&self) -> &dyn Any {
+        self as &dyn Any
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self as &mut dyn Any
+    }
+    fn initialize(&mut self, _: &mut ObjectPool) {}
+
+    fn get_children(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Associated value by name of parameter or by position
+    fn load_at(&self, m: &mut Machine, args: Vec<Value>, rindex: usize) {
+        let index = match args[1] {
+            Value::Int(i) => usize::try_from(i).ok().filter(|i| *i < self.values.len()),
+            Value::Long(l) => usize::try_from(l).ok().filter(|i| *i < self.values.len()),
+            key => key_name(m, key)
+                .and_then(|name| self.variant.params.iter().position(|p| *p == name)),
+        };
+        match index {
+            Some(index) => m.set(rindex, self.values[index]),
+            None => {
+                let key = args[1].to_String(m);
+                let e = format!("{} has no field `{}`", self.variant.typename(), key);
+                m.raise(VmError::RuntimeError(e));
+            }
+        }
+    }
+
+    /// Associated values can't be changed
+    fn store_at(&self, m: &mut Machine, _: Vec<Value>, _: usize) {
+        let e = format!("values of {} are immutable", self.variant.typename());
+        m.raise(VmError::RuntimeError(e));
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{parser::Position, regalloc::AllocError};
+use std::{error::Error, fmt};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    /// Function needs more registers than its frame can hold
+    TooManyRegisters { function: String, cause: AllocError },
+    /// Name is neither a global nor a local in scope
+    UndefinedName { name: String, pos: Position },
+    /// Local is declared twice in the same block
+    Redeclared { name: String, pos: Position },
+    /// Construct that parser accepts but compiler can't translate
+    Unsupported {
+        construct: &'static str,
+        pos: Position,
+    },
+    /// Left side of assignment is neither a variable nor a field
+    InvalidAssignment { pos: Position },
+    /// Right side of `.` is neither a field name nor a method call
+    InvalidAccess { pos: Position },
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::TooManyRegisters { function, cause } => {
+                write!(f, "function `{}` is too large: {}", function, cause)
+            }
+            CompileError::UndefinedName { name, pos } => {
+                write!(f, "{}: `{}` is not defined", pos, name)
+            }
+            CompileError::Redeclared { name, pos } => {
+                write!(f, "{}: `{}` is already declared in this block", pos, name)
+            }
+            CompileError::Unsupported { construct, pos } => {
+                write!(f, "{}: {} is not supported", pos, construct)
+            }
+            CompileError::InvalidAssignment { pos } => {
+                write!(f, "{}: can't assign to this expression", pos)
+            }
+            CompileError::InvalidAccess { pos } => {
+                write!(f, "{}: expected field name or method call after `.`", pos)
+            }
+        }
+    }
+}
+
+impl Error for CompileError {}
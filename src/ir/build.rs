@@ -0,0 +1,721 @@
+//! Construction of IR from AST
+//!
+//! SSA form is built directly, as described in "Simple and Efficient Construction of Static Single Assignment
+//! Form" by Braun et al.: definitions of variables are tracked per block, reading a variable in a block
+//! without definition adds a block parameter and asks predecessors for its value. Block is sealed once all
+//! its predecessors are known, parameters that turn out to be trivial are removed at the end.
+//!
+//! Every declaration of a local creates a new variable, names are resolved to variables through the stack of
+//! lexical scopes: a block or `for` statement opens a scope, its locals are out of reach after it.
+
+use super::{BinOp, BlockId, Const, Function, Inst, InstKind, Terminator, UnOp, ValueId};
+use crate::{
+    error::CompileError,
+    parser::{Expr, FnDef, MatchArm, Op, Pattern, Position, Stmt},
+};
+use std::collections::{HashMap, HashSet};
+
+/// Build IR of `fun`, `globals` maps names of globals to their ids
+///
+/// Translation goes on after an error, so every error in function is reported
+pub fn build(
+    name: &str,
+    fun: &FnDef,
+    globals: &HashMap<String, usize>,
+) -> Result<Function, Vec<CompileError>> {
+    let mut builder = Builder::new(name, fun.params.len(), globals);
+    let entry = builder.func.entry();
+    builder.seal(entry);
+    builder.scopes.push(HashMap::new());
+    for (i, param) in fun.params.iter().enumerate() {
+        let value = builder.func.block(entry).params[i + 1];
+        let var = builder.declare(param, fun.name.position());
+        builder.write_var(var, entry, value);
+    }
+    builder.translate_stmt(&fun.body);
+    if builder.func.block(builder.current).term.is_none() {
+        builder.terminate(Terminator::Return(None));
+    }
+    if !builder.errors.is_empty() {
+        return Err(builder.errors);
+    }
+    Ok(builder.finish())
+}
+
+/// Local variable, one for every declaration
+type Var = usize;
+
+struct Builder<'a> {
+    func: Function,
+    globals: &'a HashMap<String, usize>,
+    /// Block instructions are appended to
+    current: BlockId,
+    /// Variable of every local name declared in every open scope, innermost scope is the last
+    scopes: Vec<HashMap<String, Var>>,
+    /// Count of variables
+    nvars: usize,
+    /// Definition of every variable at the end of every block
+    defs: Vec<HashMap<Var, ValueId>>,
+    preds: Vec<Vec<BlockId>>,
+    sealed: Vec<bool>,
+    /// Parameters added to unsealed blocks: their arguments are filled when block is sealed
+    incomplete: Vec<Vec<(Var, ValueId)>>,
+    errors: Vec<CompileError>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(name: &str, argc: usize, globals: &'a HashMap<String, usize>) -> Builder<'a> {
+        Builder {
+            func: Function::new(name, argc),
+            globals,
+            current: BlockId(0),
+            scopes: vec![],
+            nvars: 0,
+            defs: vec![HashMap::new()],
+            preds: vec![vec![]],
+            sealed: vec![false],
+            incomplete: vec![vec![]],
+            errors: vec![],
+        }
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.defs.push(HashMap::new());
+        self.preds.push(vec![]);
+        self.sealed.push(false);
+        self.incomplete.push(vec![]);
+        self.func.new_block()
+    }
+
+    /// Code after `return` goes into a new unreachable block
+    fn ensure_open(&mut self) {
+        if self.func.block(self.current).term.is_some() {
+            let block = self.new_block();
+            self.seal(block);
+            self.current = block;
+        }
+    }
+
+    fn emit(&mut self, kind: InstKind) -> ValueId {
+        let value = self.func.new_value();
+        self.ensure_open();
+        self.func.block_mut(self.current).insts.push(Inst {
+            value: Some(value),
+            kind,
+        });
+        value
+    }
+
+    fn emit_store(&mut self, kind: InstKind) {
+        self.ensure_open();
+        self.func
+            .block_mut(self.current)
+            .insts
+            .push(Inst { value: None, kind });
+    }
+
+    fn constant(&mut self, c: Const) -> ValueId {
+        self.emit(InstKind::Const(c))
+    }
+
+    /// Record `error`, translation continues with null in place of failed expression
+    fn error(&mut self, error: CompileError) -> ValueId {
+        self.errors.push(error);
+        self.constant(Const::Null)
+    }
+
+    /// End current block with `term`
+    fn terminate(&mut self, term: Terminator) {
+        self.ensure_open();
+        for succ in term.successors() {
+            self.preds[succ.0].push(self.current);
+        }
+        self.func.block_mut(self.current).term = Some(term);
+    }
+
+    fn jump(&mut self, target: BlockId) {
+        self.terminate(Terminator::Jump(target, vec![]));
+    }
+
+    fn seal(&mut self, block: BlockId) {
+        let incomplete = std::mem::take(&mut self.incomplete[block.0]);
+        for (var, param) in incomplete {
+            self.add_param_operands(var, block, param);
+        }
+        self.sealed[block.0] = true;
+    }
+
+    /// New variable for local `name` in innermost scope
+    fn declare(&mut self, name: &str, pos: Position) -> Var {
+        let var = self.nvars;
+        self.nvars += 1;
+        let scope = self.scopes.last_mut().unwrap();
+        if scope.insert(name.to_owned(), var).is_some() {
+            self.errors.push(CompileError::Redeclared {
+                name: name.to_owned(),
+                pos,
+            });
+        }
+        var
+    }
+
+    /// Variable that local `name` refers to in current scope
+    fn lookup(&self, name: &str) -> Option<Var> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).cloned())
+    }
+
+    /// Run `f` in new scope
+    fn scoped<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.scopes.push(HashMap::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    fn write_var(&mut self, var: Var, block: BlockId, value: ValueId) {
+        self.defs[block.0].insert(var, value);
+    }
+
+    fn read_var(&mut self, var: Var, block: BlockId) -> ValueId {
+        if let Some(value) = self.defs[block.0].get(&var) {
+            return *value;
+        }
+        let value = if !self.sealed[block.0] {
+            let param = self.add_param(block);
+            self.incomplete[block.0].push((var, param));
+            param
+        } else if self.preds[block.0].len() == 1 {
+            let pred = self.preds[block.0][0];
+            self.read_var(var, pred)
+        } else if self.preds[block.0].is_empty() {
+            // variable without value in entry or unreachable block
+            let value = self.func.new_value();
+            self.func.block_mut(block).insts.insert(
+                0,
+                Inst {
+                    value: Some(value),
+                    kind: InstKind::Const(Const::Null),
+                },
+            );
+            value
+        } else {
+            let param = self.add_param(block);
+            self.write_var(var, block, param);
+            self.add_param_operands(var, block, param);
+            param
+        };
+        self.write_var(var, block, value);
+        value
+    }
+
+    fn add_param(&mut self, block: BlockId) -> ValueId {
+        let param = self.func.new_value();
+        self.func.block_mut(block).params.push(param);
+        param
+    }
+
+    /// Pass value of `var` from every predecessor of `block` as argument for `param`
+    fn add_param_operands(&mut self, var: Var, block: BlockId, param: ValueId) {
+        let index = self
+            .func
+            .block(block)
+            .params
+            .iter()
+            .position(|p| *p == param)
+            .unwrap();
+        for pred in self.preds[block.0].clone() {
+            let value = self.read_var(var, pred);
+            match self.func.block_mut(pred).term {
+                Some(Terminator::Jump(target, ref mut args)) if target == block => {
+                    assert_eq!(index, args.len(), "arguments of {} are out of order", block);
+                    args.push(value);
+                }
+                ref term => panic!(
+                    "{} takes parameters but {} ends with {:?}",
+                    block, pred, term
+                ),
+            }
+        }
+    }
+
+    /// Replace parameters that always receive the same value by that value
+    fn finish(mut self) -> Function {
+        let mut subst: HashMap<ValueId, ValueId> = HashMap::new();
+        fn resolve(subst: &HashMap<ValueId, ValueId>, mut v: ValueId) -> ValueId {
+            while let Some(next) = subst.get(&v) {
+                v = *next;
+            }
+            v
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let preds = self.func.predecessors();
+            for (b, preds) in preds.iter().enumerate().skip(1) {
+                let mut i = 0;
+                while i < self.func.blocks[b].params.len() {
+                    let param = self.func.blocks[b].params[i];
+                    let mut incoming = HashSet::new();
+                    for pred in preds.iter() {
+                        if let Some(Terminator::Jump(_, args)) = &self.func.block(*pred).term {
+                            let arg = resolve(&subst, args[i]);
+                            if arg != param {
+                                incoming.insert(arg);
+                            }
+                        }
+                    }
+                    if incoming.len() != 1 {
+                        i += 1;
+                        continue;
+                    }
+                    subst.insert(param, incoming.into_iter().next().unwrap());
+                    self.func.blocks[b].params.remove(i);
+                    for pred in preds.iter() {
+                        if let Some(Terminator::Jump(_, args)) =
+                            &mut self.func.block_mut(*pred).term
+                        {
+                            args.remove(i);
+                        }
+                    }
+                    changed = true;
+                }
+            }
+        }
+
+        for block in self.func.blocks.iter_mut() {
+            for inst in block.insts.iter_mut() {
+                for v in inst.kind.operands_mut() {
+                    *v = resolve(&subst, *v);
+                }
+            }
+            for v in block.term.iter_mut().flat_map(|t| t.operands_mut()) {
+                *v = resolve(&subst, *v);
+            }
+        }
+        self.func
+    }
+
+    fn translate_stmt(&mut self, s: &Stmt) {
+        self.ensure_open();
+        match s {
+            Stmt::If(condition, then) => {
+                let cond = self.translate_expr(condition);
+                let (then_block, else_block, join) =
+                    (self.new_block(), self.new_block(), self.new_block());
+                self.branch(cond, then_block, else_block);
+
+                self.current = then_block;
+                self.translate_stmt(then);
+                self.jump(join);
+                self.current = else_block;
+                self.jump(join);
+                self.seal(join);
+                self.current = join;
+            }
+
+            Stmt::IfElse(condition, if_true, if_false) => {
+                let cond = self.translate_expr(condition);
+                let (then_block, else_block, join) =
+                    (self.new_block(), self.new_block(), self.new_block());
+                self.branch(cond, then_block, else_block);
+
+                self.current = then_block;
+                self.translate_stmt(if_true);
+                self.jump(join);
+                self.current = else_block;
+                self.translate_stmt(if_false);
+                self.jump(join);
+                self.seal(join);
+                self.current = join;
+            }
+
+            Stmt::For(value, condition, expr, block) => self.scoped(|this| {
+                this.translate_stmt(value);
+                this.translate_loop(|this| this.translate_expr(condition), |this| {
+                    this.translate_stmt(block);
+                    this.translate_expr(expr);
+                });
+            }),
+
+            Stmt::While(condition, block) => {
+                self.translate_loop(
+                    |this| this.translate_expr(condition),
+                    |this| this.translate_stmt(block),
+                );
+            }
+
+            Stmt::ForIn(name, iterable, block, pos) => {
+                let iterable = self.translate_expr(iterable);
+                let it = self.call_global("__iter__", vec![iterable], *pos);
+                self.translate_loop(
+                    |this| this.call_global("__next__", vec![it], *pos),
+                    |this| {
+                        this.scoped(|this| {
+                            let value = this.call_global("__value__", vec![it], *pos);
+                            let var = this.declare(name, *pos);
+                            this.write_var(var, this.current, value);
+                            this.translate_stmt(block);
+                        })
+                    },
+                );
+            }
+
+            Stmt::Var(name, expr, pos) => {
+                let value = match expr {
+                    Some(expr) => self.translate_expr(expr),
+                    None => self.constant(Const::Null),
+                };
+                let var = self.declare(name, *pos);
+                self.write_var(var, self.current, value);
+            }
+            Stmt::Return => {
+                self.terminate(Terminator::Return(None));
+            }
+            Stmt::ReturnWithVal(val) => {
+                let value = self.translate_expr(val);
+                self.terminate(Terminator::Return(Some(value)));
+            }
+            Stmt::Yield(val) => {
+                let value = self.translate_expr(val);
+                self.emit_store(InstKind::Yield(value));
+                self.func.generator = true;
+            }
+
+            Stmt::Block(body) => self.scoped(|this| {
+                for stmt in body.iter() {
+                    this.translate_stmt(stmt);
+                }
+            }),
+            Stmt::Expr(expr) => {
+                self.translate_expr(expr);
+            }
+            Stmt::Match(subject, arms, pos) => self.translate_match(subject, arms, *pos),
+            Stmt::Loop(_, pos) => self.unsupported("`loop`", *pos),
+            Stmt::Label(_, pos) => self.unsupported("label", *pos),
+            Stmt::Goto(_, pos) => self.unsupported("`goto`", *pos),
+            Stmt::Break(pos) => self.unsupported("`break`", *pos),
+        }
+    }
+
+    fn unsupported(&mut self, construct: &'static str, pos: Position) {
+        self.errors
+            .push(CompileError::Unsupported { construct, pos });
+    }
+
+    /// Branch to `then` or `otherwise`, both blocks have no other predecessors and are sealed
+    fn branch(&mut self, cond: ValueId, then: BlockId, otherwise: BlockId) {
+        self.terminate(Terminator::Branch(cond, then, otherwise));
+        self.seal(then);
+        self.seal(otherwise);
+    }
+
+    /// Go on in new block if `cond` is true, jump to `fail` otherwise
+    ///
+    /// `fail` may get many predecessors, so both targets of branch are fresh blocks
+    fn check(&mut self, cond: ValueId, fail: BlockId) {
+        let (ok, not_ok) = (self.new_block(), self.new_block());
+        self.branch(cond, ok, not_ok);
+        self.current = not_ok;
+        self.jump(fail);
+        self.current = ok;
+    }
+
+    /// Arms are tried in order, `__no_match__` raises runtime error if none of them matches
+    fn translate_match(&mut self, subject: &Expr, arms: &[MatchArm], pos: Position) {
+        let subject = self.translate_expr(subject);
+        let end = self.new_block();
+        for arm in arms.iter() {
+            let next = self.new_block();
+            self.scoped(|this| {
+                this.translate_pattern(&arm.pattern, subject, next);
+                if let Some(guard) = &arm.guard {
+                    let cond = this.translate_expr(guard);
+                    this.check(cond, next);
+                }
+                this.translate_stmt(&arm.body);
+            });
+            self.jump(end);
+            self.seal(next);
+            self.current = next;
+        }
+        self.call_global("__no_match__", vec![subject], pos);
+        self.jump(end);
+        self.seal(end);
+        self.current = end;
+    }
+
+    /// Check that `value` matches `pattern` and bind its names in current scope, jump to `fail` if it doesn't
+    fn translate_pattern(&mut self, pattern: &Pattern, value: ValueId, fail: BlockId) {
+        match pattern {
+            Pattern::Wildcard => (),
+            Pattern::Bind(name, pos) => {
+                let var = self.declare(name, *pos);
+                self.write_var(var, self.current, value);
+            }
+            Pattern::Literal(expr) => {
+                let constant = self.translate_expr(expr);
+                let cond = self.emit(InstKind::Binary(BinOp::Eq, value, constant));
+                self.check(cond, fail);
+            }
+            Pattern::Type(name, ty, pos) => {
+                let ty = self.translate_expr(ty);
+                let cond = self.emit(InstKind::Binary(BinOp::Isa, value, ty));
+                self.check(cond, fail);
+                if let Some(name) = name {
+                    let var = self.declare(name, *pos);
+                    self.write_var(var, self.current, value);
+                }
+            }
+            Pattern::Array(elements, pos) => {
+                self.translate_elements(elements, value, false, fail, *pos);
+            }
+            Pattern::Variant(variant, values, pos) => {
+                let variant = self.translate_expr(variant);
+                let cond = self.emit(InstKind::Binary(BinOp::Isa, value, variant));
+                self.check(cond, fail);
+                if let Some(values) = values {
+                    self.translate_elements(values, value, true, fail, *pos);
+                }
+            }
+        }
+    }
+
+    /// Match elements of array or associated values of enum `variant` with `patterns`
+    fn translate_elements(
+        &mut self,
+        patterns: &[Pattern],
+        value: ValueId,
+        variant: bool,
+        fail: BlockId,
+        pos: Position,
+    ) {
+        let size = self.constant(Const::Long(patterns.len() as i64));
+        let variant = self.constant(Const::Bool(variant));
+        let cond = self.call_global("__match_size__", vec![value, size, variant], pos);
+        self.check(cond, fail);
+        for (i, pattern) in patterns.iter().enumerate() {
+            let index = self.constant(Const::Long(i as i64));
+            let element = self.emit(InstKind::LoadAt(value, index));
+            self.translate_pattern(pattern, element, fail);
+        }
+    }
+
+    /// Loop which runs `body` while value of `condition` is true, `condition` is evaluated before each iteration
+    fn translate_loop(
+        &mut self,
+        condition: impl FnOnce(&mut Self) -> ValueId,
+        body: impl FnOnce(&mut Self),
+    ) {
+        let header = self.new_block();
+        self.jump(header);
+        self.current = header;
+
+        let cond = condition(self);
+        let (body_block, end) = (self.new_block(), self.new_block());
+        self.branch(cond, body_block, end);
+
+        self.current = body_block;
+        body(self);
+        self.jump(header);
+        self.seal(header);
+        self.current = end;
+    }
+
+    fn translate_expr(&mut self, expr: &Expr) -> ValueId {
+        self.ensure_open();
+        match expr {
+            Expr::IntConst(int, _) => self.constant(Const::Long(*int)),
+            Expr::FloatConst(float, _) => self.constant(Const::Double(*float)),
+            Expr::StringConst(s, _) => self.constant(Const::Str(s.to_string())),
+            Expr::CharConst(c, _) => self.constant(Const::Char(*c)),
+            Expr::True(_) => self.constant(Const::Bool(true)),
+            Expr::False(_) => self.constant(Const::Bool(false)),
+            Expr::Unit(_) => self.constant(Const::Null),
+            Expr::This(_) => self.func.block(self.func.entry()).params[0],
+
+            Expr::FnCall(fname, args, pos) | Expr::New(fname, args, pos) => {
+                self.translate_call(fname, args, *pos)
+            }
+            Expr::Array(arr_expr, pos) => self.translate_call("__new_array__", arr_expr, *pos),
+            Expr::Map(entries, pos) => {
+                let args: Vec<Expr> = entries
+                    .iter()
+                    .flat_map(|(k, v)| vec![k.clone(), v.clone()])
+                    .collect();
+                self.translate_call("__new_map__", &args, *pos)
+            }
+            Expr::Interpolation(parts, pos) => self.translate_call("__interpolate__", parts, *pos),
+
+            Expr::Op(op, e1, e2, pos) => self.translate_operation(op, e1, e2, *pos),
+
+            Expr::Identifier(name, pos) => self.translate_name(name, *pos),
+
+            Expr::Assignment(e1, e2, pos) => match &**e1 {
+                Expr::Identifier(name, name_pos) => {
+                    let value = self.translate_expr(e2);
+                    if let Some(var) = self.lookup(name) {
+                        self.write_var(var, self.current, value);
+                    } else if let Some(id) = self.globals.get(name) {
+                        self.emit_store(InstKind::StoreGlobal(*id, value));
+                    } else {
+                        self.error(CompileError::UndefinedName {
+                            name: name.to_string(),
+                            pos: *name_pos,
+                        });
+                    }
+                    value
+                }
+                Expr::Op(Op::Access, this, fname, access_pos) => {
+                    let key = match &**fname {
+                        Expr::Identifier(n, _) => self.constant(Const::Str(n.to_string())),
+                        _ => self.error(CompileError::InvalidAccess { pos: *access_pos }),
+                    };
+                    let value = self.translate_expr(e2);
+                    let this = self.translate_expr(this);
+                    self.emit_store(InstKind::StoreAt(this, key, value));
+                    value
+                }
+                Expr::Index(name, idx, index_pos) => {
+                    let value = self.translate_expr(e2);
+                    let target = self.translate_name(name, *index_pos);
+                    let index = self.translate_expr(idx);
+                    self.emit_store(InstKind::StoreAt(target, index, value));
+                    value
+                }
+                _ => {
+                    self.translate_expr(e2);
+                    self.error(CompileError::InvalidAssignment { pos: *pos })
+                }
+            },
+            Expr::Index(name, idx, pos) => {
+                let target = self.translate_name(name, *pos);
+                let index = self.translate_expr(idx);
+                self.emit(InstKind::LoadAt(target, index))
+            }
+            Expr::Dot(_, _, pos) => self.error(CompileError::Unsupported {
+                construct: "`.` expression",
+                pos: *pos,
+            }),
+        }
+    }
+
+    /// Value of local variable `name` or of global if there is no such local in scope
+    fn translate_name(&mut self, name: &str, pos: Position) -> ValueId {
+        if let Some(var) = self.lookup(name) {
+            return self.read_var(var, self.current);
+        }
+        match self.globals.get(name) {
+            Some(id) => self.emit(InstKind::LoadGlobal(*id)),
+            None => self.error(CompileError::UndefinedName {
+                name: name.to_string(),
+                pos,
+            }),
+        }
+    }
+
+    fn translate_operation(&mut self, op: &Op, e1: &Expr, e2: &Expr, pos: Position) -> ValueId {
+        let op = match op {
+            Op::Access => {
+                return match e2 {
+                    Expr::Identifier(field, _) => {
+                        let this = self.translate_expr(e1);
+                        let key = self.constant(Const::Str(field.to_string()));
+                        self.emit(InstKind::LoadAt(this, key))
+                    }
+                    Expr::FnCall(fname, args, _) => {
+                        let this = self.translate_expr(e1);
+                        let key = self.constant(Const::Str(fname.to_string()));
+                        let callee = self.emit(InstKind::LoadAt(this, key));
+                        let args = args.iter().map(|arg| self.translate_expr(arg)).collect();
+                        self.emit(InstKind::Call(callee, this, args))
+                    }
+                    _ => {
+                        self.translate_expr(e1);
+                        self.error(CompileError::InvalidAccess { pos })
+                    }
+                };
+            }
+            Op::Not | Op::Neg | Op::BitNot => {
+                let op = match op {
+                    Op::Not => UnOp::Not,
+                    Op::Neg => UnOp::Neg,
+                    _ => UnOp::BitNot,
+                };
+                let value = self.translate_expr(e1);
+                return self.emit(InstKind::Unary(op, value));
+            }
+            Op::And => return self.translate_logical(true, e1, e2),
+            Op::Or => return self.translate_logical(false, e1, e2),
+            Op::Add => BinOp::Add,
+            Op::Sub => BinOp::Sub,
+            Op::Mul => BinOp::Mul,
+            Op::Div => BinOp::Div,
+            Op::IntDiv => BinOp::IntDiv,
+            Op::Rem => BinOp::Rem,
+            Op::Pow => BinOp::Pow,
+            Op::Eq => BinOp::Eq,
+            Op::Ne => BinOp::Ne,
+            Op::Gt => BinOp::Gt,
+            Op::Lt => BinOp::Lt,
+            Op::Ge => BinOp::Ge,
+            Op::Le => BinOp::Le,
+            Op::BitAnd => BinOp::BitAnd,
+            Op::BitOr => BinOp::BitOr,
+            Op::BitXor => BinOp::BitXor,
+            Op::Shl => BinOp::Shl,
+            Op::Shr => BinOp::Shr,
+            Op::Isa => BinOp::Isa,
+            Op::Range | Op::RangeInclusive => {
+                let start = self.translate_expr(e1);
+                let end = self.translate_expr(e2);
+                let inclusive = self.constant(Const::Bool(*op == Op::RangeInclusive));
+                return self.call_global("__range__", vec![start, end, inclusive], pos);
+            }
+        };
+        let a = self.translate_expr(e1);
+        let b = self.translate_expr(e2);
+        self.emit(InstKind::Binary(op, a, b))
+    }
+
+    /// `&&` if `and` is true and `||` otherwise
+    ///
+    /// Right operand is evaluated only if left one doesn't decide result, value of expression is the operand
+    /// evaluated last. Operands of any type are allowed, those which `!` turns into true are false
+    fn translate_logical(&mut self, and: bool, e1: &Expr, e2: &Expr) -> ValueId {
+        let left = self.translate_expr(e1);
+        let falsy = self.emit(InstKind::Unary(UnOp::Not, left));
+        let (right_block, short, join) = (self.new_block(), self.new_block(), self.new_block());
+        let result = self.add_param(join);
+        if and {
+            self.branch(falsy, short, right_block);
+        } else {
+            self.branch(falsy, right_block, short);
+        }
+
+        self.current = right_block;
+        let right = self.translate_expr(e2);
+        self.terminate(Terminator::Jump(join, vec![right]));
+        self.current = short;
+        self.terminate(Terminator::Jump(join, vec![left]));
+        self.seal(join);
+        self.current = join;
+        result
+    }
+
+    /// Call global `name` with already translated `args`
+    fn call_global(&mut self, name: &str, args: Vec<ValueId>, pos: Position) -> ValueId {
+        let callee = self.translate_name(name, pos);
+        self.emit(InstKind::Call(callee, callee, args))
+    }
+
+    /// Call global or local `name` with `args`, `this` for a plain call is the callee itself
+    fn translate_call(&mut self, name: &str, args: &[Expr], pos: Position) -> ValueId {
+        let callee = self.translate_name(name, pos);
+        let args = args.iter().map(|arg| self.translate_expr(arg)).collect();
+        self.emit(InstKind::Call(callee, callee, args))
+    }
+}
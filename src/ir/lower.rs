@@ -0,0 +1,401 @@
+//! Lowering of IR to VM instructions
+//!
+//! Every value gets a virtual register of `FunctionBuilder`, physical registers are assigned by
+//! `regalloc`. Blocks are laid out in reverse postorder, so jumps to the next block are omitted.
+//!
+//! Lowering also picks instructions that fit best:
+//! - branch on comparison used only by this branch becomes fused compare-and-jump
+//! - addition of integer constant becomes `AddI`, or `Inc` when it updates register in place
+//! - call which result is returned becomes `TailCall`
+//! - value used only as call argument or as jump argument is computed right into register that needs it
+//!
+//! Null constants emit nothing: their registers are never written and hold null of cleared frame.
+
+use super::{BinOp, BlockId, Const, Function, InstKind, Terminator, UnOp, ValueId};
+use crate::{ircode::FunctionBuilder, regalloc::AllocError};
+use jazz_vm::{
+    opcodes::{Cmp, Instruction},
+    optimizer::remove,
+};
+use std::collections::HashMap;
+
+/// Second operand of fused compare-and-jump
+enum Operand {
+    Reg(ValueId),
+    Imm(i64),
+}
+
+struct Lowering<'a> {
+    func: &'a Function,
+    builder: FunctionBuilder,
+    /// Virtual register of every value
+    regs: HashMap<ValueId, usize>,
+    /// Block and index of instruction that defines value
+    defs: HashMap<ValueId, (BlockId, usize)>,
+    uses: HashMap<ValueId, usize>,
+}
+
+impl<'a> Lowering<'a> {
+    fn kind(&self, v: ValueId) -> Option<&'a InstKind> {
+        let func = self.func;
+        self.defs
+            .get(&v)
+            .map(|&(b, i)| &func.block(b).insts[i].kind)
+    }
+
+    fn long_const(&self, v: ValueId) -> Option<i64> {
+        match self.kind(v) {
+            Some(InstKind::Const(Const::Long(l))) => Some(*l),
+            _ => None,
+        }
+    }
+
+    fn uses(&self, v: ValueId) -> usize {
+        self.uses.get(&v).cloned().unwrap_or(0)
+    }
+
+    fn unuse(&mut self, v: ValueId) {
+        if let Some(n) = self.uses.get_mut(&v) {
+            *n -= 1;
+        }
+    }
+
+    fn reg(&mut self, v: ValueId) -> usize {
+        if let Some(r) = self.regs.get(&v) {
+            return *r;
+        }
+        let r = self.builder.register_new();
+        self.regs.insert(v, r);
+        r
+    }
+
+    /// Whether `v` can be computed right into register of its only user in `block`
+    fn can_place(&self, v: ValueId, block: BlockId, uses: usize) -> bool {
+        match (self.defs.get(&v), self.kind(v)) {
+            (_, Some(InstKind::Const(Const::Null))) => false,
+            (Some(&(b, _)), Some(_)) => {
+                b == block && self.uses(v) == uses && !self.regs.contains_key(&v)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Blocks reachable from entry in reverse postorder, then-branch goes right after the branch
+fn layout(func: &Function) -> Vec<BlockId> {
+    let mut visited = vec![false; func.blocks.len()];
+    let mut order = vec![];
+    let mut stack = vec![(func.entry(), false)];
+    while let Some((b, done)) = stack.pop() {
+        if done {
+            order.push(b);
+            continue;
+        }
+        if visited[b.0] {
+            continue;
+        }
+        visited[b.0] = true;
+        stack.push((b, true));
+        // successors pushed last are visited first, so the first successor ends up first in reverse postorder
+        let succs = func.block(b).term.iter().flat_map(|t| t.successors());
+        for succ in succs {
+            if !visited[succ.0] {
+                stack.push((succ, false));
+            }
+        }
+    }
+    order.reverse();
+    order
+}
+
+/// Emit moves `(dest, src)` that happen at once
+fn parallel_move(builder: &mut FunctionBuilder, mut moves: Vec<(usize, usize)>) {
+    moves.retain(|&(d, s)| d != s);
+    while !moves.is_empty() {
+        let ready = (0..moves.len()).find(|&i| moves.iter().all(|&(_, s)| s != moves[i].0));
+        match ready {
+            Some(i) => {
+                let (d, s) = moves.remove(i);
+                builder.push_op(Instruction::Move(d, s));
+            }
+            None => {
+                // every destination is read by another move: save one of them
+                let d = moves[0].0;
+                let tmp = builder.register_new();
+                builder.push_op(Instruction::Move(tmp, d));
+                for m in moves.iter_mut().filter(|m| m.1 == d) {
+                    m.1 = tmp;
+                }
+            }
+        }
+    }
+}
+
+/// VM code of `func`
+pub fn lower(func: &Function) -> Result<Vec<Instruction>, AllocError> {
+    let order = layout(func);
+    let mut l = Lowering {
+        func,
+        builder: FunctionBuilder::new(func.argc),
+        regs: HashMap::new(),
+        defs: HashMap::new(),
+        uses: HashMap::new(),
+    };
+
+    for &b in order.iter() {
+        let block = func.block(b);
+        for (i, inst) in block.insts.iter().enumerate() {
+            if let Some(v) = inst.value {
+                l.defs.insert(v, (b, i));
+            }
+            for op in inst.kind.operands() {
+                *l.uses.entry(op).or_insert(0) += 1;
+            }
+        }
+        for op in block.term.iter().flat_map(|t| t.operands()) {
+            *l.uses.entry(op).or_insert(0) += 1;
+        }
+    }
+
+    let entry = func.block(func.entry());
+    l.regs.insert(entry.params[0], 0);
+    for param in entry.params[1..].iter() {
+        let r = l.builder.register_new();
+        l.regs.insert(*param, r);
+    }
+
+    // fused compare-and-jump and `AddI`
+    let mut fused: HashMap<BlockId, (Cmp, ValueId, Operand)> = HashMap::new();
+    let mut addi: HashMap<ValueId, (ValueId, i64)> = HashMap::new();
+    for &b in order.iter() {
+        if let Some(Terminator::Branch(cond, _, _)) = func.block(b).term {
+            if let (Some(&(db, _)), Some(&InstKind::Binary(op, x, y))) =
+                (l.defs.get(&cond), l.kind(cond))
+            {
+                if let (true, 1, Some(cmp)) = (db == b, l.uses(cond), op.cmp()) {
+                    let operand = match l.long_const(y) {
+                        Some(imm) => {
+                            l.unuse(y);
+                            Operand::Imm(imm)
+                        }
+                        None => Operand::Reg(y),
+                    };
+                    l.unuse(cond);
+                    fused.insert(b, (cmp, x, operand));
+                }
+            }
+        }
+        for inst in func.block(b).insts.iter() {
+            if let (Some(v), InstKind::Binary(op, x, y)) = (inst.value, &inst.kind) {
+                let imm = match (op, l.long_const(*y)) {
+                    (BinOp::Add, Some(imm)) => Some(imm),
+                    (BinOp::Sub, Some(imm)) => imm.checked_neg(),
+                    _ => None,
+                };
+                if let Some(imm) = imm {
+                    l.unuse(*y);
+                    addi.insert(v, (*x, imm));
+                }
+            }
+        }
+    }
+
+    // call windows
+    let mut windows: HashMap<(BlockId, usize), usize> = HashMap::new();
+    for &b in order.iter() {
+        for (i, inst) in func.block(b).insts.iter().enumerate() {
+            if let InstKind::Call(callee, this, args) = &inst.kind {
+                let base = l.builder.register_window(args.len() + 2);
+                windows.insert((b, i), base);
+                let callee_uses = if callee == this { 2 } else { 1 };
+                if l.can_place(*callee, b, callee_uses) {
+                    l.regs.insert(*callee, base);
+                }
+                if callee != this && l.can_place(*this, b, 1) {
+                    l.regs.insert(*this, base + 1);
+                }
+                for (k, arg) in args.iter().enumerate() {
+                    if l.can_place(*arg, b, 1) {
+                        l.regs.insert(*arg, base + 2 + k);
+                    }
+                }
+            }
+        }
+    }
+
+    // arguments of jumps computed right into parameters of target
+    for &b in order.iter() {
+        let block = func.block(b);
+        if let Some(Terminator::Jump(target, args)) = &block.term {
+            for (arg, param) in args.iter().zip(func.block(*target).params.iter()) {
+                if !l.can_place(*arg, b, 1) || args.contains(param) {
+                    continue;
+                }
+                let (_, def) = l.defs[arg];
+                let read_later = block.insts[def + 1..]
+                    .iter()
+                    .any(|inst| inst.kind.operands().contains(param));
+                if !read_later {
+                    let r = l.reg(*param);
+                    l.regs.insert(*arg, r);
+                }
+            }
+        }
+    }
+
+    let labels: HashMap<BlockId, usize> =
+        order.iter().map(|&b| (b, l.builder.new_label())).collect();
+    let tail = |b: BlockId| match &func.block(b).term {
+        Some(Terminator::Return(Some(v))) => match func.block(b).insts.last() {
+            Some(inst) => inst.value == Some(*v) && matches!(inst.kind, InstKind::Call(_, _, _)),
+            None => false,
+        },
+        _ => false,
+    };
+
+    for (pos, &b) in order.iter().enumerate() {
+        let next = order.get(pos + 1).cloned();
+        let block = func.block(b);
+        l.builder.label_here(labels[&b]);
+
+        for (i, inst) in block.insts.iter().enumerate() {
+            let value = inst.value;
+            if let Some(v) = value {
+                let pure = matches!(inst.kind, InstKind::Const(_) | InstKind::LoadGlobal(_));
+                if (pure
+                    || fused.contains_key(&b) && block.term.as_ref().unwrap().operands() == [v])
+                    && l.uses(v) == 0
+                {
+                    continue;
+                }
+            }
+            let dest = value.map(|v| l.reg(v)).unwrap_or(0);
+            let ins = match &inst.kind {
+                InstKind::Const(Const::Null) => continue,
+                InstKind::Const(Const::Bool(b)) => Instruction::LoadBool(dest, *b),
+                InstKind::Const(Const::Long(n)) => Instruction::LoadLong(dest, *n),
+                InstKind::Const(Const::Double(d)) => Instruction::LoadDouble(dest, *d),
+                InstKind::Const(Const::Char(c)) => Instruction::LoadChar(dest, *c),
+                InstKind::Const(Const::Str(s)) => Instruction::LoadString(dest, s.to_string()),
+                InstKind::LoadGlobal(id) => Instruction::LoadGlobal(dest, *id),
+                InstKind::StoreGlobal(id, v) => Instruction::StoreGlobal(l.reg(*v), *id),
+                InstKind::Yield(v) => Instruction::Yield(l.reg(*v)),
+                InstKind::Unary(op, v) => {
+                    let v = l.reg(*v);
+                    match op {
+                        UnOp::Not => Instruction::Not(dest, v),
+                        UnOp::Neg => Instruction::Neg(dest, v),
+                        UnOp::BitNot => Instruction::BitNot(dest, v),
+                    }
+                }
+                InstKind::LoadAt(obj, key) => Instruction::LoadAt(dest, l.reg(*obj), l.reg(*key)),
+                InstKind::StoreAt(obj, key, v) => {
+                    Instruction::StoreAt(l.reg(*v), l.reg(*obj), l.reg(*key))
+                }
+                InstKind::Binary(op, x, y) => {
+                    if let Some(&(x, imm)) = value.and_then(|v| addi.get(&v)) {
+                        Instruction::AddI(dest, l.reg(x), imm)
+                    } else {
+                        let (x, y) = (l.reg(*x), l.reg(*y));
+                        binary(*op, dest, x, y)
+                    }
+                }
+                InstKind::Call(callee, this, args) => {
+                    let base = windows[&(b, i)];
+                    let slots = [*callee, *this]
+                        .iter()
+                        .chain(args.iter())
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for (k, v) in slots.into_iter().enumerate() {
+                        let r = l.reg(v);
+                        if r != base + k {
+                            l.builder.push_op(Instruction::Move(base + k, r));
+                        }
+                    }
+                    if i + 1 == block.insts.len() && tail(b) {
+                        Instruction::TailCall(base, args.len())
+                    } else {
+                        Instruction::Call(dest, base, args.len())
+                    }
+                }
+            };
+            l.builder.push_op(ins);
+        }
+
+        match block.term.as_ref().expect("block without terminator") {
+            Terminator::Return(None) => l.builder.push_op(Instruction::Ret0),
+            Terminator::Return(Some(v)) => {
+                if !tail(b) {
+                    let r = l.reg(*v);
+                    l.builder.push_op(Instruction::Ret(r));
+                }
+            }
+            Terminator::Jump(target, args) => {
+                let moves = args
+                    .iter()
+                    .zip(func.block(*target).params.iter())
+                    .map(|(arg, param)| (l.reg(*param), l.reg(*arg)))
+                    .collect();
+                parallel_move(&mut l.builder, moves);
+                if next != Some(*target) {
+                    l.builder.push_op(Instruction::Goto(labels[target]));
+                }
+            }
+            Terminator::Branch(cond, then, otherwise) => {
+                let ins = match fused.get(&b) {
+                    Some((cmp, x, Operand::Imm(imm))) => {
+                        Instruction::JumpCmpIF(*cmp, l.reg(*x), *imm, labels[otherwise])
+                    }
+                    Some((cmp, x, Operand::Reg(y))) => {
+                        Instruction::JumpCmpF(*cmp, l.reg(*x), l.reg(*y), labels[otherwise])
+                    }
+                    None => Instruction::GotoF(l.reg(*cond), labels[otherwise]),
+                };
+                l.builder.push_op(ins);
+                if next != Some(*then) {
+                    l.builder.push_op(Instruction::Goto(labels[then]));
+                }
+            }
+        }
+    }
+
+    let mut code = l.builder.get_insts()?;
+    for ins in code.iter_mut() {
+        if let Instruction::AddI(d, r, 1) = *ins {
+            if d == r {
+                *ins = Instruction::Inc(r);
+            }
+        }
+    }
+    let keep: Vec<bool> = code
+        .iter()
+        .map(|ins| !matches!(*ins, Instruction::Move(d, s) if d == s))
+        .collect();
+    remove(&mut code, &keep);
+    Ok(code)
+}
+
+fn binary(op: BinOp, r1: usize, r2: usize, r3: usize) -> Instruction {
+    match op {
+        BinOp::Add => Instruction::Add(r1, r2, r3),
+        BinOp::Sub => Instruction::Sub(r1, r2, r3),
+        BinOp::Mul => Instruction::Mul(r1, r2, r3),
+        BinOp::Div => Instruction::Div(r1, r2, r3),
+        BinOp::IntDiv => Instruction::IntDiv(r1, r2, r3),
+        BinOp::Rem => Instruction::Rem(r1, r2, r3),
+        BinOp::Pow => Instruction::Pow(r1, r2, r3),
+        BinOp::Eq => Instruction::Eq(r1, r2, r3),
+        BinOp::Ne => Instruction::Neq(r1, r2, r3),
+        BinOp::Gt => Instruction::Gt(r1, r2, r3),
+        BinOp::Lt => Instruction::Lt(r1, r2, r3),
+        BinOp::Ge => Instruction::Ge(r1, r2, r3),
+        BinOp::Le => Instruction::Le(r1, r2, r3),
+        BinOp::BitAnd => Instruction::BitAnd(r1, r2, r3),
+        BinOp::BitOr => Instruction::BitOr(r1, r2, r3),
+        BinOp::BitXor => Instruction::BitXor(r1, r2, r3),
+        BinOp::Shl => Instruction::Shl(r1, r2, r3),
+        BinOp::Shr => Instruction::Shr(r1, r2, r3),
+        BinOp::Isa => Instruction::Isa(r1, r2, r3),
+    }
+}
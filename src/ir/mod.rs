@@ -0,0 +1,394 @@
+//! Mid-level intermediate representation
+//!
+//! Function body is a graph of basic blocks over SSA values: every value is defined once, either as a block
+//! parameter or by an instruction, and it's used only where its definition dominates. Block parameters take
+//! the place of phi nodes: every jump passes arguments for parameters of its target.
+//!
+//! `build` constructs IR from AST, `validate` checks invariants above and `lower` emits VM instructions.
+
+mod build;
+mod lower;
+mod validate;
+
+pub use self::{
+    build::build,
+    lower::lower,
+    validate::{validate, ValidateError},
+};
+
+use jazz_vm::opcodes::Cmp;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ValueId(pub usize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+impl fmt::Display for ValueId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "b{}", self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Const {
+    Null,
+    Bool(bool),
+    Long(i64),
+    Double(f64),
+    Char(char),
+    Str(String),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    IntDiv,
+    Rem,
+    Pow,
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Isa,
+}
+
+impl BinOp {
+    /// Comparison of fused compare-and-jump instruction for this operator
+    pub fn cmp(self) -> Option<Cmp> {
+        match self {
+            BinOp::Gt => Some(Cmp::Gt),
+            BinOp::Lt => Some(Cmp::Lt),
+            BinOp::Ge => Some(Cmp::Ge),
+            BinOp::Le => Some(Cmp::Le),
+            BinOp::Eq => Some(Cmp::Eq),
+            BinOp::Ne => Some(Cmp::Neq),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BinOp::Add => "add",
+            BinOp::Sub => "sub",
+            BinOp::Mul => "mul",
+            BinOp::Div => "div",
+            BinOp::IntDiv => "idiv",
+            BinOp::Rem => "rem",
+            BinOp::Pow => "pow",
+            BinOp::Eq => "eq",
+            BinOp::Ne => "ne",
+            BinOp::Gt => "gt",
+            BinOp::Lt => "lt",
+            BinOp::Ge => "ge",
+            BinOp::Le => "le",
+            BinOp::BitAnd => "bitand",
+            BinOp::BitOr => "bitor",
+            BinOp::BitXor => "bitxor",
+            BinOp::Shl => "shl",
+            BinOp::Shr => "shr",
+            BinOp::Isa => "isa",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UnOp {
+    Not,
+    Neg,
+    BitNot,
+}
+
+impl UnOp {
+    pub fn name(self) -> &'static str {
+        match self {
+            UnOp::Not => "not",
+            UnOp::Neg => "neg",
+            UnOp::BitNot => "bitnot",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InstKind {
+    Const(Const),
+    LoadGlobal(usize),
+    /// Global id and stored value
+    StoreGlobal(usize, ValueId),
+    Binary(BinOp, ValueId, ValueId),
+    Unary(UnOp, ValueId),
+    /// Object and key
+    LoadAt(ValueId, ValueId),
+    /// Object, key and stored value
+    StoreAt(ValueId, ValueId, ValueId),
+    /// Callee, `this` and arguments
+    Call(ValueId, ValueId, Vec<ValueId>),
+    /// Suspend generator passing value to its caller
+    Yield(ValueId),
+}
+
+impl InstKind {
+    /// Whether instruction defines a value
+    pub fn has_value(&self) -> bool {
+        !matches!(
+            self,
+            InstKind::StoreGlobal(_, _) | InstKind::StoreAt(_, _, _) | InstKind::Yield(_)
+        )
+    }
+
+    pub fn operands(&self) -> Vec<ValueId> {
+        match self {
+            InstKind::Const(_) | InstKind::LoadGlobal(_) => vec![],
+            InstKind::StoreGlobal(_, v) | InstKind::Unary(_, v) | InstKind::Yield(v) => vec![*v],
+            InstKind::Binary(_, a, b) | InstKind::LoadAt(a, b) => vec![*a, *b],
+            InstKind::StoreAt(a, b, c) => vec![*a, *b, *c],
+            InstKind::Call(callee, this, args) => {
+                let mut operands = vec![*callee, *this];
+                operands.extend(args.iter().cloned());
+                operands
+            }
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut ValueId> {
+        match self {
+            InstKind::Const(_) | InstKind::LoadGlobal(_) => vec![],
+            InstKind::StoreGlobal(_, v) | InstKind::Unary(_, v) | InstKind::Yield(v) => vec![v],
+            InstKind::Binary(_, a, b) | InstKind::LoadAt(a, b) => vec![a, b],
+            InstKind::StoreAt(a, b, c) => vec![a, b, c],
+            InstKind::Call(callee, this, args) => {
+                let mut operands = vec![callee, this];
+                operands.extend(args.iter_mut());
+                operands
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inst {
+    /// Defined value, `None` for stores
+    pub value: Option<ValueId>,
+    pub kind: InstKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Terminator {
+    /// Jump with arguments for parameters of target
+    Jump(BlockId, Vec<ValueId>),
+    /// Go to first block if value is true and to second one otherwise, both blocks take no parameters
+    Branch(ValueId, BlockId, BlockId),
+    Return(Option<ValueId>),
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target, _) => vec![*target],
+            Terminator::Branch(_, then, otherwise) => vec![*then, *otherwise],
+            Terminator::Return(_) => vec![],
+        }
+    }
+
+    pub fn operands(&self) -> Vec<ValueId> {
+        match self {
+            Terminator::Jump(_, args) => args.clone(),
+            Terminator::Branch(cond, _, _) => vec![*cond],
+            Terminator::Return(value) => value.iter().cloned().collect(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut ValueId> {
+        match self {
+            Terminator::Jump(_, args) => args.iter_mut().collect(),
+            Terminator::Branch(cond, _, _) => vec![cond],
+            Terminator::Return(value) => value.iter_mut().collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Block {
+    pub params: Vec<ValueId>,
+    pub insts: Vec<Inst>,
+    /// `None` only while block is built
+    pub term: Option<Terminator>,
+}
+
+/// Function in IR form
+///
+/// Entry block is the first one, its parameters are `this` and arguments
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub argc: usize,
+    pub blocks: Vec<Block>,
+    /// Count of values
+    pub nvalues: usize,
+    /// Function contains `yield`, its call creates generator
+    pub generator: bool,
+}
+
+impl Function {
+    pub fn new(name: &str, argc: usize) -> Function {
+        let mut func = Function {
+            name: name.to_owned(),
+            argc,
+            blocks: vec![],
+            nvalues: 0,
+            generator: false,
+        };
+        let entry = func.new_block();
+        for _ in 0..=argc {
+            let param = func.new_value();
+            func.blocks[entry.0].params.push(param);
+        }
+        func
+    }
+
+    pub fn entry(&self) -> BlockId {
+        BlockId(0)
+    }
+
+    pub fn new_value(&mut self) -> ValueId {
+        self.nvalues += 1;
+        ValueId(self.nvalues - 1)
+    }
+
+    pub fn new_block(&mut self) -> BlockId {
+        self.blocks.push(Block::default());
+        BlockId(self.blocks.len() - 1)
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.0]
+    }
+
+    pub fn block_mut(&mut self, id: BlockId) -> &mut Block {
+        &mut self.blocks[id.0]
+    }
+
+    /// Predecessors of every block, a block is listed once for every edge
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut preds = vec![vec![]; self.blocks.len()];
+        for (i, block) in self.blocks.iter().enumerate() {
+            for succ in block.term.iter().flat_map(|t| t.successors()) {
+                if succ.0 < preds.len() {
+                    preds[succ.0].push(BlockId(i));
+                }
+            }
+        }
+        preds
+    }
+
+    /// Whether every block is reachable from entry
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut work = vec![self.entry()];
+        while let Some(b) = work.pop() {
+            if b.0 >= self.blocks.len() || reachable[b.0] {
+                continue;
+            }
+            reachable[b.0] = true;
+            work.extend(self.block(b).term.iter().flat_map(|t| t.successors()));
+        }
+        reachable
+    }
+}
+
+impl fmt::Display for Const {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Const::Null => write!(f, "null"),
+            Const::Bool(b) => write!(f, "{}", b),
+            Const::Long(l) => write!(f, "{}", l),
+            Const::Double(d) => write!(f, "{:?}", d),
+            Const::Char(c) => write!(f, "{:?}", c),
+            Const::Str(s) => write!(f, "{:?}", s),
+        }
+    }
+}
+
+/// Comma separated list of values
+struct Values<'a>(&'a [ValueId]);
+
+impl fmt::Display for Values<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, v) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", v)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for InstKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstKind::Const(c) => write!(f, "const {}", c),
+            InstKind::LoadGlobal(id) => write!(f, "load_global {}", id),
+            InstKind::StoreGlobal(id, v) => write!(f, "store_global {}, {}", id, v),
+            InstKind::Binary(op, a, b) => write!(f, "{} {}, {}", op.name(), a, b),
+            InstKind::Unary(op, v) => write!(f, "{} {}", op.name(), v),
+            InstKind::LoadAt(obj, key) => write!(f, "load_at {}, {}", obj, key),
+            InstKind::StoreAt(obj, key, v) => write!(f, "store_at {}, {}, {}", obj, key, v),
+            InstKind::Call(callee, this, args) => {
+                write!(f, "call {}, {}({})", callee, this, Values(args))
+            }
+            InstKind::Yield(v) => write!(f, "yield {}", v),
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Jump(target, args) => write!(f, "jump {}({})", target, Values(args)),
+            Terminator::Branch(cond, then, otherwise) => {
+                write!(f, "branch {}, {}, {}", cond, then, otherwise)
+            }
+            Terminator::Return(Some(v)) => write!(f, "return {}", v),
+            Terminator::Return(None) => write!(f, "return"),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.generator { "generator" } else { "func" };
+        writeln!(f, "{} {}:", kind, self.name)?;
+        for (i, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "{}({}):", BlockId(i), Values(&block.params))?;
+            for inst in block.insts.iter() {
+                match inst.value {
+                    Some(v) => writeln!(f, "    {} = {}", v, inst.kind)?,
+                    None => writeln!(f, "    {}", inst.kind)?,
+                }
+            }
+            match &block.term {
+                Some(term) => writeln!(f, "    {}", term)?,
+                None => writeln!(f, "    <no terminator>")?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+//! Validation of IR invariants
+
+use super::{BlockId, Function, Terminator, ValueId};
+use std::{collections::HashMap, error::Error, fmt};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidateError {
+    /// Block isn't finished by terminator
+    MissingTerminator(BlockId),
+    /// Terminator of block refers to block that doesn't exist
+    UnknownBlock { block: BlockId, target: BlockId },
+    /// Jump passes wrong count of arguments for parameters of target
+    ArgumentCount {
+        block: BlockId,
+        target: BlockId,
+        expected: usize,
+        found: usize,
+    },
+    /// Branch goes to block that takes parameters
+    BranchToParams { block: BlockId, target: BlockId },
+    /// Something jumps to entry block
+    EntryHasPredecessors,
+    /// Entry block doesn't take `this` and arguments
+    EntryParams { expected: usize, found: usize },
+    /// Value is defined more than once
+    Redefined(ValueId),
+    /// Value is used where its definition doesn't dominate
+    Undefined { block: BlockId, value: ValueId },
+}
+
+impl fmt::Display for ValidateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidateError::MissingTerminator(b) => write!(f, "{} has no terminator", b),
+            ValidateError::UnknownBlock { block, target } => {
+                write!(f, "{} goes to unknown block {}", block, target)
+            }
+            ValidateError::ArgumentCount {
+                block,
+                target,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} passes {} arguments to {} which takes {}",
+                block, found, target, expected
+            ),
+            ValidateError::BranchToParams { block, target } => {
+                write!(f, "{} branches to {} which takes parameters", block, target)
+            }
+            ValidateError::EntryHasPredecessors => write!(f, "entry block has predecessors"),
+            ValidateError::EntryParams { expected, found } => write!(
+                f,
+                "entry block takes {} parameters instead of {}",
+                found, expected
+            ),
+            ValidateError::Redefined(v) => write!(f, "{} is defined more than once", v),
+            ValidateError::Undefined { block, value } => {
+                write!(f, "{} uses {} where it isn't defined", block, value)
+            }
+        }
+    }
+}
+
+impl Error for ValidateError {}
+
+/// Blocks that dominate every reachable block
+fn dominators(func: &Function, reachable: &[bool]) -> Vec<Vec<bool>> {
+    let n = func.blocks.len();
+    let preds = func.predecessors();
+    let mut doms = vec![vec![true; n]; n];
+    doms[0] = (0..n).map(|b| b == 0).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for b in 1..n {
+            if !reachable[b] {
+                continue;
+            }
+            let mut set = vec![true; n];
+            for p in preds[b].iter().filter(|p| reachable[p.0]) {
+                for (s, d) in set.iter_mut().zip(doms[p.0].iter()) {
+                    *s &= *d;
+                }
+            }
+            set[b] = true;
+            if set != doms[b] {
+                doms[b] = set;
+                changed = true;
+            }
+        }
+    }
+    doms
+}
+
+/// Check that `func` is well formed
+///
+/// Structure of every block is checked, dominance of definitions is checked in reachable blocks only
+pub fn validate(func: &Function) -> Result<(), ValidateError> {
+    let entry = func.block(func.entry());
+    if entry.params.len() != func.argc + 1 {
+        return Err(ValidateError::EntryParams {
+            expected: func.argc + 1,
+            found: entry.params.len(),
+        });
+    }
+    if !func.predecessors()[0].is_empty() {
+        return Err(ValidateError::EntryHasPredecessors);
+    }
+
+    // where every value is defined: block and position, parameters are at position 0
+    let mut defs: HashMap<ValueId, (usize, usize)> = HashMap::new();
+    for (b, block) in func.blocks.iter().enumerate() {
+        let block_id = BlockId(b);
+        let params = block.params.iter().map(|p| (*p, 0));
+        let insts = block
+            .insts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, inst)| inst.value.map(|v| (v, i + 1)));
+        for (v, pos) in params.chain(insts) {
+            if defs.insert(v, (b, pos)).is_some() {
+                return Err(ValidateError::Redefined(v));
+            }
+        }
+
+        let term = block
+            .term
+            .as_ref()
+            .ok_or(ValidateError::MissingTerminator(block_id))?;
+        for target in term.successors() {
+            let target_block = func
+                .blocks
+                .get(target.0)
+                .ok_or(ValidateError::UnknownBlock {
+                    block: block_id,
+                    target,
+                })?;
+            match term {
+                Terminator::Jump(_, args) if args.len() != target_block.params.len() => {
+                    return Err(ValidateError::ArgumentCount {
+                        block: block_id,
+                        target,
+                        expected: target_block.params.len(),
+                        found: args.len(),
+                    });
+                }
+                Terminator::Branch(_, _, _) if !target_block.params.is_empty() => {
+                    return Err(ValidateError::BranchToParams {
+                        block: block_id,
+                        target,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let reachable = func.reachable();
+    let doms = dominators(func, &reachable);
+    for (b, block) in func.blocks.iter().enumerate() {
+        if !reachable[b] {
+            continue;
+        }
+        let insts = block
+            .insts
+            .iter()
+            .enumerate()
+            .map(|(i, inst)| (i + 1, inst.kind.operands()));
+        let term = block
+            .term
+            .iter()
+            .map(|t| (block.insts.len() + 1, t.operands()));
+        for (pos, operands) in insts.chain(term) {
+            for value in operands {
+                let defined = match defs.get(&value) {
+                    Some(&(db, dpos)) if db == b => dpos < pos,
+                    Some(&(db, _)) => doms[b][db],
+                    None => false,
+                };
+                if !defined {
+                    return Err(ValidateError::Undefined {
+                        block: BlockId(b),
+                        value,
+                    });
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,56 +1,32 @@
+use crate::regalloc::{allocate, AllocError};
 use jazz_vm::opcodes::Instruction;
 use std::collections::HashMap;
 
-pub const MAX_REGISTERS: usize = 256;
-
+/// Builder of function code
+///
+/// Code is emitted over virtual registers: every temp and local gets a register of its own and registers
+/// are never freed. Physical registers are assigned by `get_insts`.
+/// Register 0 holds `this` and registers `1..=nlocals` hold arguments, they are created by `new`
 #[derive(Clone)]
 pub struct FunctionBuilder {
     pub list: Vec<Instruction>,
     pub label_counter: usize,
-    pub maxtemps: usize,
-    pub ntemps: usize,
+    /// Count of arguments
     pub nlocals: usize,
-    pub locals: HashMap<String, usize>,
-    pub state: [bool; MAX_REGISTERS],
-    pub skipclear: [bool; MAX_REGISTERS],
-    pub registers: Vec<usize>,
-    pub context: Vec<Vec<bool>>,
+    /// Count of virtual registers
+    pub nregs: usize,
+    /// First register and size of every call window
+    pub windows: Vec<(usize, usize)>,
 }
 
 impl FunctionBuilder {
     pub fn new(nlocals: usize) -> FunctionBuilder {
-        let mut state = [false; MAX_REGISTERS];
-        state[0] = true;
-        /*for i in 0..nlocals {
-            state[i] = true;
-        }*/
-
         FunctionBuilder {
             label_counter: 0,
             nlocals,
-            ntemps: 0,
-            locals: HashMap::new(),
-            maxtemps: 0,
             list: Vec::new(),
-            registers: Vec::with_capacity(MAX_REGISTERS),
-            context: Vec::new(),
-            state,
-            skipclear: [false; MAX_REGISTERS],
-        }
-    }
-
-    pub fn new_local(&mut self, n: String, reg: usize) {
-        self.state[reg] = true;
-        self.nlocals += 1;
-        self.locals.insert(n, reg);
-    }
-
-    pub fn get_local(&mut self, n: &str) -> usize {
-        if self.locals.contains_key(n) {
-            let r = self.locals.get(n).expect("Unknown local").clone();
-            r
-        } else {
-            panic!("Local `{}` doesn't exists", n);
+            nregs: 1,
+            windows: Vec::new(),
         }
     }
 
@@ -68,102 +44,54 @@ impl FunctionBuilder {
     }
 
     pub fn register_new(&mut self) -> usize {
-        for i in 0..MAX_REGISTERS {
-            if self.state[i] == false {
-                self.state[i] = true;
-                return i;
+        self.nregs += 1;
+        self.nregs - 1
+    }
+
+    /// Reserve `n` consecutive registers for a call window
+    ///
+    /// Window is placed above every register live across the call when registers are allocated
+    pub fn register_window(&mut self, n: usize) -> usize {
+        let base = self.nregs;
+        self.nregs += n;
+        self.windows.push((base, n));
+        base
+    }
+
+    /// Finished code of function
+    ///
+    /// Labels are removed and every jump to a label is replaced by jump to instruction index:
+    /// `Goto` becomes `Jump`, `GotoF` becomes `JumpF`, and `JumpCmpF`/`JumpCmpIF` are emitted with label id as target.
+    /// Virtual registers are replaced by physical ones
+    pub fn get_insts(&mut self) -> Result<Vec<Instruction>, AllocError> {
+        let mut labels = HashMap::new();
+        let mut ip = 0;
+        for ins in self.list.iter() {
+            if let Instruction::Label(id) = ins {
+                labels.insert(*id, ip);
+            } else {
+                ip += 1;
             }
         }
-        println!("No registers available");
-        return 0;
-    }
-
-    pub fn register_push(&mut self, nreg: usize) -> usize {
-        self.registers.push(nreg);
-        if self.register_is_temp(nreg) {
-            self.ntemps += 1;
-        }
-        return nreg;
-    }
-
-    pub fn register_first_temp_available(&mut self) -> usize {
-        for i in 0..MAX_REGISTERS {
-            if self.state[i] == false {
-                return i;
-            }
-        }
-        return 0;
-    }
-
-    pub fn register_push_temp(&mut self) -> usize {
-        let value = self.register_new();
-        self.registers.push(value);
-        if value > self.maxtemps {
-            self.maxtemps = value;
-            self.ntemps += 1;
-        }
-
-        return value;
-    }
-
-    pub fn get_insts(&mut self) -> Vec<Instruction> {
-        self.list.clone()
-    }
 
-    pub fn register_pop_context_protect(&mut self, protect: bool) -> usize {
-        if self.registers.len() == 0 {
-            panic!("REGISTER ERROR");
-        }
-
-        let value = self.registers.pop().unwrap_or_default();
-
-        if protect {
-            self.state[value] = true;
-        } else if value > self.nlocals {
-            self.state[value] = false;
-        }
-
-        if protect && value >= self.nlocals {
-            let ctx = self.context.last_mut().unwrap();
-            ctx[value] = true;
-        }
-
-        return value;
-    }
-
-    pub fn int_const(&mut self, int: i32) -> usize {
-        let register = self.register_push_temp();
-        self.list.push(Instruction::LoadInt(register, int));
-        return register;
-    }
-
-    pub fn long_const(&mut self, long: i64) -> usize {
-        let register = self.register_push_temp();
-        self.list.push(Instruction::LoadLong(register, long));
-        return register;
-    }
-
-    pub fn float_const(&mut self, float: f32) -> usize {
-        let register = self.register_push_temp();
-        self.list.push(Instruction::LoadFloat(register, float));
-        return register;
-    }
-
-    pub fn double_const(&mut self, float: f64) -> usize {
-        let register = self.register_push_temp();
-        self.list.push(Instruction::LoadDouble(register, float));
-        return register;
-    }
-    pub fn register_pop(&mut self) -> usize {
-        self.register_pop_context_protect(false)
-    }
-
-    pub fn register_clear(&mut self, nreg: usize) {
-        if nreg >= self.nlocals {
-            self.state[nreg] = false;
-        }
-    }
-    pub fn register_is_temp(&self, nreg: usize) -> bool {
-        return nreg >= self.nlocals;
+        let target = |id: &usize| *labels.get(id).expect("Unknown label");
+        let mut code: Vec<Instruction> = self
+            .list
+            .iter()
+            .filter(|ins| !matches!(ins, Instruction::Label(_)))
+            .map(|ins| match ins {
+                Instruction::Goto(id) => Instruction::Jump(target(id)),
+                Instruction::GotoF(r, id) => Instruction::JumpF(*r, target(id)),
+                Instruction::JumpCmpF(cmp, r1, r2, id) => {
+                    Instruction::JumpCmpF(*cmp, *r1, *r2, target(id))
+                }
+                Instruction::JumpCmpIF(cmp, r, imm, id) => {
+                    Instruction::JumpCmpIF(*cmp, *r, *imm, target(id))
+                }
+                ins => ins.clone(),
+            })
+            .collect();
+        allocate(&mut code, self.nregs, self.nlocals + 1, &self.windows)?;
+        Ok(code)
     }
 }
@@ -3,7 +3,16 @@
 pub mod builtins;
 pub mod class;
 pub mod compiler;
+pub mod enums;
+pub mod error;
+pub mod ir;
 pub mod ircode;
+pub mod module;
 pub mod parser;
+pub mod regalloc;
 pub mod std_library;
-pub use self::compiler::Compiler;
+pub use self::{
+    compiler::Compiler,
+    error::CompileError,
+    module::{Load, Module},
+};
@@ -1,13 +1,14 @@
 extern crate jazz;
 extern crate jazz_vm;
 extern crate structopt;
+use float_duration::TimePoint;
 use jazz::{
     parser::{lex, parse},
-    Compiler,
+    Compiler, Load,
 };
-use jazz_vm::machine::Machine;
+use jazz_vm::{machine::Machine, object::ObjectAddon, opcodes::DebugCode};
 
-use std::{fs::File, io::prelude::*, path::PathBuf};
+use std::{fs::File, io::prelude::*, path::PathBuf, time::Instant};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -16,11 +17,12 @@ pub struct Options {
     file: Option<PathBuf>,
     #[structopt(short = "d", long = "debug")]
     debug: bool,
+    #[structopt(short = "O", long = "opt-level", default_value = "0")]
+    opt_level: u8,
 }
 
 fn main() {
     let mut src = String::new();
-
     let ops = Options::from_args();
 
     if let Some(path) = ops.file {
@@ -29,9 +31,51 @@ fn main() {
         panic!("You should enter file path");
     }
 
-    let lex = lex(&src);
-    let parsed = parse(&mut lex.peekable()).unwrap();
+    let mut lex = lex(&src);
+    let parsed = match parse(&mut lex) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("error: {}: {}", lex.position(), e);
+            std::process::exit(1);
+        }
+    };
+    let mut cmpl = Compiler::new(ops.debug);
+    cmpl.opt_level = ops.opt_level;
+    let module = match cmpl.compile(parsed) {
+        Ok(module) => module,
+        Err(errors) => {
+            for e in errors.iter() {
+                eprintln!("error: {}", e);
+            }
+            std::process::exit(1);
+        }
+    };
+    if ops.debug {
+        let methods = module.classes.iter().flat_map(|c| c.methods.iter());
+        for fun in module.functions.iter().chain(methods) {
+            if let Some(ir) = &fun.ir {
+                println!("function `{}` IR:", fun.name);
+                print!("{}", ir);
+            }
+            println!("function `{}` code: ", fun.name);
+            println!("{}", fun.code.toString());
+        }
+    }
+
     let mut machine = Machine::new();
-    let mut cmpl = Compiler::new(&mut machine, 0, ops.debug);
-    cmpl.compile(parsed);
+    let result = machine.load(&module).and_then(|_| {
+        let start = Instant::now();
+        let ret = machine.call("main", &[])?;
+        let end = Instant::now();
+        Ok((ret, end.float_duration_since(start).unwrap()))
+    });
+    match result {
+        Ok((ret, duration)) => {
+            println!("RESULT: {} (in {})", ret.to_String(&mut machine), duration)
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
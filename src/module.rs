@@ -0,0 +1,246 @@
+//! Compiled programs
+//!
+//! `Compiler` produces a `Module`: code of functions and classes which refer to globals by module-local ids.
+//! `globals` of module maps names to these ids, so module doesn't depend on any `Machine`. `Load` loads
+//! modules into machine: globals defined by module get ids of their own, references to globals defined by
+//! modules loaded before and to standard globals are linked by name.
+
+use crate::{class::Class, enums::Enum, parser::Position, std_library};
+use jazz_vm::{
+    error::VmError,
+    function::Function,
+    machine::Machine,
+    opcodes::Instruction,
+    value::Value,
+    verifier::{self, VerifyError},
+};
+use std::{
+    cell::UnsafeCell,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+};
+
+/// Initial value of class field
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constant {
+    Null,
+    Int(i32),
+    Float(f32),
+    Char(char),
+    Str(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct ModuleFunction {
+    pub name: String,
+    pub argc: usize,
+    pub code: Vec<Instruction>,
+    /// Position of function name in source
+    pub pos: Position,
+    /// IR listing, kept when compiler runs with `debug`
+    pub ir: Option<String>,
+    /// Function contains `yield`, see `jazz_vm::generator`
+    pub generator: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct ModuleClass {
+    pub name: String,
+    pub pos: Position,
+    pub fields: Vec<(String, Constant)>,
+    pub methods: Vec<ModuleFunction>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ModuleEnum {
+    pub name: String,
+    pub pos: Position,
+    /// Names of variants and of their associated values
+    pub variants: Vec<(String, Vec<String>)>,
+}
+
+/// Functions, classes and enums of one compiled file
+#[derive(Clone, Debug, Default)]
+pub struct Module {
+    /// Module-local id of every global that module defines or refers to
+    pub globals: HashMap<String, usize>,
+    pub functions: Vec<ModuleFunction>,
+    pub classes: Vec<ModuleClass>,
+    pub enums: Vec<ModuleEnum>,
+}
+
+impl Module {
+    /// Names of globals defined by module
+    pub fn definitions(&self) -> impl Iterator<Item = &str> {
+        let functions = self.functions.iter().map(|f| f.name.as_str());
+        let classes = self.classes.iter().map(|c| c.name.as_str());
+        functions
+            .chain(classes)
+            .chain(self.enums.iter().map(|e| e.name.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkError {
+    /// Global is neither defined by a loaded module nor standard
+    Undefined(String),
+    /// Module defines global that is defined already
+    Redefined(String),
+    /// Code of function is rejected by `jazz_vm::verifier`
+    Verify(String, VerifyError),
+    /// Function called by `Load::call` failed
+    Runtime(VmError),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::Undefined(name) => write!(f, "global `{}` is not defined", name),
+            LinkError::Redefined(name) => write!(f, "global `{}` is defined twice", name),
+            LinkError::Verify(name, e) => write!(f, "function `{}` is malformed: {}", name, e),
+            LinkError::Runtime(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for LinkError {}
+
+/// Loading of modules into machine
+pub trait Load {
+    /// Define globals of `module` and link its references
+    ///
+    /// Nothing is loaded if module can't be linked or code of any of its functions fails verification
+    fn load(&mut self, module: &Module) -> Result<(), LinkError>;
+
+    /// Call global function `name`, `this` is null
+    ///
+    /// Error raised by the call is returned as `LinkError::Runtime`
+    fn call(&mut self, name: &str, args: &[Value]) -> Result<Value, LinkError>;
+}
+
+/// Unused global id
+fn free_id(m: &Machine) -> usize {
+    let globals = m.globals.keys();
+    globals.chain(m.names.values()).max().map_or(0, |id| id + 1)
+}
+
+/// Code of function with ids of globals replaced by ids of machine
+fn link(fun: &ModuleFunction, ids: &HashMap<usize, usize>) -> Vec<Instruction> {
+    fun.code
+        .iter()
+        .map(|ins| match *ins {
+            Instruction::LoadGlobal(r, id) => Instruction::LoadGlobal(r, ids[&id]),
+            Instruction::StoreGlobal(r, id) => Instruction::StoreGlobal(r, ids[&id]),
+            ref ins => ins.clone(),
+        })
+        .collect()
+}
+
+/// Function object with linked code of `fun`
+fn function(m: &mut Machine, fun: &ModuleFunction, ids: &HashMap<usize, usize>) -> Value {
+    let code = link(fun, ids);
+    let func = if fun.generator {
+        Function::from_generator(code, fun.argc)
+    } else {
+        Function::from_instructions(code, fun.argc)
+    };
+    Value::Object(m.pool.allocate(Box::new(func)))
+}
+
+impl Load for Machine {
+    fn load(&mut self, module: &Module) -> Result<(), LinkError> {
+        let defined: HashSet<&str> = module.definitions().collect();
+        for name in defined.iter() {
+            if self.names.contains_key(*name) {
+                return Err(LinkError::Redefined(name.to_string()));
+            }
+        }
+        for name in module.globals.keys() {
+            let known = defined.contains(name.as_str())
+                || self.names.contains_key(name)
+                || std_library::GLOBALS.contains(&name.as_str());
+            if !known {
+                return Err(LinkError::Undefined(name.to_owned()));
+            }
+        }
+
+        // module-local id to id of machine, globals which machine lacks get unused ids
+        let mut ids = HashMap::new();
+        let mut missing = vec![];
+        let mut next = free_id(self);
+        for (name, local) in module.globals.iter() {
+            let id = match self.names.get(name) {
+                Some(id) => *id,
+                None => {
+                    missing.push((name, next));
+                    next += 1;
+                    next - 1
+                }
+            };
+            ids.insert(*local, id);
+        }
+
+        let methods = module.classes.iter().flat_map(|c| c.methods.iter());
+        for fun in module.functions.iter().chain(methods) {
+            verifier::verify(&link(fun, &ids), self)
+                .map_err(|e| LinkError::Verify(fun.name.clone(), e))?;
+        }
+
+        for (name, id) in missing {
+            let value = if defined.contains(name.as_str()) {
+                Value::Null
+            } else {
+                std_library::global(self, name).unwrap()
+            };
+            self.names.insert(name.clone(), id);
+            self.globals.insert(id, value);
+        }
+
+        for fun in module.functions.iter() {
+            let value = function(self, fun, &ids);
+            self.globals.insert(ids[&module.globals[&fun.name]], value);
+        }
+        for class in module.classes.iter() {
+            let mut fields = HashMap::new();
+            for fun in class.methods.iter() {
+                fields.insert(fun.name.clone(), function(self, fun, &ids));
+            }
+            for (name, constant) in class.fields.iter() {
+                let value = match constant {
+                    Constant::Null => Value::Null,
+                    Constant::Int(i) => Value::Int(*i),
+                    Constant::Float(f) => Value::Float(*f),
+                    Constant::Char(c) => Value::Char(*c),
+                    Constant::Str(s) => Value::Object(self.pool.allocate(Box::new(s.clone()))),
+                };
+                fields.insert(name.clone(), value);
+            }
+            let class_value = Class {
+                name: class.name.clone(),
+                fields: UnsafeCell::new(fields),
+            };
+            let value = Value::Object(self.pool.allocate(Box::new(class_value)));
+            self.globals
+                .insert(ids[&module.globals[&class.name]], value);
+        }
+        for enumeration in module.enums.iter() {
+            let value = Enum::allocate(self, &enumeration.name, &enumeration.variants);
+            self.globals
+                .insert(ids[&module.globals[&enumeration.name]], value);
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, args: &[Value]) -> Result<Value, LinkError> {
+        let callee = self
+            .names
+            .get(name)
+            .and_then(|id| self.globals.get(id))
+            .cloned()
+            .ok_or_else(|| LinkError::Undefined(name.to_owned()))?;
+        let mut call_args = vec![Value::Null];
+        call_args.extend_from_slice(args);
+        self.invoke(callee, &call_args).map_err(LinkError::Runtime)
+    }
+}
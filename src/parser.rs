@@ -6,16 +6,18 @@ pub enum LexError {
     MalformedEscapeSequence,
     MalformedNumber,
     MalformedChar,
+    UnterminatedString,
     Nothing,
 }
 
-impl Error for LexError {
-    fn description(&self) -> &str {
+impl LexError {
+    fn message(&self) -> &str {
         match *self {
             LexError::UnexpectedChar => "Unexpected character in input",
             LexError::MalformedEscapeSequence => "Unexpected values in escape sequence",
             LexError::MalformedNumber => "Unexpected characters in number",
             LexError::MalformedChar => "Char constant not a single character",
+            LexError::UnterminatedString => "String literal is not terminated",
             LexError::Nothing => "This error is for internal use only",
         }
     }
@@ -23,10 +25,12 @@ impl Error for LexError {
 
 impl fmt::Display for LexError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.description())
+        write!(f, "{}", self.message())
     }
 }
 
+impl Error for LexError {}
+
 #[derive(Debug)]
 pub enum ParseError {
     BadInput,
@@ -38,16 +42,25 @@ pub enum ParseError {
     MissingSemicolon,
     MissingRCurly,
     MissingRSquare,
+    MissingColon,
     MalformedCallExpr,
     MalformedIndexExpr,
+    MalformedInterpolation,
     VarExpectsIdentifier,
     FnMissingName,
     ClassMissingName,
+    EnumMissingName,
+    MalformedVariant,
+    DuplicateVariant,
+    MissingFatArrow,
+    MalformedPattern,
+    MissingIn,
     FnMissingParams,
+    ExpectedDeclaration,
 }
 
-impl Error for ParseError {
-    fn description(&self) -> &str {
+impl ParseError {
+    fn message(&self) -> &str {
         match *self {
             ParseError::BadInput => "Unparseable characters in the input stream",
             ParseError::InputPastEndOfFile => "Input past end of file",
@@ -58,26 +71,33 @@ impl Error for ParseError {
             ParseError::MissingLCurly => "Expected '{'",
             ParseError::MissingRCurly => "Expected '}'",
             ParseError::MissingRSquare => "Expected ']'",
+            ParseError::MissingColon => "Expected ':'",
             ParseError::MalformedCallExpr => "Call contains bad expression",
             ParseError::MalformedIndexExpr => "Indexing expression missing correct index",
+            ParseError::MalformedInterpolation => "Interpolation must contain a single expression",
             ParseError::VarExpectsIdentifier => "'var' expects the name of a variable",
             ParseError::FnMissingName => "Function declaration is missing name",
             ParseError::FnMissingParams => "Function declaration is missing parameters",
             ParseError::ClassMissingName => "Class missing name",
+            ParseError::EnumMissingName => "Enum missing name",
+            ParseError::MalformedVariant => "Enum variant must be a name with optional parameters",
+            ParseError::DuplicateVariant => "Enum variant is declared twice",
+            ParseError::MissingFatArrow => "Expected '=>'",
+            ParseError::MalformedPattern => "Malformed pattern",
+            ParseError::MissingIn => "Expected 'in'",
+            ParseError::ExpectedDeclaration => "Expected function, class, enum or variable declaration",
         }
     }
-
-    fn cause(&self) -> Option<&dyn Error> {
-        None
-    }
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.description())
+        write!(f, "{}", self.message())
     }
 }
 
+impl Error for ParseError {}
+
 #[derive(Debug, Clone)]
 pub struct FnDef {
     pub name: Box<Expr>,
@@ -92,9 +112,24 @@ pub struct ClassDef {
     pub methods: Vec<FnDef>,
 }
 
+/// Variant of enum, `params` are names of its associated values
+#[derive(Debug, Clone)]
+pub struct VariantDef {
+    pub name: String,
+    pub params: Vec<String>,
+    pub pos: Position,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumDef {
+    pub name: Box<Expr>,
+    pub variants: Vec<VariantDef>,
+}
+
 #[derive(Clone, Debug)]
 pub enum Global {
     ClassDefinition(ClassDef),
+    EnumDefinition(EnumDef),
     FnDefenition(FnDef),
     Variable(Stmt),
 }
@@ -105,46 +140,108 @@ pub enum Stmt {
     IfElse(Box<Expr>, Box<Stmt>, Box<Stmt>),
     While(Box<Expr>, Box<Stmt>),
     For(Box<Stmt>, Box<Expr>, Box<Expr>, Box<Stmt>),
-    Loop(Box<Stmt>),
-    Var(String, Option<Box<Expr>>),
+    /// `for name in iterable { }`, position of `for` keyword
+    ForIn(String, Box<Expr>, Box<Stmt>, Position),
+    Loop(Box<Stmt>, Position),
+    Var(String, Option<Box<Expr>>, Position),
     Block(Vec<Stmt>),
     Expr(Box<Expr>),
-    Label(String),
-    Goto(String),
-    Break,
+    Label(String, Position),
+    Goto(String, Position),
+    Break(Position),
     Return,
     ReturnWithVal(Box<Expr>),
+    /// `yield value;`, bare `yield;` yields null
+    Yield(Box<Expr>),
+    /// `match` on value, position of `match` keyword
+    Match(Box<Expr>, Vec<MatchArm>, Position),
+}
+
+/// `pattern if guard => body`
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Box<Stmt>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `_`, matches anything
+    Wildcard,
+    /// Name that is bound to matched value
+    Bind(String, Position),
+    /// Constant equal to matched value
+    Literal(Expr),
+    /// `~Type` or `name ~ Type`, value that `Isa` type, bound to name if there is one
+    Type(Option<String>, Box<Expr>, Position),
+    /// `[p1, p2]`, array of as many elements as there are patterns
+    Array(Vec<Pattern>, Position),
+    /// `Enum.Variant` or `Enum.Variant(p1, p2)` with patterns of associated values
+    Variant(Box<Expr>, Option<Vec<Pattern>>, Position),
 }
 
 #[derive(Debug, Clone)]
 pub enum Expr {
-    IntConst(i64),
-    FloatConst(f64),
-    Identifier(String),
-    CharConst(char),
-    StringConst(String),
-    FnCall(String, Vec<Expr>),
-    Assignment(Box<Expr>, Box<Expr>),
-    Dot(Box<Expr>, Box<Expr>),
-    Index(String, Box<Expr>),
-    Array(Vec<Expr>),
-    New(String, Vec<Expr>),
-    True,
-    This,
-    False,
-    Unit,
-    Op(Op, Box<Expr>, Box<Expr>),
+    IntConst(i64, Position),
+    FloatConst(f64, Position),
+    Identifier(String, Position),
+    CharConst(char, Position),
+    StringConst(String, Position),
+    /// String literal with `${expr}` parts, converted to strings and concatenated
+    Interpolation(Vec<Expr>, Position),
+    FnCall(String, Vec<Expr>, Position),
+    Assignment(Box<Expr>, Box<Expr>, Position),
+    Dot(Box<Expr>, Box<Expr>, Position),
+    Index(String, Box<Expr>, Position),
+    Array(Vec<Expr>, Position),
+    Map(Vec<(Expr, Expr)>, Position),
+    New(String, Vec<Expr>, Position),
+    True(Position),
+    This(Position),
+    False(Position),
+    Unit(Position),
+    Op(Op, Box<Expr>, Box<Expr>, Position),
 }
 
 impl Expr {
     pub fn is_call(&self) -> bool {
-        match self {
-            Expr::FnCall(_, _) => true,
-            _ => false,
+        matches!(self, Expr::FnCall(_, _, _))
+    }
+
+    /// Position of first token of a constant or name, operator token of an operation
+    pub fn position(&self) -> Position {
+        match *self {
+            Expr::IntConst(_, pos)
+            | Expr::FloatConst(_, pos)
+            | Expr::Identifier(_, pos)
+            | Expr::CharConst(_, pos)
+            | Expr::StringConst(_, pos)
+            | Expr::Interpolation(_, pos)
+            | Expr::FnCall(_, _, pos)
+            | Expr::Assignment(_, _, pos)
+            | Expr::Dot(_, _, pos)
+            | Expr::Index(_, _, pos)
+            | Expr::Array(_, pos)
+            | Expr::Map(_, pos)
+            | Expr::New(_, _, pos)
+            | Expr::True(pos)
+            | Expr::This(pos)
+            | Expr::False(pos)
+            | Expr::Unit(pos)
+            | Expr::Op(_, _, _, pos) => pos,
         }
     }
 }
 
+/// Part of interpolated string literal
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    /// Source of interpolated expression and position of its first character
+    Code(String, Position),
+}
+
 #[derive(Debug, Clone)]
 pub enum Token {
     IntConst(i64),
@@ -152,13 +249,19 @@ pub enum Token {
     Identifier(String),
     CharConst(char),
     StringConst(String),
+    /// String literal with `${expr}` interpolations
+    Interpolated(Vec<StringPart>),
     GlobalIdent(String),
     Null,
     Label,
     For,
+    In,
     NewLine,
     End,
     Enum,
+    Match,
+    /// `=>` of match arm
+    FatArrow,
     This,
     Goto,
     LCurly,
@@ -174,10 +277,16 @@ pub enum Token {
     UnaryMinus,
     Multiply,
     Divide,
+    /// `div` keyword, integer division
+    IntDivide,
     Semicolon,
     Colon,
     Comma,
     Period,
+    /// `..` of exclusive range
+    DotDot,
+    /// `..=` of inclusive range
+    DotDotEq,
     Equals,
     True,
     False,
@@ -202,6 +311,7 @@ pub enum Token {
     Class,
     Break,
     Return,
+    Yield,
     PlusAssign,
     MinusAssign,
     MultiplyAssign,
@@ -218,6 +328,10 @@ pub enum Token {
     ModuloAssign,
     PowerOf,
     PowerOfAssign,
+    /// Unary `~`, bitwise not
+    Tilde,
+    /// Binary `~`, `value ~ Class`
+    Isa,
     LexErr(LexError),
 }
 
@@ -226,6 +340,9 @@ pub enum Op {
     Add,
     Sub,
     Div,
+    IntDiv,
+    Rem,
+    Pow,
     Mul,
     Eq,
     Ge,
@@ -233,6 +350,8 @@ pub enum Op {
     Lt,
     Gt,
     Not,
+    Neg,
+    BitNot,
     BitAnd,
     BitOr,
     BitXor,
@@ -243,6 +362,10 @@ pub enum Op {
     Access,
     Isa,
     Ne,
+    /// `a..b`
+    Range,
+    /// `a..=b`
+    RangeInclusive,
 }
 
 impl Token {
@@ -264,9 +387,13 @@ impl Token {
             UnaryMinus       |
             Multiply         |
             Divide           |
+            IntDivide        |
             Colon            |
             Comma            |
             Period           |
+            DotDot           |
+            DotDotEq         |
+            In               |
             Equals           |
             LessThan         |
             GreaterThan      |
@@ -296,8 +423,13 @@ impl Token {
             Modulo           |
             ModuloAssign     |
             Return           |
+            Yield            |
+            Match            |
+            FatArrow         |
             PowerOf          |
-            PowerOfAssign => true,
+            PowerOfAssign    |
+            Tilde            |
+            Isa => true,
             _ => false,
         }
     }
@@ -306,136 +438,212 @@ impl Token {
     pub fn is_bin_op(&self) -> bool {
         use self::Token::*;
 
-        match *self {
+        matches!(
+            *self,
             RCurly | RParen | RSquare | Plus | Minus | Multiply | Divide | Comma | Period
-            | Equals | LessThan | GreaterThan | LessThanEqual | GreaterThanEqual | EqualTo
-            | NotEqualTo | Pipe | Or | Ampersand | And | PowerOf => true,
-            _ => false,
-        }
+                | Equals | LessThan | GreaterThan | LessThanEqual | GreaterThanEqual | EqualTo
+                | NotEqualTo | Pipe | Or | Ampersand | And | PowerOf
+        )
     }
 
     #[allow(dead_code)]
     pub fn is_un_op(&self) -> bool {
         use self::Token::*;
 
-        match *self {
-            UnaryPlus | UnaryMinus | Equals | Bang | Return => true,
-            _ => false,
-        }
+        matches!(*self, UnaryPlus | UnaryMinus | Tilde | Equals | Bang | Return)
+    }
+}
+
+/// Location in source text, lines and columns start from 1
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
     }
 }
 
 pub struct TokenIterator<'a> {
     last: Token,
     char_stream: Peekable<Chars<'a>>,
+    /// Position of next character in `char_stream`
+    pos: Position,
+    /// Position of first character of token that is being lexed
+    start: Position,
+    /// Token lexed by `peek`
+    peeked: Option<Option<(Token, Position)>>,
 }
 
 impl<'a> TokenIterator<'a> {
-    pub fn parse_string_const(&mut self, enclosing_char: char) -> Result<String, LexError> {
-        let mut result = Vec::new();
-        let mut escape = false;
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.char_stream.next()?;
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
+        }
+        Some(c)
+    }
 
-        while let Some(nxt) = self.char_stream.next() {
-            match nxt {
-                '\\' if !escape => escape = true,
-                '\\' if escape => {
-                    escape = false;
-                    result.push('\\');
-                }
-                't' if escape => {
-                    escape = false;
-                    result.push('\t');
-                }
-                'n' if escape => {
-                    escape = false;
-                    result.push('\n');
-                }
-                'r' if escape => {
-                    escape = false;
-                    result.push('\r');
-                }
-                'x' if escape => {
-                    escape = false;
-                    let mut out_val: u32 = 0;
-                    for _ in 0..2 {
-                        if let Some(c) = self.char_stream.next() {
-                            if let Some(d1) = c.to_digit(16) {
-                                out_val *= 16;
-                                out_val += d1;
-                            } else {
-                                return Err(LexError::MalformedEscapeSequence);
-                            }
-                        } else {
-                            return Err(LexError::MalformedEscapeSequence);
-                        }
-                    }
+    /// Next token without consuming it
+    pub fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lex_next());
+        }
+        match &self.peeked {
+            Some(Some((token, _))) => Some(token),
+            _ => None,
+        }
+    }
 
-                    if let Some(r) = char::from_u32(out_val) {
-                        result.push(r);
-                    } else {
-                        return Err(LexError::MalformedEscapeSequence);
-                    }
-                }
-                'u' if escape => {
-                    escape = false;
-                    let mut out_val: u32 = 0;
-                    for _ in 0..4 {
-                        if let Some(c) = self.char_stream.next() {
-                            if let Some(d1) = c.to_digit(16) {
-                                out_val *= 16;
-                                out_val += d1;
-                            } else {
-                                return Err(LexError::MalformedEscapeSequence);
-                            }
-                        } else {
-                            return Err(LexError::MalformedEscapeSequence);
-                        }
-                    }
+    /// Position of next token or end of input
+    pub fn position(&mut self) -> Position {
+        self.peek();
+        match self.peeked {
+            Some(Some((_, pos))) => pos,
+            _ => self.pos,
+        }
+    }
 
-                    if let Some(r) = char::from_u32(out_val) {
-                        result.push(r);
-                    } else {
-                        return Err(LexError::MalformedEscapeSequence);
-                    }
-                }
-                'U' if escape => {
-                    escape = false;
-                    let mut out_val: u32 = 0;
-                    for _ in 0..8 {
-                        if let Some(c) = self.char_stream.next() {
-                            if let Some(d1) = c.to_digit(16) {
-                                out_val *= 16;
-                                out_val += d1;
-                            } else {
-                                return Err(LexError::MalformedEscapeSequence);
-                            }
-                        } else {
-                            return Err(LexError::MalformedEscapeSequence);
-                        }
-                    }
+    fn lex_next(&mut self) -> Option<(Token, Position)> {
+        self.last = self.inner_next()?;
+        Some((self.last.clone(), self.start))
+    }
 
-                    if let Some(r) = char::from_u32(out_val) {
-                        result.push(r);
-                    } else {
-                        return Err(LexError::MalformedEscapeSequence);
-                    }
-                }
-                x if enclosing_char == x && escape => result.push(x),
-                x if enclosing_char == x && !escape => break,
-                _ if escape => return Err(LexError::MalformedEscapeSequence),
-                _ => {
-                    escape = false;
-                    result.push(nxt);
+    /// `digits` hexadecimal digits of `\x`, `\u` or `\U` escape
+    fn parse_hex_escape(&mut self, digits: usize) -> Result<char, LexError> {
+        let mut out_val: u32 = 0;
+        for _ in 0..digits {
+            match self.next_char().and_then(|c| c.to_digit(16)) {
+                Some(d) => out_val = out_val * 16 + d,
+                None => return Err(LexError::MalformedEscapeSequence),
+            }
+        }
+        char::from_u32(out_val).ok_or(LexError::MalformedEscapeSequence)
+    }
+
+    /// Whether input continues with `s`
+    fn lookahead(&self, s: &str) -> bool {
+        let mut ahead = self.char_stream.clone();
+        s.chars().all(|c| ahead.next() == Some(c))
+    }
+
+    /// Source of `${...}` interpolation, opening `${` is consumed already
+    fn parse_interpolation(&mut self) -> Result<String, LexError> {
+        let mut code = String::new();
+        let mut depth = 0;
+        let mut quote = None;
+        let mut escape = false;
+        while let Some(c) = self.next_char() {
+            match (quote, c) {
+                (Some(_), '\\') if !escape => escape = true,
+                (Some(q), c) if c == q && !escape => quote = None,
+                (Some(_), _) => escape = false,
+                (None, '"') | (None, '\'') => quote = Some(c),
+                (None, '{') => depth += 1,
+                (None, '}') if depth == 0 => return Ok(code),
+                (None, '}') => depth -= 1,
+                _ => (),
+            }
+            code.push(c);
+        }
+        Err(LexError::UnterminatedString)
+    }
+
+    /// Body of string literal which ends with `quote`, opening quote is consumed already
+    ///
+    /// Escapes are not processed in `raw` literal. `${expr}` becomes separate part if `interpolate` is true,
+    /// `\$` stands for `$` in such literal.
+    pub fn parse_string_const(
+        &mut self,
+        quote: &str,
+        raw: bool,
+        interpolate: bool,
+    ) -> Result<Vec<StringPart>, LexError> {
+        let mut parts = Vec::new();
+        let mut result = String::new();
+
+        loop {
+            // closing quotes of multi-line literal are the last ones of a run of quotes
+            if quote.len() > 1 && self.lookahead("\"\"\"\"") {
+                self.next_char();
+                result.push('"');
+                continue;
+            }
+            if self.lookahead(quote) {
+                for _ in quote.chars() {
+                    self.next_char();
                 }
+                break;
+            }
+            if interpolate && self.lookahead("${") {
+                self.next_char();
+                self.next_char();
+                let pos = self.pos;
+                let code = self.parse_interpolation()?;
+                parts.push(StringPart::Text(std::mem::take(&mut result)));
+                parts.push(StringPart::Code(code, pos));
+                continue;
             }
+            let nxt = self.next_char().ok_or(LexError::UnterminatedString)?;
+            if raw || nxt != '\\' {
+                result.push(nxt);
+                continue;
+            }
+            let escaped = match self.next_char() {
+                Some('\\') => '\\',
+                Some('t') => '\t',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('"') => '"',
+                Some('\'') => '\'',
+                Some('$') if interpolate => '$',
+                Some('x') => self.parse_hex_escape(2)?,
+                Some('u') => self.parse_hex_escape(4)?,
+                Some('U') => self.parse_hex_escape(8)?,
+                _ => return Err(LexError::MalformedEscapeSequence),
+            };
+            result.push(escaped);
         }
 
-        let out: String = result.iter().cloned().collect();
-        Ok(out)
+        parts.push(StringPart::Text(result));
+        Ok(parts)
+    }
+
+    /// String literal after its opening `"`, `raw` if it's prefixed with `r`
+    fn parse_string(&mut self, raw: bool) -> Token {
+        let quote = if self.lookahead("\"\"") {
+            self.next_char();
+            self.next_char();
+            // line break right after opening quotes is not part of multi-line literal
+            if self.lookahead("\n") {
+                self.next_char();
+            }
+            "\"\"\""
+        } else {
+            "\""
+        };
+        match self.parse_string_const(quote, raw, !raw) {
+            Ok(mut parts) if parts.len() == 1 => match parts.pop() {
+                Some(StringPart::Text(s)) => Token::StringConst(s),
+                _ => unreachable!(),
+            },
+            Ok(parts) => Token::Interpolated(parts),
+            Err(e) => Token::LexErr(e),
+        }
     }
 
     fn inner_next(&mut self) -> Option<Token> {
-        while let Some(c) = self.char_stream.next() {
+        while let Some(c) = self.next_char() {
+            self.start = Position {
+                line: self.pos.line,
+                column: self.pos.column - 1,
+            };
             match c {
                 '0'..='9' => {
                     let mut result = Vec::new();
@@ -446,16 +654,18 @@ impl<'a> TokenIterator<'a> {
                         match nxt {
                             '0'..='9' => {
                                 result.push(nxt);
-                                self.char_stream.next();
+                                self.next_char();
                             }
+                            // `1..2` is range, not float
+                            '.' if self.lookahead("..") => break,
                             '.' => {
                                 result.push(nxt);
-                                self.char_stream.next();
+                                self.next_char();
                                 while let Some(&nxt_float) = self.char_stream.peek() {
                                     match nxt_float {
                                         '0'..='9' => {
                                             result.push(nxt_float);
-                                            self.char_stream.next();
+                                            self.next_char();
                                         }
                                         _ => break,
                                     }
@@ -463,12 +673,12 @@ impl<'a> TokenIterator<'a> {
                             }
                             'x' | 'X' => {
                                 result.push(nxt);
-                                self.char_stream.next();
+                                self.next_char();
                                 while let Some(&nxt_hex) = self.char_stream.peek() {
                                     match nxt_hex {
                                         '0'..='9' | 'a'..='f' | 'A'..='F' => {
                                             result.push(nxt_hex);
-                                            self.char_stream.next();
+                                            self.next_char();
                                         }
                                         _ => break,
                                     }
@@ -477,12 +687,12 @@ impl<'a> TokenIterator<'a> {
                             }
                             'o' | 'O' => {
                                 result.push(nxt);
-                                self.char_stream.next();
+                                self.next_char();
                                 while let Some(&nxt_oct) = self.char_stream.peek() {
                                     match nxt_oct {
                                         '0'..='8' => {
                                             result.push(nxt_oct);
-                                            self.char_stream.next();
+                                            self.next_char();
                                         }
                                         _ => break,
                                     }
@@ -491,12 +701,12 @@ impl<'a> TokenIterator<'a> {
                             }
                             'b' | 'B' => {
                                 result.push(nxt);
-                                self.char_stream.next();
+                                self.next_char();
                                 while let Some(&nxt_bin) = self.char_stream.peek() {
                                     match nxt_bin {
                                         '0' | '1' | '_' => {
                                             result.push(nxt_bin);
-                                            self.char_stream.next();
+                                            self.next_char();
                                         }
                                         _ => break,
                                     }
@@ -529,6 +739,10 @@ impl<'a> TokenIterator<'a> {
                     return Some(Token::LexErr(LexError::MalformedNumber));
                 }
 
+                'r' if self.lookahead("\"") => {
+                    self.next_char();
+                    return Some(self.parse_string(true));
+                }
                 '$' | 'A'..='Z' | 'a'..='z' | '_' => {
                     let mut result = Vec::new();
                     result.push(c);
@@ -537,7 +751,7 @@ impl<'a> TokenIterator<'a> {
                         match nxt {
                             x if x.is_alphanumeric() || x == '_' => {
                                 result.push(x);
-                                self.char_stream.next();
+                                self.next_char();
                             }
                             _ => break,
                         }
@@ -552,34 +766,38 @@ impl<'a> TokenIterator<'a> {
                         "end" => return Some(Token::End),
                         "else" => return Some(Token::Else),
                         "for" => return Some(Token::For),
+                        "in" => return Some(Token::In),
                         "while" => return Some(Token::While),
                         "loop" => return Some(Token::Loop),
                         "break" => return Some(Token::Break),
                         "return" => return Some(Token::Return),
+                        "yield" => return Some(Token::Yield),
                         "new" => return Some(Token::New),
                         "func" => return Some(Token::Fn),
                         "null" => return Some(Token::Null),
 
                         "enum" => return Some(Token::Enum),
+                        "match" => return Some(Token::Match),
                         "this" => return Some(Token::This),
                         "class" => return Some(Token::Class),
                         "label" => return Some(Token::Label),
                         "goto" => return Some(Token::Goto),
+                        "div" => return Some(Token::IntDivide),
                         x => {
                             return Some(Token::Identifier(x.into()));
                         }
                     }
                 }
-                '"' => match self.parse_string_const('"') {
-                    Ok(out) => return Some(Token::StringConst(out)),
-                    Err(e) => return Some(Token::LexErr(e)),
-                },
-                '\'' => match self.parse_string_const('\'') {
-                    Ok(result) => {
+                '"' => return Some(self.parse_string(false)),
+                '\'' => match self.parse_string_const("'", false, false) {
+                    Ok(parts) => {
+                        let result = match &parts[..] {
+                            [StringPart::Text(s)] => s.clone(),
+                            _ => unreachable!(),
+                        };
                         let mut chars = result.chars();
 
                         if let Some(out) = chars.next() {
-                            println!("result: {}", result);
                             if chars.count() != 0 {
                                 return Some(Token::LexErr(LexError::MalformedChar));
                             }
@@ -599,7 +817,7 @@ impl<'a> TokenIterator<'a> {
                 '+' => {
                     return match self.char_stream.peek() {
                         Some(&'=') => {
-                            self.char_stream.next();
+                            self.next_char();
                             Some(Token::PlusAssign)
                         }
                         _ if self.last.is_next_unary() => Some(Token::UnaryPlus),
@@ -609,7 +827,7 @@ impl<'a> TokenIterator<'a> {
                 '-' => {
                     return match self.char_stream.peek() {
                         Some(&'=') => {
-                            self.char_stream.next();
+                            self.next_char();
                             Some(Token::MinusAssign)
                         }
                         _ if self.last.is_next_unary() => Some(Token::UnaryMinus),
@@ -619,16 +837,26 @@ impl<'a> TokenIterator<'a> {
                 '*' => {
                     return match self.char_stream.peek() {
                         Some(&'=') => {
-                            self.char_stream.next();
+                            self.next_char();
                             Some(Token::MultiplyAssign)
                         }
+                        Some(&'*') => {
+                            self.next_char();
+                            match self.char_stream.peek() {
+                                Some(&'=') => {
+                                    self.next_char();
+                                    Some(Token::PowerOfAssign)
+                                }
+                                _ => Some(Token::PowerOf),
+                            }
+                        }
                         _ => Some(Token::Multiply),
                     }
                 }
                 '/' => match self.char_stream.peek() {
                     Some(&'/') => {
-                        self.char_stream.next();
-                        while let Some(c) = self.char_stream.next() {
+                        self.next_char();
+                        for c in self.char_stream.by_ref() {
                             if c == '\n' {
                                 break;
                             }
@@ -636,16 +864,16 @@ impl<'a> TokenIterator<'a> {
                     }
                     Some(&'*') => {
                         let mut level = 1;
-                        self.char_stream.next();
-                        while let Some(c) = self.char_stream.next() {
+                        self.next_char();
+                        while let Some(c) = self.next_char() {
                             match c {
                                 '/' => {
-                                    if let Some('*') = self.char_stream.next() {
+                                    if let Some('*') = self.next_char() {
                                         level += 1;
                                     }
                                 }
                                 '*' => {
-                                    if let Some('/') = self.char_stream.next() {
+                                    if let Some('/') = self.next_char() {
                                         level -= 1;
                                     }
                                 }
@@ -658,7 +886,7 @@ impl<'a> TokenIterator<'a> {
                         }
                     }
                     Some(&'=') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return Some(Token::DivideAssign);
                     }
                     _ => return Some(Token::Divide),
@@ -666,28 +894,42 @@ impl<'a> TokenIterator<'a> {
                 ';' => return Some(Token::Semicolon),
                 ':' => return Some(Token::Colon),
                 ',' => return Some(Token::Comma),
-                '.' => return Some(Token::Period),
+                '.' => match self.char_stream.peek() {
+                    Some(&'.') => {
+                        self.next_char();
+                        if let Some(&'=') = self.char_stream.peek() {
+                            self.next_char();
+                            return Some(Token::DotDotEq);
+                        }
+                        return Some(Token::DotDot);
+                    }
+                    _ => return Some(Token::Period),
+                },
                 '=' => match self.char_stream.peek() {
                     Some(&'=') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return Some(Token::EqualTo);
                     }
+                    Some(&'>') => {
+                        self.next_char();
+                        return Some(Token::FatArrow);
+                    }
                     _ => return Some(Token::Equals),
                 },
                 '<' => match self.char_stream.peek() {
                     Some(&'=') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return Some(Token::LessThanEqual);
                     }
                     Some(&'<') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return match self.char_stream.peek() {
                             Some(&'=') => {
-                                self.char_stream.next();
+                                self.next_char();
                                 Some(Token::LeftShiftAssign)
                             }
                             _ => {
-                                self.char_stream.next();
+                                self.next_char();
                                 Some(Token::LeftShift)
                             }
                         };
@@ -696,18 +938,18 @@ impl<'a> TokenIterator<'a> {
                 },
                 '>' => match self.char_stream.peek() {
                     Some(&'=') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return Some(Token::GreaterThanEqual);
                     }
                     Some(&'>') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return match self.char_stream.peek() {
                             Some(&'=') => {
-                                self.char_stream.next();
+                                self.next_char();
                                 Some(Token::RightShiftAssign)
                             }
                             _ => {
-                                self.char_stream.next();
+                                self.next_char();
                                 Some(Token::RightShift)
                             }
                         };
@@ -716,54 +958,49 @@ impl<'a> TokenIterator<'a> {
                 },
                 '!' => match self.char_stream.peek() {
                     Some(&'=') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return Some(Token::NotEqualTo);
                     }
                     _ => return Some(Token::Bang),
                 },
                 '|' => match self.char_stream.peek() {
                     Some(&'|') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return Some(Token::Or);
                     }
                     Some(&'=') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return Some(Token::OrAssign);
                     }
                     _ => return Some(Token::Pipe),
                 },
                 '&' => match self.char_stream.peek() {
                     Some(&'&') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return Some(Token::And);
                     }
                     Some(&'=') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return Some(Token::AndAssign);
                     }
                     _ => return Some(Token::Ampersand),
                 },
                 '^' => match self.char_stream.peek() {
                     Some(&'=') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return Some(Token::XOrAssign);
                     }
                     _ => return Some(Token::XOr),
                 },
                 '%' => match self.char_stream.peek() {
                     Some(&'=') => {
-                        self.char_stream.next();
+                        self.next_char();
                         return Some(Token::ModuloAssign);
                     }
                     _ => return Some(Token::Modulo),
                 },
-                '~' => match self.char_stream.peek() {
-                    Some(&'=') => {
-                        self.char_stream.next();
-                        return Some(Token::PowerOfAssign);
-                    }
-                    _ => return Some(Token::PowerOf),
-                },
+                '~' if self.last.is_next_unary() => return Some(Token::Tilde),
+                '~' => return Some(Token::Isa),
 
                 _x if _x.is_whitespace() => (),
                 _ => return Some(Token::LexErr(LexError::UnexpectedChar)),
@@ -777,20 +1014,27 @@ impl<'a> TokenIterator<'a> {
 impl<'a> Iterator for TokenIterator<'a> {
     type Item = Token;
 
-    // TODO - perhaps this could be optimized?
     fn next(&mut self) -> Option<Self::Item> {
-        self.last = match self.inner_next() {
-            Some(c) => c,
-            None => return None,
+        let next = match self.peeked.take() {
+            Some(next) => next,
+            None => self.lex_next(),
         };
-        Some(self.last.clone())
+        next.map(|(token, _)| token)
     }
 }
 
 pub fn lex(input: &str) -> TokenIterator<'_> {
+    lex_at(input, Position { line: 1, column: 1 })
+}
+
+/// Lexer for `input` which begins at `start` of enclosing source
+pub fn lex_at(input: &str, start: Position) -> TokenIterator<'_> {
     TokenIterator {
         last: Token::LexErr(LexError::Nothing),
         char_stream: input.chars().peekable(),
+        pos: start,
+        start,
+        peeked: None,
     }
 }
 
@@ -816,19 +1060,20 @@ fn get_precedence(token: &Token) -> i32 {
         | Token::GreaterThanEqual
         | Token::EqualTo
         | Token::NotEqualTo => 15,
+        Token::DotDot | Token::DotDotEq => 17,
         Token::Plus | Token::Minus => 20,
 
-        Token::Divide | Token::Multiply | Token::PowerOf => 40,
+        Token::Divide | Token::Multiply | Token::IntDivide | Token::Modulo => 40,
+        Token::PowerOf => 45,
         Token::LeftShift | Token::RightShift => 50,
-        Token::Modulo => 60,
-        //Token::Isa => 70,
+        Token::Isa => 70,
         Token::Period => 100,
 
         _ => -1,
     }
 }
 
-fn parse_paren_expr<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, ParseError> {
+fn parse_paren_expr<'a>(input: &mut TokenIterator<'a>) -> Result<Expr, ParseError> {
     let expr = parse_expr(input).unwrap();
 
     match input.next() {
@@ -837,9 +1082,10 @@ fn parse_paren_expr<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr,
     }
 }
 
-fn parse_new_expr<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, ParseError> {
+fn parse_new_expr<'a>(input: &mut TokenIterator<'a>) -> Result<Expr, ParseError> {
     let mut args = Vec::new();
 
+    let pos = input.position();
     let name = match input.next() {
         Some(Token::Identifier(ref s)) => s.clone(),
         v => panic!("{:?}", v),
@@ -854,7 +1100,7 @@ fn parse_new_expr<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, P
 
     if let Some(&Token::RParen) = input.peek() {
         input.next();
-        return Ok(Expr::New(name, args));
+        return Ok(Expr::New(name, args, pos));
     }
 
     loop {
@@ -867,7 +1113,7 @@ fn parse_new_expr<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, P
         match input.peek() {
             Some(&Token::RParen) => {
                 input.next();
-                return Ok(Expr::New(name, args));
+                return Ok(Expr::New(name, args, pos));
             }
             Some(&Token::Comma) => (),
             _ => return Err(ParseError::MalformedCallExpr),
@@ -878,13 +1124,14 @@ fn parse_new_expr<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, P
 
 fn parse_call_expr<'a>(
     id: String,
-    input: &mut Peekable<TokenIterator<'a>>,
+    pos: Position,
+    input: &mut TokenIterator<'a>,
 ) -> Result<Expr, ParseError> {
     let mut args = Vec::new();
 
     if let Some(&Token::RParen) = input.peek() {
         input.next();
-        return Ok(Expr::FnCall(id, args));
+        return Ok(Expr::FnCall(id, args, pos));
     }
 
     loop {
@@ -897,7 +1144,7 @@ fn parse_call_expr<'a>(
         match input.peek() {
             Some(&Token::RParen) => {
                 input.next();
-                return Ok(Expr::FnCall(id, args));
+                return Ok(Expr::FnCall(id, args, pos));
             }
             Some(&Token::Comma) => (),
             v => {
@@ -912,48 +1159,47 @@ fn parse_call_expr<'a>(
 
 fn parse_index_expr<'a>(
     id: String,
-    input: &mut Peekable<TokenIterator<'a>>,
+    pos: Position,
+    input: &mut TokenIterator<'a>,
 ) -> Result<Expr, ParseError> {
     if let Ok(idx) = parse_expr(input) {
         match input.peek() {
             Some(&Token::RSquare) => {
                 input.next();
-                return Ok(Expr::Index(id, Box::new(idx)));
+                Ok(Expr::Index(id, Box::new(idx), pos))
             }
-            _ => return Err(ParseError::MalformedIndexExpr),
+            _ => Err(ParseError::MalformedIndexExpr),
         }
     } else {
-        return Err(ParseError::MalformedIndexExpr);
+        Err(ParseError::MalformedIndexExpr)
     }
 }
 
 fn parse_ident_expr<'a>(
     id: String,
-    input: &mut Peekable<TokenIterator<'a>>,
+    pos: Position,
+    input: &mut TokenIterator<'a>,
 ) -> Result<Expr, ParseError> {
     match input.peek() {
         Some(&Token::LParen) => {
             input.next();
-            parse_call_expr(id, input)
+            parse_call_expr(id, pos, input)
         }
         Some(&Token::LSquare) => {
             input.next();
-            parse_index_expr(id, input)
+            parse_index_expr(id, pos, input)
         }
-        _ => Ok(Expr::Identifier(id)),
+        _ => Ok(Expr::Identifier(id, pos)),
     }
 }
 
-fn parse_array_expr<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, ParseError> {
+fn parse_array_expr<'a>(pos: Position, input: &mut TokenIterator<'a>) -> Result<Expr, ParseError> {
     let mut arr = Vec::new();
 
-    let skip_contents = match input.peek() {
-        Some(&Token::RSquare) => true,
-        _ => false,
-    };
+    let skip_contents = matches!(input.peek(), Some(&Token::RSquare));
 
     if !skip_contents {
-        while let Some(_) = input.peek() {
+        while input.peek().is_some() {
             arr.push(parse_expr(input)?);
             if let Some(&Token::Comma) = input.peek() {
                 input.next();
@@ -968,27 +1214,82 @@ fn parse_array_expr<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr,
     match input.peek() {
         Some(&Token::RSquare) => {
             input.next();
-            Ok(Expr::Array(arr))
+            Ok(Expr::Array(arr, pos))
         }
         _ => Err(ParseError::MissingRSquare),
     }
 }
 
-fn parse_primary<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, ParseError> {
+fn parse_map_expr<'a>(pos: Position, input: &mut TokenIterator<'a>) -> Result<Expr, ParseError> {
+    let mut entries = Vec::new();
+
+    while input.peek().is_some() {
+        if let Some(&Token::RCurly) = input.peek() {
+            break;
+        }
+        let key = parse_expr(input)?;
+        match input.next() {
+            Some(Token::Colon) => (),
+            _ => return Err(ParseError::MissingColon),
+        }
+        entries.push((key, parse_expr(input)?));
+        match input.peek() {
+            Some(&Token::Comma) => {
+                input.next();
+            }
+            _ => break,
+        }
+    }
+
+    match input.peek() {
+        Some(&Token::RCurly) => {
+            input.next();
+            Ok(Expr::Map(entries, pos))
+        }
+        _ => Err(ParseError::MissingRCurly),
+    }
+}
+
+/// Text parts of interpolated string become string constants, code parts are parsed as expressions
+fn parse_interpolation(parts: &[StringPart], pos: Position) -> Result<Expr, ParseError> {
+    let mut exprs = Vec::new();
+    for part in parts {
+        match part {
+            StringPart::Text(s) if s.is_empty() => (),
+            StringPart::Text(s) => exprs.push(Expr::StringConst(s.clone(), pos)),
+            StringPart::Code(code, start) => {
+                let mut input = lex_at(code, *start);
+                if input.peek().is_none() {
+                    return Err(ParseError::MalformedInterpolation);
+                }
+                exprs.push(parse_expr(&mut input)?);
+                if input.peek().is_some() {
+                    return Err(ParseError::MalformedInterpolation);
+                }
+            }
+        }
+    }
+    Ok(Expr::Interpolation(exprs, pos))
+}
+
+fn parse_primary<'a>(input: &mut TokenIterator<'a>) -> Result<Expr, ParseError> {
+    let pos = input.position();
     if let Some(token) = input.next() {
         match token {
-            Token::IntConst(ref x) => Ok(Expr::IntConst(*x)),
-            Token::FloatConst(ref x) => Ok(Expr::FloatConst(*x)),
-            Token::StringConst(ref s) => Ok(Expr::StringConst(s.clone())),
-            Token::CharConst(ref c) => Ok(Expr::CharConst(*c)),
-            Token::Identifier(ref s) => parse_ident_expr(s.clone(), input),
+            Token::IntConst(ref x) => Ok(Expr::IntConst(*x, pos)),
+            Token::FloatConst(ref x) => Ok(Expr::FloatConst(*x, pos)),
+            Token::StringConst(ref s) => Ok(Expr::StringConst(s.clone(), pos)),
+            Token::Interpolated(ref parts) => parse_interpolation(parts, pos),
+            Token::CharConst(ref c) => Ok(Expr::CharConst(*c, pos)),
+            Token::Identifier(ref s) => parse_ident_expr(s.clone(), pos, input),
             Token::New => parse_new_expr(input),
-            Token::Null => Ok(Expr::Unit),
+            Token::Null => Ok(Expr::Unit(pos)),
             Token::LParen => parse_paren_expr(input),
-            Token::LSquare => parse_array_expr(input),
-            Token::True => Ok(Expr::True),
-            Token::False => Ok(Expr::False),
-            Token::This => Ok(Expr::This),
+            Token::LSquare => parse_array_expr(pos, input),
+            Token::LCurly => parse_map_expr(pos, input),
+            Token::True => Ok(Expr::True(pos)),
+            Token::False => Ok(Expr::False(pos)),
+            Token::This => Ok(Expr::This(pos)),
             Token::LexErr(le) => {
                 println!("Error: {}", le);
                 Err(ParseError::BadInput)
@@ -1003,7 +1304,8 @@ fn parse_primary<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, Pa
     }
 }
 
-fn parse_unary<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, ParseError> {
+fn parse_unary<'a>(input: &mut TokenIterator<'a>) -> Result<Expr, ParseError> {
+    let pos = input.position();
     let tok = match input.peek() {
         Some(tok) => tok.clone(),
         None => return Err(ParseError::InputPastEndOfFile),
@@ -1012,21 +1314,34 @@ fn parse_unary<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, Pars
     match tok {
         Token::UnaryMinus => {
             input.next();
-            Ok(Expr::FnCall(
-                "__unary_minus__".into(),
-                vec![parse_primary(input)?],
+            Ok(Expr::Op(
+                Op::Neg,
+                Box::new(parse_unary(input)?),
+                Box::new(Expr::Unit(pos)),
+                pos,
+            ))
+        }
+        Token::Tilde => {
+            input.next();
+            Ok(Expr::Op(
+                Op::BitNot,
+                Box::new(parse_unary(input)?),
+                Box::new(Expr::Unit(pos)),
+                pos,
             ))
         }
+        // `+` doesn't change its operand
         Token::UnaryPlus => {
             input.next();
-            parse_primary(input)
+            parse_unary(input)
         }
         Token::Bang => {
             input.next();
             Ok(Expr::Op(
                 Op::Not,
                 Box::new(parse_primary(input)?),
-                Box::new(Expr::Unit),
+                Box::new(Expr::Unit(pos)),
+                pos,
             ))
         }
         _ => parse_primary(input),
@@ -1034,7 +1349,7 @@ fn parse_unary<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, Pars
 }
 
 fn parse_binop<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
+    input: &mut TokenIterator<'a>,
     prec: i32,
     lhs: Expr,
 ) -> Result<Expr, ParseError> {
@@ -1051,6 +1366,7 @@ fn parse_binop<'a>(
             return Ok(lhs_curr);
         }
 
+        let pos = input.position();
         if let Some(op_token) = input.next() {
             let mut rhs = parse_unary(input)?;
 
@@ -1062,35 +1378,41 @@ fn parse_binop<'a>(
 
             if curr_prec < next_prec {
                 rhs = parse_binop(input, curr_prec + 1, rhs)?;
-            } else if curr_prec >= 100 {
-                // Always bind right to left for precedence over 100
+            } else if curr_prec >= 100
+                || (matches!(op_token, Token::PowerOf) && next_prec == curr_prec)
+            {
+                // Always bind right to left for precedence over 100 and `**`
                 rhs = parse_binop(input, curr_prec, rhs)?;
             }
 
             lhs_curr = match op_token {
-                Token::Plus => Expr::Op(Op::Add, Box::new(lhs_curr), Box::new(rhs)),
-                Token::Minus => Expr::Op(Op::Sub, Box::new(lhs_curr), Box::new(rhs)),
-                Token::Multiply => Expr::Op(Op::Mul, Box::new(lhs_curr), Box::new(rhs)),
-                Token::Divide => Expr::Op(Op::Div, Box::new(lhs_curr), Box::new(rhs)),
-                Token::EqualTo => Expr::Op(Op::Eq, Box::new(lhs_curr), Box::new(rhs)),
-                Token::GreaterThan => Expr::Op(Op::Gt, Box::new(lhs_curr), Box::new(rhs)),
-                Token::LessThan => Expr::Op(Op::Lt, Box::new(lhs_curr), Box::new(rhs)),
-                Token::Equals => Expr::Assignment(Box::new(lhs_curr), Box::new(rhs)),
-                Token::And => Expr::Op(Op::And, Box::new(lhs_curr), Box::new(rhs)),
-                Token::Or => Expr::Op(Op::Or, Box::new(lhs_curr), Box::new(rhs)),
-                Token::Ampersand => Expr::Op(Op::BitAnd, Box::new(lhs_curr), Box::new(rhs)),
-                Token::Pipe => Expr::Op(Op::BitOr, Box::new(lhs_curr), Box::new(rhs)),
-                Token::XOr => Expr::Op(Op::BitXor, Box::new(lhs_curr), Box::new(rhs)),
-                Token::LeftShift => Expr::Op(Op::Shl, Box::new(lhs_curr), Box::new(rhs)),
-                Token::RightShift => Expr::Op(Op::Shr, Box::new(lhs_curr), Box::new(rhs)),
-                Token::Period => Expr::Op(Op::Access, Box::new(lhs_curr), Box::new(rhs)),
-                Token::LessThanEqual => Expr::Op(Op::Le, Box::new(lhs_curr), Box::new(rhs)),
+                Token::Plus => Expr::Op(Op::Add, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::Minus => Expr::Op(Op::Sub, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::Multiply => Expr::Op(Op::Mul, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::Divide => Expr::Op(Op::Div, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::IntDivide => Expr::Op(Op::IntDiv, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::Modulo => Expr::Op(Op::Rem, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::PowerOf => Expr::Op(Op::Pow, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::EqualTo => Expr::Op(Op::Eq, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::GreaterThan => Expr::Op(Op::Gt, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::LessThan => Expr::Op(Op::Lt, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::Equals => Expr::Assignment(Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::And => Expr::Op(Op::And, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::Or => Expr::Op(Op::Or, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::Ampersand => Expr::Op(Op::BitAnd, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::Pipe => Expr::Op(Op::BitOr, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::XOr => Expr::Op(Op::BitXor, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::LeftShift => Expr::Op(Op::Shl, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::RightShift => Expr::Op(Op::Shr, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::Period => Expr::Op(Op::Access, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::LessThanEqual => Expr::Op(Op::Le, Box::new(lhs_curr), Box::new(rhs), pos),
                 Token::PlusAssign => {
                     let lhs_copy = lhs_curr.clone();
 
                     Expr::Assignment(
                         Box::new(lhs_curr),
-                        Box::new(Expr::Op(Op::Add, Box::new(lhs_copy), Box::new(rhs))),
+                        Box::new(Expr::Op(Op::Add, Box::new(lhs_copy), Box::new(rhs), pos)),
+                        pos,
                     )
                 }
                 Token::MinusAssign => {
@@ -1098,12 +1420,53 @@ fn parse_binop<'a>(
 
                     Expr::Assignment(
                         Box::new(lhs_curr),
-                        Box::new(Expr::Op(Op::Sub, Box::new(lhs_copy), Box::new(rhs))),
+                        Box::new(Expr::Op(Op::Sub, Box::new(lhs_copy), Box::new(rhs), pos)),
+                        pos,
+                    )
+                }
+                Token::MultiplyAssign => {
+                    let lhs_copy = lhs_curr.clone();
+
+                    Expr::Assignment(
+                        Box::new(lhs_curr),
+                        Box::new(Expr::Op(Op::Mul, Box::new(lhs_copy), Box::new(rhs), pos)),
+                        pos,
+                    )
+                }
+                Token::DivideAssign => {
+                    let lhs_copy = lhs_curr.clone();
+
+                    Expr::Assignment(
+                        Box::new(lhs_curr),
+                        Box::new(Expr::Op(Op::Div, Box::new(lhs_copy), Box::new(rhs), pos)),
+                        pos,
+                    )
+                }
+                Token::ModuloAssign => {
+                    let lhs_copy = lhs_curr.clone();
+
+                    Expr::Assignment(
+                        Box::new(lhs_curr),
+                        Box::new(Expr::Op(Op::Rem, Box::new(lhs_copy), Box::new(rhs), pos)),
+                        pos,
                     )
                 }
-                Token::GreaterThanEqual => Expr::Op(Op::Ge, Box::new(lhs_curr), Box::new(rhs)),
-                Token::PowerOf => Expr::Op(Op::Isa, Box::new(lhs_curr), Box::new(rhs)),
-                Token::NotEqualTo => Expr::Op(Op::Ne,Box::new(lhs_curr),Box::new(rhs)),
+                Token::PowerOfAssign => {
+                    let lhs_copy = lhs_curr.clone();
+
+                    Expr::Assignment(
+                        Box::new(lhs_curr),
+                        Box::new(Expr::Op(Op::Pow, Box::new(lhs_copy), Box::new(rhs), pos)),
+                        pos,
+                    )
+                }
+                Token::GreaterThanEqual => Expr::Op(Op::Ge, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::Isa => Expr::Op(Op::Isa, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::DotDot => Expr::Op(Op::Range, Box::new(lhs_curr), Box::new(rhs), pos),
+                Token::DotDotEq => {
+                    Expr::Op(Op::RangeInclusive, Box::new(lhs_curr), Box::new(rhs), pos)
+                }
+                Token::NotEqualTo => Expr::Op(Op::Ne, Box::new(lhs_curr), Box::new(rhs), pos),
                 /* Token::PlusAssign => {
                     let lhs_copy = lhs_curr.clone();
                     Expr::Assignment(
@@ -1207,9 +1570,9 @@ fn parse_binop<'a>(
     }
 }
 
-fn parse_expr<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, ParseError> {
+fn parse_expr<'a>(input: &mut TokenIterator<'a>) -> Result<Expr, ParseError> {
     match input.peek() {
-        Some(Token::RParen) => Ok(Expr::Unit),
+        Some(Token::RParen) => Ok(Expr::Unit(input.position())),
         _ => {
             let lhs = parse_unary(input)?;
 
@@ -1218,7 +1581,7 @@ fn parse_expr<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Expr, Parse
     }
 }
 
-fn parse_if<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseError> {
+fn parse_if<'a>(input: &mut TokenIterator<'a>) -> Result<Stmt, ParseError> {
     input.next();
 
     let guard = parse_expr(input)?;
@@ -1238,10 +1601,12 @@ fn parse_if<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseEr
     }
 }
 
-fn parse_for<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseError> {
+fn parse_for<'a>(input: &mut TokenIterator<'a>) -> Result<Stmt, ParseError> {
+    let pos = input.position();
     input.next();
     match input.next() {
         Some(Token::LParen) => {}
+        Some(Token::Identifier(name)) => return parse_for_in(input, name, pos),
         _ => return Err(ParseError::MissingLParen),
     }
     let value = parse_var(input)?;
@@ -1269,7 +1634,23 @@ fn parse_for<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseE
     ))
 }
 
-fn parse_while<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseError> {
+/// Rest of `for name in iterable { }` after `name`
+fn parse_for_in<'a>(
+    input: &mut TokenIterator<'a>,
+    name: String,
+    pos: Position,
+) -> Result<Stmt, ParseError> {
+    match input.next() {
+        Some(Token::In) => {}
+        _ => return Err(ParseError::MissingIn),
+    }
+    let iterable = parse_expr(input)?;
+    let block = parse_block(input)?;
+
+    Ok(Stmt::ForIn(name, Box::new(iterable), Box::new(block), pos))
+}
+
+fn parse_while<'a>(input: &mut TokenIterator<'a>) -> Result<Stmt, ParseError> {
     input.next();
 
     let guard = parse_expr(input)?;
@@ -1278,26 +1659,29 @@ fn parse_while<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, Pars
     Ok(Stmt::While(Box::new(guard), Box::new(body)))
 }
 
-fn parse_loop<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseError> {
+fn parse_loop<'a>(input: &mut TokenIterator<'a>) -> Result<Stmt, ParseError> {
+    let pos = input.position();
     input.next();
 
     let body = parse_block(input)?;
 
-    Ok(Stmt::Loop(Box::new(body)))
+    Ok(Stmt::Loop(Box::new(body), pos))
 }
 
-fn parse_label<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseError> {
+fn parse_label<'a>(input: &mut TokenIterator<'a>) -> Result<Stmt, ParseError> {
+    let pos = input.position();
     input.next();
     let name = match input.next() {
         Some(Token::Identifier(ref s)) => s.clone(),
         _ => return Err(ParseError::VarExpectsIdentifier),
     };
-    return Ok(Stmt::Label(name.clone()));
+    Ok(Stmt::Label(name, pos))
 }
 
-fn parse_var<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseError> {
+fn parse_var<'a>(input: &mut TokenIterator<'a>) -> Result<Stmt, ParseError> {
     input.next();
 
+    let pos = input.position();
     let name = match input.next() {
         Some(Token::Identifier(ref s)) => s.clone(),
         _ => return Err(ParseError::VarExpectsIdentifier),
@@ -1307,13 +1691,13 @@ fn parse_var<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseE
         Some(&Token::Equals) => {
             input.next();
             let initializer = parse_expr(input)?;
-            Ok(Stmt::Var(name, Some(Box::new(initializer))))
+            Ok(Stmt::Var(name, Some(Box::new(initializer)), pos))
         }
-        _ => Ok(Stmt::Var(name, None)),
+        _ => Ok(Stmt::Var(name, None, pos)),
     }
 }
 
-fn parse_block<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseError> {
+fn parse_block<'a>(input: &mut TokenIterator<'a>) -> Result<Stmt, ParseError> {
     match input.peek() {
         Some(&Token::LCurly) => (),
         Some(&Token::NewLine) => (),
@@ -1325,14 +1709,10 @@ fn parse_block<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, Pars
 
     let mut stmts = Vec::new();
 
-    let skip_body = match input.peek() {
-        Some(&Token::RCurly) => true,
-        Some(&Token::End) => true,
-        _ => false,
-    };
+    let skip_body = matches!(input.peek(), Some(&Token::RCurly) | Some(&Token::End));
 
     if !skip_body {
-        while let Some(_) = input.peek() {
+        while input.peek().is_some() {
             stmts.push(parse_stmt(input)?);
 
             if let Some(&Token::Semicolon) = input.peek() {
@@ -1361,12 +1741,13 @@ fn parse_block<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, Pars
     }
 }
 
-fn parse_expr_stmt<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseError> {
+fn parse_expr_stmt<'a>(input: &mut TokenIterator<'a>) -> Result<Stmt, ParseError> {
     let expr = parse_expr(input)?;
     Ok(Stmt::Expr(Box::new(expr)))
 }
 
-fn parse_stmt<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, ParseError> {
+fn parse_stmt<'a>(input: &mut TokenIterator<'a>) -> Result<Stmt, ParseError> {
+    let pos = input.position();
     match input.peek() {
         Some(&Token::If) => parse_if(input),
         Some(&Token::While) => parse_while(input),
@@ -1374,7 +1755,7 @@ fn parse_stmt<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, Parse
         Some(&Token::Loop) => parse_loop(input),
         Some(&Token::Break) => {
             input.next();
-            Ok(Stmt::Break)
+            Ok(Stmt::Break(pos))
         }
         Some(&Token::Return) => {
             input.next();
@@ -1386,23 +1767,191 @@ fn parse_stmt<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Stmt, Parse
                 }
             }
         }
+        Some(&Token::Yield) => {
+            input.next();
+            match input.peek() {
+                Some(&Token::Semicolon) => Ok(Stmt::Yield(Box::new(Expr::Unit(pos)))),
+                _ => {
+                    let value = parse_expr(input)?;
+                    Ok(Stmt::Yield(Box::new(value)))
+                }
+            }
+        }
         Some(&Token::Label) => parse_label(input),
         Some(&Token::Goto) => {
             input.next();
             match input.next() {
-                Some(Token::Identifier(ref s)) => Ok(Stmt::Goto(s.clone())),
-                _ => return Err(ParseError::VarExpectsIdentifier),
+                Some(Token::Identifier(ref s)) => Ok(Stmt::Goto(s.clone(), pos)),
+                _ => Err(ParseError::VarExpectsIdentifier),
             }
         }
         Some(&Token::LCurly) => parse_block(input),
         Some(&Token::Var) => parse_var(input),
+        Some(&Token::Match) => parse_match(input),
         _ => parse_expr_stmt(input),
     }
 }
 
-fn parse_class_block<'a>(
-    input: &mut Peekable<TokenIterator<'a>>,
-) -> Result<(Stmt, Vec<FnDef>), ParseError> {
+/// `match expr { pattern if guard => body, ... }`, body of arm is a block or a single statement
+fn parse_match<'a>(input: &mut TokenIterator<'a>) -> Result<Stmt, ParseError> {
+    let pos = input.position();
+    input.next();
+
+    let subject = parse_expr(input)?;
+    match input.next() {
+        Some(Token::LCurly) => (),
+        _ => return Err(ParseError::MissingLCurly),
+    }
+
+    let mut arms = Vec::new();
+    loop {
+        match input.peek() {
+            Some(&Token::RCurly) => {
+                input.next();
+                break;
+            }
+            None => return Err(ParseError::MissingRCurly),
+            _ => (),
+        }
+        let pattern = parse_pattern(input)?;
+        let guard = match input.peek() {
+            Some(&Token::If) => {
+                input.next();
+                Some(parse_expr(input)?)
+            }
+            _ => None,
+        };
+        match input.next() {
+            Some(Token::FatArrow) => (),
+            _ => return Err(ParseError::MissingFatArrow),
+        }
+        let body = parse_stmt(input)?;
+        while let Some(&Token::Semicolon) | Some(&Token::Comma) = input.peek() {
+            input.next();
+        }
+        arms.push(MatchArm {
+            pattern,
+            guard,
+            body: Box::new(body),
+        });
+    }
+
+    Ok(Stmt::Match(Box::new(subject), arms, pos))
+}
+
+/// `Name` or `Name.Name` after `~` of type pattern
+fn parse_type_path<'a>(input: &mut TokenIterator<'a>) -> Result<Expr, ParseError> {
+    let pos = input.position();
+    let name = match input.next() {
+        Some(Token::Identifier(ref s)) => Expr::Identifier(s.clone(), pos),
+        _ => return Err(ParseError::MalformedPattern),
+    };
+    parse_variant_path(name, input)
+}
+
+/// `Enum.Variant` if `name` is followed by `.`
+fn parse_variant_path<'a>(name: Expr, input: &mut TokenIterator<'a>) -> Result<Expr, ParseError> {
+    let pos = input.position();
+    match input.peek() {
+        Some(&Token::Period) => {
+            input.next();
+        }
+        _ => return Ok(name),
+    }
+    let variant_pos = input.position();
+    match input.next() {
+        Some(Token::Identifier(ref s)) => {
+            let variant = Expr::Identifier(s.clone(), variant_pos);
+            Ok(Expr::Op(Op::Access, Box::new(name), Box::new(variant), pos))
+        }
+        _ => Err(ParseError::MalformedPattern),
+    }
+}
+
+/// Comma separated patterns up to closing `end` token
+fn parse_patterns<'a>(
+    input: &mut TokenIterator<'a>,
+    end: fn(&Token) -> bool,
+) -> Result<Vec<Pattern>, ParseError> {
+    let mut patterns = Vec::new();
+    loop {
+        match input.peek() {
+            Some(token) if end(token) => {
+                input.next();
+                return Ok(patterns);
+            }
+            _ => patterns.push(parse_pattern(input)?),
+        }
+        match input.next() {
+            Some(Token::Comma) => (),
+            Some(ref token) if end(token) => return Ok(patterns),
+            _ => return Err(ParseError::MalformedPattern),
+        }
+    }
+}
+
+fn parse_pattern<'a>(input: &mut TokenIterator<'a>) -> Result<Pattern, ParseError> {
+    let pos = input.position();
+    match input.peek() {
+        Some(Token::Identifier(s)) if s == "_" => {
+            input.next();
+            Ok(Pattern::Wildcard)
+        }
+        Some(&Token::Identifier(_)) => {
+            let name = match input.next() {
+                Some(Token::Identifier(s)) => s,
+                _ => unreachable!(),
+            };
+            match input.peek() {
+                Some(&Token::Period) => {
+                    let path = parse_variant_path(Expr::Identifier(name, pos), input)?;
+                    let values = match input.peek() {
+                        Some(&Token::LParen) => {
+                            input.next();
+                            Some(parse_patterns(input, |t| matches!(t, Token::RParen))?)
+                        }
+                        _ => None,
+                    };
+                    Ok(Pattern::Variant(Box::new(path), values, pos))
+                }
+                Some(&Token::Isa) | Some(&Token::Tilde) => {
+                    input.next();
+                    let ty = parse_type_path(input)?;
+                    Ok(Pattern::Type(Some(name), Box::new(ty), pos))
+                }
+                _ => Ok(Pattern::Bind(name, pos)),
+            }
+        }
+        Some(&Token::Isa) | Some(&Token::Tilde) => {
+            input.next();
+            let ty = parse_type_path(input)?;
+            Ok(Pattern::Type(None, Box::new(ty), pos))
+        }
+        Some(&Token::LSquare) => {
+            input.next();
+            let elements = parse_patterns(input, |t| matches!(t, Token::RSquare))?;
+            Ok(Pattern::Array(elements, pos))
+        }
+        Some(&Token::UnaryMinus) | Some(&Token::Minus) => {
+            input.next();
+            match input.next() {
+                Some(Token::IntConst(i)) => Ok(Pattern::Literal(Expr::IntConst(-i, pos))),
+                Some(Token::FloatConst(f)) => Ok(Pattern::Literal(Expr::FloatConst(-f, pos))),
+                _ => Err(ParseError::MalformedPattern),
+            }
+        }
+        Some(&Token::IntConst(_))
+        | Some(&Token::FloatConst(_))
+        | Some(&Token::StringConst(_))
+        | Some(&Token::CharConst(_))
+        | Some(&Token::True)
+        | Some(&Token::False)
+        | Some(&Token::Null) => Ok(Pattern::Literal(parse_primary(input)?)),
+        _ => Err(ParseError::MalformedPattern),
+    }
+}
+
+fn parse_class_block<'a>(input: &mut TokenIterator<'a>) -> Result<(Stmt, Vec<FnDef>), ParseError> {
     match input.peek() {
         Some(&Token::LCurly) => (),
         _ => return Err(ParseError::MissingLCurly),
@@ -1412,13 +1961,10 @@ fn parse_class_block<'a>(
 
     let mut stmts = Vec::new();
     let mut fns = Vec::new();
-    let skip_body = match input.peek() {
-        Some(&Token::RCurly) => true,
-        _ => false,
-    };
+    let skip_body = matches!(input.peek(), Some(&Token::RCurly));
 
     if !skip_body {
-        while let Some(_) = input.peek() {
+        while input.peek().is_some() {
             if let Some(&Token::Var) = input.peek() {
                 stmts.push(parse_var(input)?);
                 if let Some(&Token::Semicolon) = input.peek() {
@@ -1451,21 +1997,20 @@ fn parse_class_block<'a>(
     }
 }
 
-fn parse_class<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<ClassDef, ParseError> {
+fn parse_class<'a>(input: &mut TokenIterator<'a>) -> Result<ClassDef, ParseError> {
     input.next();
 
-    let mut def = ClassDef {
-        name: Box::new(Expr::Identifier(String::new())),
-        vars: Vec::new(),
-        methods: Vec::new(),
-    };
-
+    let pos = input.position();
     let name = match input.next() {
-        Some(Token::Identifier(ref s)) => Box::new(Expr::Identifier(s.clone())),
+        Some(Token::Identifier(ref s)) => Box::new(Expr::Identifier(s.clone(), pos)),
         _ => return Err(ParseError::ClassMissingName),
     };
 
-    def.name = name;
+    let mut def = ClassDef {
+        name,
+        vars: Vec::new(),
+        methods: Vec::new(),
+    };
 
     let (block, fndefs) = parse_class_block(input)?;
     def.methods = fndefs;
@@ -1473,7 +2018,7 @@ fn parse_class<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<ClassDef,
     if let Stmt::Block(stmts) = block {
         for stmt in stmts.iter() {
             match stmt {
-                Stmt::Var(ref name, ref expr) => {
+                Stmt::Var(ref name, ref expr, _) => {
                     if expr.is_some() {
                         let expr = expr.clone().unwrap();
                         def.vars.push((name.clone(), Some(*expr)));
@@ -1489,11 +2034,67 @@ fn parse_class<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<ClassDef,
     Ok(def)
 }
 
-fn parse_fn<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<FnDef, ParseError> {
+/// `enum Name { A, B(x), C(x, y) }`, trailing comma is allowed
+fn parse_enum<'a>(input: &mut TokenIterator<'a>) -> Result<EnumDef, ParseError> {
+    input.next();
+
+    let pos = input.position();
+    let name = match input.next() {
+        Some(Token::Identifier(ref s)) => Box::new(Expr::Identifier(s.clone(), pos)),
+        _ => return Err(ParseError::EnumMissingName),
+    };
+
+    match input.next() {
+        Some(Token::LCurly) => (),
+        _ => return Err(ParseError::MissingLCurly),
+    }
+
+    let mut variants: Vec<VariantDef> = Vec::new();
+    loop {
+        let pos = input.position();
+        let name = match input.next() {
+            Some(Token::RCurly) => break,
+            Some(Token::Identifier(ref s)) => s.clone(),
+            None => return Err(ParseError::MissingRCurly),
+            _ => return Err(ParseError::MalformedVariant),
+        };
+        if variants.iter().any(|v| v.name == name) {
+            return Err(ParseError::DuplicateVariant);
+        }
+
+        let mut params = Vec::new();
+        if let Some(&Token::LParen) = input.peek() {
+            input.next();
+            loop {
+                match input.next() {
+                    Some(Token::Identifier(ref s)) => params.push(s.clone()),
+                    _ => return Err(ParseError::MalformedVariant),
+                }
+                match input.next() {
+                    Some(Token::Comma) => (),
+                    Some(Token::RParen) => break,
+                    _ => return Err(ParseError::MissingRParen),
+                }
+            }
+        }
+        variants.push(VariantDef { name, params, pos });
+
+        match input.next() {
+            Some(Token::Comma) => (),
+            Some(Token::RCurly) => break,
+            _ => return Err(ParseError::MissingRCurly),
+        }
+    }
+
+    Ok(EnumDef { name, variants })
+}
+
+fn parse_fn<'a>(input: &mut TokenIterator<'a>) -> Result<FnDef, ParseError> {
     input.next();
 
+    let pos = input.position();
     let name = match input.next() {
-        Some(Token::Identifier(ref s)) => Box::new(Expr::Identifier(s.clone())),
+        Some(Token::Identifier(ref s)) => Box::new(Expr::Identifier(s.clone(), pos)),
         _ => return Err(ParseError::FnMissingName),
     };
 
@@ -1533,21 +2134,22 @@ fn parse_fn<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<FnDef, ParseE
     let body = parse_block(input)?;
 
     Ok(FnDef {
-        name: name,
-        params: params,
+        name,
+        params,
         body: Box::new(body),
     })
 }
 
-fn parse_top_level<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Vec<Global>, ParseError> {
+fn parse_top_level<'a>(input: &mut TokenIterator<'a>) -> Result<Vec<Global>, ParseError> {
     let mut globals = Vec::new();
-    while let Some(_) = input.peek() {
+    while input.peek().is_some() {
         match input.peek() {
             Some(&Token::Class) => globals.push(Global::ClassDefinition(parse_class(input)?)),
+            Some(&Token::Enum) => globals.push(Global::EnumDefinition(parse_enum(input)?)),
             Some(&Token::Fn) => globals.push(Global::FnDefenition(parse_fn(input)?)),
             Some(&Token::NewLine) => {}
             Some(&Token::Var) => globals.push(Global::Variable(parse_var(input)?)),
-            _ => panic!("Expected top level element"),
+            _ => return Err(ParseError::ExpectedDeclaration),
         }
 
         if let Some(&Token::Semicolon) = input.peek() {
@@ -1558,6 +2160,6 @@ fn parse_top_level<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Vec<Gl
     Ok(globals)
 }
 
-pub fn parse<'a>(input: &mut Peekable<TokenIterator<'a>>) -> Result<Vec<Global>, ParseError> {
+pub fn parse<'a>(input: &mut TokenIterator<'a>) -> Result<Vec<Global>, ParseError> {
     parse_top_level(input)
 }
@@ -0,0 +1,243 @@
+//! Register allocation
+//!
+//! `FunctionBuilder` emits code over an unbounded set of virtual registers. `allocate` computes liveness of
+//! every virtual register and assigns physical registers with linear scan, so registers of dead temps are
+//! reused and frame is as small as possible.
+//!
+//! Call windows are allocated as a whole: registers of a window are consecutive and placed above every
+//! register that is live across the call, because callee frame overwrites registers above the window.
+
+use jazz_vm::{opcodes::Instruction, optimizer::successors};
+use std::fmt;
+
+/// Registers available to a function frame
+pub const MAX_REGISTERS: usize = jazz_vm::verifier::MAX_FRAME_REGISTERS;
+
+/// Function needs more registers than frame can hold
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllocError {
+    /// Instruction at which registers ran out
+    pub ip: usize,
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}: more than {} registers are live",
+            self.ip, MAX_REGISTERS
+        )
+    }
+}
+
+/// Set of virtual registers
+#[derive(Clone, PartialEq)]
+struct RegSet(Vec<u64>);
+
+impl RegSet {
+    fn new(n: usize) -> RegSet {
+        RegSet(vec![0; n.div_ceil(64)])
+    }
+
+    fn insert(&mut self, r: usize) {
+        self.0[r / 64] |= 1 << (r % 64);
+    }
+
+    fn remove(&mut self, r: usize) {
+        self.0[r / 64] &= !(1 << (r % 64));
+    }
+
+    fn contains(&self, r: usize) -> bool {
+        self.0[r / 64] & (1 << (r % 64)) != 0
+    }
+
+    fn union(&mut self, other: &RegSet) {
+        for (w, o) in self.0.iter_mut().zip(other.0.iter()) {
+            *w |= *o;
+        }
+    }
+}
+
+/// Virtual registers which are allocated together
+struct Unit {
+    /// First virtual register, the rest follow it
+    first: usize,
+    size: usize,
+    /// Live range in positions: instruction `i` reads at `2 * i` and writes at `2 * i + 1`
+    start: usize,
+    end: usize,
+    /// Index of `Call` or `TailCall` that uses this unit as window
+    call: Option<usize>,
+}
+
+impl Unit {
+    fn live_across(&self, call: usize) -> bool {
+        self.start <= 2 * call && self.end > 2 * call
+    }
+}
+
+/// Live registers at entry of every instruction
+fn liveness(code: &[Instruction], nregs: usize) -> Vec<RegSet> {
+    let mut live = vec![RegSet::new(nregs); code.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in (0..code.len()).rev() {
+            let mut set = RegSet::new(nregs);
+            for s in successors(code, i) {
+                if s < code.len() {
+                    set.union(&live[s]);
+                }
+            }
+            if let Some(d) = code[i].writes() {
+                set.remove(d);
+            }
+            for r in code[i].reads() {
+                set.insert(r);
+            }
+            if set != live[i] {
+                live[i] = set;
+                changed = true;
+            }
+        }
+    }
+    live
+}
+
+/// Replace virtual registers in `code` by physical ones
+///
+/// Virtual registers below `pinned` keep their numbers: they hold `this` and arguments on entry.
+/// `windows` lists first register and size of every call window
+pub fn allocate(
+    code: &mut [Instruction],
+    nregs: usize,
+    pinned: usize,
+    windows: &[(usize, usize)],
+) -> Result<(), AllocError> {
+    if pinned > MAX_REGISTERS {
+        return Err(AllocError { ip: 0 });
+    }
+    let live = liveness(code, nregs);
+
+    // live range of every virtual register
+    let mut ranges: Vec<Option<(usize, usize)>> = vec![None; nregs];
+    let mut extend = |r: usize, pos: usize| {
+        let range = ranges[r].get_or_insert((pos, pos));
+        range.0 = range.0.min(pos);
+        range.1 = range.1.max(pos);
+    };
+    for r in 0..pinned {
+        extend(r, 0);
+    }
+    for (i, ins) in code.iter().enumerate() {
+        for r in ins.reads() {
+            extend(r, 2 * i);
+        }
+        if let Some(d) = ins.writes() {
+            extend(d, 2 * i + 1);
+        }
+        for r in 0..nregs {
+            if live[i].contains(r) {
+                extend(r, 2 * i);
+            }
+        }
+        for s in successors(code, i) {
+            if s < code.len() {
+                for r in 0..nregs {
+                    if live[s].contains(r) {
+                        extend(r, 2 * i + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut in_window = vec![false; nregs];
+    let mut units = vec![];
+    for &(first, size) in windows {
+        let members: Vec<(usize, usize)> =
+            (first..first + size).filter_map(|r| ranges[r]).collect();
+        if members.is_empty() {
+            continue;
+        }
+        for flag in &mut in_window[first..first + size] {
+            *flag = true;
+        }
+        let call = code.iter().position(|ins| match *ins {
+            Instruction::Call(_, r, _) | Instruction::TailCall(r, _) => r == first,
+            _ => false,
+        });
+        units.push(Unit {
+            first,
+            size,
+            start: members.iter().map(|m| m.0).min().unwrap(),
+            end: members.iter().map(|m| m.1).max().unwrap(),
+            call,
+        });
+    }
+    for r in 0..nregs {
+        if let (Some((start, end)), false) = (ranges[r], in_window[r]) {
+            units.push(Unit {
+                first: r,
+                size: 1,
+                start,
+                end,
+                call: None,
+            });
+        }
+    }
+    // single registers are allocated first in order of their start, then windows: window of the last call
+    // goes first, so windows of calls nested into its arguments are placed above it
+    units.sort_by_key(|u| match u.call {
+        None => (0, u.start, u.first),
+        Some(c) => (1, usize::MAX - c, u.first),
+    });
+
+    let mut map = vec![0; nregs];
+    // allocated units: unit index and first physical register
+    let mut allocated: Vec<(usize, usize)> = vec![];
+
+    for (idx, unit) in units.iter().enumerate() {
+        let mut busy = [false; MAX_REGISTERS];
+        for &(a, phys) in allocated.iter() {
+            let other = &units[a];
+            if other.start <= unit.end && unit.start <= other.end {
+                for flag in &mut busy[phys..phys + other.size] {
+                    *flag = true;
+                }
+            }
+        }
+
+        // callee frame overwrites registers above its window, so window is placed above every register
+        // live across the call
+        let floor = match unit.call {
+            Some(c) => allocated
+                .iter()
+                .filter(|&&(a, _)| units[a].live_across(c))
+                .map(|&(a, phys)| phys + units[a].size)
+                .max()
+                .unwrap_or(0),
+            None => 0,
+        };
+
+        let phys = if unit.first < pinned {
+            unit.first
+        } else {
+            (floor..(MAX_REGISTERS + 1).saturating_sub(unit.size))
+                .find(|&p| busy[p..p + unit.size].iter().all(|b| !b))
+                .ok_or(AllocError {
+                    ip: unit.call.unwrap_or(unit.start / 2),
+                })?
+        };
+
+        for i in 0..unit.size {
+            map[unit.first + i] = phys + i;
+        }
+        allocated.push((idx, phys));
+    }
+
+    for ins in code.iter_mut() {
+        ins.rename_registers(|r| map[r]);
+    }
+    Ok(())
+}
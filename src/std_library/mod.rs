@@ -4,13 +4,12 @@ use float_duration;
 use jazz_vm::{function::Function, machine::Machine, value::Value};
 use std::{cell::UnsafeCell, collections::HashMap, time::Instant};
 
-pub fn time(m: &mut Machine, _: Vec<Value>) -> Value {
+pub fn time(m: &mut Machine, _: &[Value]) -> Value {
     let now = Instant::now();
     let duration = FloatDuration::from_std(now.elapsed());
 
     let str = format!("{}", duration);
-    let obj = Value::Object(m.pool.allocate(Box::new(str)));
-    return obj;
+    Value::Object(m.pool.allocate(Box::new(str)))
 }
 
 pub fn system_class(m: &mut Machine) -> Class {
@@ -40,15 +39,6 @@ pub fn system_class(m: &mut Machine) -> Class {
     }
 }
 
-pub fn unary_minus(m: &mut Machine) -> Value {
-    use jazz_vm::opcodes::Instruction::*;
-
-    let code = vec![LoadLong(2, 0), Move(3, 1), Sub(3, 2, 3), Ret(3)];
-
-    let func = Function::from_instructions(code, 1);
-    Value::Object(m.pool.allocate(Box::new(func)))
-}
-
 pub fn int_class() -> Class {
     Class {
         name: String::from("Int"),
@@ -63,9 +53,97 @@ pub fn float_class() -> Class {
     }
 }
 
+pub fn char_class() -> Class {
+    Class {
+        name: String::from("Char"),
+        fields: UnsafeCell::new(HashMap::new()),
+    }
+}
+
+pub fn range_class() -> Class {
+    Class {
+        name: String::from("Range"),
+        fields: UnsafeCell::new(HashMap::new()),
+    }
+}
+
+pub fn generator_class() -> Class {
+    Class {
+        name: String::from("Generator"),
+        fields: UnsafeCell::new(HashMap::new()),
+    }
+}
+
 pub fn str_class() -> Class {
     Class {
         name: String::from("Str"),
         fields: UnsafeCell::new(HashMap::new()),
     }
 }
+
+/// Names of globals that every program can use without defining them
+pub const GLOBALS: &[&str] = &[
+    "print",
+    "readln",
+    "__new_array__",
+    "__new_map__",
+    "__interpolate__",
+    "__match_size__",
+    "__no_match__",
+    "__range__",
+    "__iter__",
+    "__next__",
+    "__value__",
+    "concat",
+    "bigint",
+    "long",
+    "char",
+    "str",
+    "System",
+    "Int",
+    "Float",
+    "Str",
+    "Char",
+    "Range",
+    "Generator",
+];
+
+/// Create value of standard global `name`
+pub fn global(m: &mut Machine, name: &str) -> Option<Value> {
+    let native = |m: &mut Machine, f: fn(&mut Machine, &[Value]) -> Value| {
+        Value::Object(
+            m.pool
+                .allocate(Box::new(Function::from_native(Box::new(f)))),
+        )
+    };
+    let value = match name {
+        "print" => native(m, print),
+        "readln" => native(m, readln),
+        "__new_array__" => native(m, new_array),
+        "__new_map__" => native(m, new_map),
+        "__interpolate__" => native(m, interpolate),
+        "__match_size__" => native(m, match_size),
+        "__no_match__" => native(m, no_match),
+        "__range__" => native(m, range),
+        "__iter__" => native(m, iter),
+        "__next__" => native(m, next),
+        "__value__" => native(m, value),
+        "concat" => native(m, concat),
+        "bigint" => native(m, bigint),
+        "long" => native(m, long),
+        "char" => native(m, char),
+        "str" => native(m, str),
+        "System" => {
+            let class = system_class(m);
+            Value::Object(m.pool.allocate(Box::new(class)))
+        }
+        "Int" => Value::Object(m.pool.allocate(Box::new(int_class()))),
+        "Float" => Value::Object(m.pool.allocate(Box::new(float_class()))),
+        "Str" => Value::Object(m.pool.allocate(Box::new(str_class()))),
+        "Char" => Value::Object(m.pool.allocate(Box::new(char_class()))),
+        "Range" => Value::Object(m.pool.allocate(Box::new(range_class()))),
+        "Generator" => Value::Object(m.pool.allocate(Box::new(generator_class()))),
+        _ => return None,
+    };
+    Some(value)
+}
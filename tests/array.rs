@@ -0,0 +1,156 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{compile, error, eval, load, try_run};
+use jazz::{module::LinkError, Load};
+use jazz_vm::error::VmError;
+
+#[test]
+fn editing() {
+    let src = "
+        var a = [1, 2, 3];
+        a.insert(0, 0);
+        a.insert(4, 4);
+        var removed = a.remove(2);
+        a.set(0, -1);
+        a[1] = 10;
+        a.push(5, 6);
+        var last = a.pop();
+        return [removed, last, a, a.size(), a.get(3)];
+    ";
+    assert_eq!("[2,6,[-1,10,3,4,5],5,4]", eval(src));
+    assert_eq!(
+        "[[],0]",
+        eval("var a = [1, 2]; a.clear(); return [a, a.size()];")
+    );
+    assert_eq!("[7,7,7]", eval("var a = [1, 2, 3]; return a.fill(7);"));
+    assert_eq!("[3,2,1]", eval("var a = [1, 2, 3]; a.reverse(); return a;"));
+}
+
+#[test]
+fn queries() {
+    assert_eq!("[2,3]", eval("return [1, 2, 3].slice(1);"));
+    assert_eq!("[2]", eval("return [1, 2, 3].slice(1, 2);"));
+    assert_eq!("[]", eval("return [1, 2, 3].slice(3);"));
+    assert_eq!("[1,2,3,4]", eval("return [1].concat([2, 3], [], [4]);"));
+    assert_eq!(
+        "[1,-1,0]",
+        eval("var a = [\"a\", 2, 2.0]; return [a.indexOf(2.0), a.indexOf(3), a.indexOf(\"a\")];")
+    );
+    assert_eq!(
+        "[true,false]",
+        eval("var a = [null, true]; return [a.contains(null), a.contains(false)];")
+    );
+    assert_eq!("1, x, [2]", eval("return [1, \"x\", [2]].join(\", \");"));
+    assert_eq!("1,2", eval("return [1, 2].join();"));
+}
+
+#[test]
+fn callbacks() {
+    let src = "
+        func square(x) { return x * x; }
+        func odd(x) { return x % 2 == 1; }
+        func sum(acc, x) { return acc + x; }
+        func weighted(acc, x, i) { return acc + x * i; }
+        func bump(cell, i) { cell[0] += i + 10; }
+        func main() {
+            var a = [1, 2, 3, 4];
+            var cells = [[1], [2]];
+            cells.forEach(bump);
+            return [cells,
+                a.map(square), a.filter(odd), a.reduce(sum), a.reduce(sum, 10),
+                a.reduce(weighted, 0), a.find(odd), [2, 4].find(odd)
+            ];
+        }
+    ";
+    assert_eq!(
+        "[[[11],[13]],[1,4,9,16],[1,3],10,20,20,1,null]",
+        try_run(src).unwrap()
+    );
+}
+
+#[test]
+fn sort() {
+    let src = "
+        func desc(a, b) { return b - a; }
+        func by_length(a, b) { return a.size() - b.size(); }
+        func main() {
+            var numbers = [3, 1.5, 10, -2];
+            var copy = numbers.slice(0);
+            var sorted = [copy.sort(), numbers.sort(desc)];
+            var lists = [[1, 2], [3], [4, 5], [], [6]];
+            sorted.push(lists.sort(by_length));
+            return sorted;
+        }
+    ";
+    assert_eq!(
+        "[[-2,1.5,3,10],[10,3,1.5,-2],[[],[3],[6],[1,2],[4,5]]]",
+        try_run(src).unwrap()
+    );
+}
+
+#[test]
+fn bounds_errors() {
+    let bounds = |i: i64, size: usize| {
+        format!(
+            "Runtime Error: `index {} is out of bounds for Array of size {}`",
+            i, size
+        )
+    };
+    assert_eq!(bounds(3, 3), error("var a = [1, 2, 3]; return a[3];"));
+    assert_eq!(bounds(-1, 3), error("var a = [1, 2, 3]; a[-1] = 0;"));
+    assert_eq!(bounds(0, 0), error("return [].get(0);"));
+    assert_eq!(bounds(5, 1), error("return [1].set(5, 0);"));
+    assert_eq!(bounds(2, 0), error("return [].insert(2, 0);"));
+    assert_eq!(bounds(1, 1), error("return [1].remove(1);"));
+    assert_eq!(bounds(4, 3), error("return [1, 2, 3].slice(0, 4);"));
+    assert_eq!(
+        "Runtime Error: `slice start 2 is greater than end 1`",
+        error("return [1, 2, 3].slice(2, 1);")
+    );
+    assert_eq!(
+        "Runtime Error: `reduce of empty Array with no initial value`",
+        try_run("func f(a, b) { return a; } func main() { return [].reduce(f); }").unwrap_err()
+    );
+    assert_eq!(
+        "Runtime Error: `Array has no method `sum``",
+        error("return [1].sum();")
+    );
+}
+
+#[test]
+fn callback_errors() {
+    // error of callback stops the method and is returned from the call
+    let src = "
+        func check(x) { var a = [x]; return a[1]; }
+        func main() { var a = [1, 2]; a.forEach(check); return a; }
+    ";
+    assert_eq!(
+        "Runtime Error: `index 1 is out of bounds for Array of size 1`",
+        try_run(src).unwrap_err()
+    );
+    let src = "func number(x) { return 1; } func main() { return [1, 2].filter(number); }";
+    assert_eq!(
+        "Runtime Error: `Array::filter expects Bool from callback, found Long(1)`",
+        try_run(src).unwrap_err()
+    );
+    let src = "func cmp(a, b) { return null; } func main() { return [1, 2].sort(cmp); }";
+    assert!(try_run(src)
+        .unwrap_err()
+        .contains("cannot be applied to Null"));
+}
+
+#[test]
+fn errors_reach_host() {
+    // errors are returned by `Load::call` the same way as by `invoke`
+    let src = "func main() { return [1, 2].get(5); }";
+    let mut machine = load(&compile(src, 0, &[]).unwrap(), vec![]);
+    let error = VmError::RuntimeError(String::from("index 5 is out of bounds for Array of size 2"));
+    assert_eq!(
+        LinkError::Runtime(error),
+        machine.call("main", &[]).unwrap_err()
+    );
+    assert!(machine.stack.is_empty());
+}
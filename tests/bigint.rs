@@ -0,0 +1,57 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::try_run_typed;
+
+/// `<typename> <value>` of `expr`
+fn eval(expr: &str) -> String {
+    try_run_typed(&format!("func main() {{ return {}; }}", expr)).unwrap()
+}
+
+#[test]
+fn factorial_overflows_into_bigint() {
+    let src = "
+        func factorial(n) {
+            if n < 2 { return 1; }
+            return n * factorial(n - 1);
+        }
+        func main() { return factorial(25); }
+    ";
+    assert_eq!(
+        "Int 15511210043330985984000000",
+        try_run_typed(src).unwrap()
+    );
+}
+
+#[test]
+fn overflowing_literals_arithmetic() {
+    assert_eq!("Int 9223372036854775808", eval("9223372036854775807 + 1"));
+    assert_eq!("Int 18446744073709551616", eval("2 ** 64"));
+    assert_eq!("Int 36893488147419103232", eval("1 << 65"));
+    assert_eq!("Int 1", eval("(2 ** 64) - (2 ** 64 - 1)"));
+    assert_eq!("Bool true", eval("2 ** 64 > 9223372036854775807"));
+    assert_eq!(
+        "Bool true",
+        eval("2 ** 64 == bigint(\"18446744073709551616\")")
+    );
+}
+
+#[test]
+fn conversion_builtins() {
+    assert_eq!("Int 42", eval("bigint(42)"));
+    assert_eq!(
+        "Int -123456789012345678901234567890",
+        eval("bigint(\"-123456789012345678901234567890\")")
+    );
+    assert_eq!("null null", eval("bigint(\"12x\")"));
+    assert_eq!("null null", eval("bigint(1.5)"));
+
+    assert_eq!("Int 42", eval("long(bigint(42))"));
+    assert_eq!("Int -7", eval("long(\"-7\")"));
+    assert_eq!("Int 3", eval("long(3.9)"));
+    assert_eq!("null null", eval("long(2 ** 64)"));
+    assert_eq!("null null", eval("long(\"abc\")"));
+    assert_eq!("null null", eval("long(10.0 ** 300)"));
+}
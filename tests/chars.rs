@@ -0,0 +1,98 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{error, eval_typed, try_run_typed};
+
+#[test]
+fn literals() {
+    assert_eq!("Char a", eval_typed("return 'a';"));
+    assert_eq!("Char €", eval_typed("return '€';"));
+    assert_eq!("Char \n", eval_typed("return '\\n';"));
+    assert_eq!("Char '", eval_typed("return '\\'';"));
+    assert_eq!("Char \u{263a}", eval_typed("return '\\u263a';"));
+    assert_eq!("Bool true", eval_typed("var c = 'x'; return c~Char;"));
+    assert_eq!(
+        "Char b",
+        try_run_typed(
+            "class A { var c = 'b'; func init() { return this; } }\nfunc main() { return A().c; }"
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn conversions() {
+    assert_eq!("Int 97", eval_typed("return long('a');"));
+    assert_eq!("Int 8364", eval_typed("return long('€');"));
+    assert_eq!("Char a", eval_typed("return char(97);"));
+    assert_eq!("Char z", eval_typed("return char(\"z\");"));
+    assert_eq!("null null", eval_typed("return char(-1);"));
+    assert_eq!("null null", eval_typed("return char(55296);"));
+    assert_eq!("null null", eval_typed("return char(\"ab\");"));
+    assert_eq!("Str a", eval_typed("return str('a');"));
+    assert_eq!("Str 12", eval_typed("return str(12);"));
+    assert_eq!("Str ab", eval_typed("return 'a' + \"b\";"));
+    assert_eq!("Str ab", eval_typed("return \"a\" + 'b';"));
+    assert_eq!("Str <x>", eval_typed("var c = 'x'; return \"<${c}>\";"));
+}
+
+#[test]
+fn comparisons() {
+    assert_eq!(
+        "Object [true,false,true,true,false]",
+        eval_typed("var a = 'a'; return [a == 'a', a == 'b', a != 'b', a < 'b', 'Z' > a];")
+    );
+    assert_eq!(
+        "Object [false,false]",
+        eval_typed("return ['a' == \"a\", 'a' == 97];")
+    );
+    assert_eq!(
+        "Object [false,true,false]",
+        eval_typed("var s = \"a\"; return [!'a', !'\\u0000', !s];")
+    );
+    assert!(error("return 'a' < 1;").contains("`<` cannot be applied to Char('a')"));
+    assert!(error("return 'a' + 'b';").contains("`+` cannot be applied to Char('a')"));
+    assert_eq!(
+        "Object [a,b,c]",
+        eval_typed("var a = ['c', 'a', 'b']; return a.sort();")
+    );
+    assert_eq!(
+        "Int 1",
+        eval_typed("var a = ['c', 'a', 'b']; return a.indexOf('a');")
+    );
+    assert_eq!(
+        "Int 2",
+        eval_typed("var m = {'a': 1}; m['a'] = 2; return m['a'];")
+    );
+}
+
+#[test]
+fn strings_yield_chars() {
+    assert_eq!("Char ñ", eval_typed("var s = \"añb\"; return s[1];"));
+    assert_eq!(
+        "Bool true",
+        eval_typed("var s = \"añb\"; return s[2] == 'b';")
+    );
+    assert_eq!(
+        "Object [a,ñ,b]",
+        eval_typed("var cs = \"añb\".chars(); return cs;")
+    );
+    assert_eq!(
+        "Char ñ",
+        eval_typed("var cs = \"añb\".chars(); return cs[1];")
+    );
+    let src = "
+        func main() {
+            var cs = \"añb\".chars();
+            var n = 0;
+            var i = 0;
+            while i < cs.size() {
+                if cs[i]~Char { n = n + 1; }
+                i = i + 1;
+            }
+            return n;
+        }";
+    assert_eq!("Int 3", try_run_typed(src).unwrap());
+}
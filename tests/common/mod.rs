@@ -0,0 +1,123 @@
+//! Helpers shared by compiler tests, every file uses its own subset of them
+#![allow(dead_code)]
+
+use jazz::{
+    parser::{lex, parse},
+    CompileError, Compiler, Load, Module,
+};
+use jazz_vm::{function::Function, machine::Machine, object::ObjectAddon, value::Value};
+
+/// Enum with fields and function which matches on its variants, prelude of enum and `match` tests
+pub const SHAPE: &str = "
+enum Shape { Circle(r), Rect(w, h), Empty }
+
+func area(s) {
+    if s ~ Shape.Circle { return 3 * s.r * s.r; }
+    if s ~ Shape.Rect { return s.w * s.h; }
+    return 0;
+}
+";
+
+/// Module of `src` compiled by `compiler`, which keeps names defined by previous modules
+pub fn compile_with(compiler: &mut Compiler, src: &str) -> Result<Module, Vec<CompileError>> {
+    compiler.compile(parse(&mut lex(src)).unwrap())
+}
+
+/// Module of `src` at `opt_level`, `natives` are names of globals which host defines
+pub fn compile(src: &str, opt_level: u8, natives: &[&str]) -> Result<Module, Vec<CompileError>> {
+    let mut compiler = Compiler::new(false);
+    compiler.opt_level = opt_level;
+    for name in natives {
+        compiler.defined.insert(String::from(*name));
+    }
+    compile_with(&mut compiler, src)
+}
+
+/// Machine with `module` loaded after host defined each of `natives` under its name
+pub fn load(module: &Module, natives: Vec<(&str, Function)>) -> Machine {
+    let mut machine = Machine::new();
+    for (id, (name, native)) in natives.into_iter().enumerate() {
+        let native = Value::Object(machine.pool.allocate(Box::new(native)));
+        machine.names.insert(String::from(name), id);
+        machine.globals.insert(id, native);
+    }
+    machine.load(module).unwrap();
+    machine
+}
+
+/// Result of `main` of `module`
+pub fn run_main(module: &Module) -> String {
+    let mut machine = load(module, vec![]);
+    let value = machine.call("main", &[]).unwrap();
+    value.to_String(&mut machine)
+}
+
+/// `show` of result of `main` or runtime error, same at every optimization level
+fn outcome(src: &str, show: fn(&mut Machine, Value) -> String) -> Result<String, String> {
+    let mut results = vec![];
+    for opt_level in 0..3 {
+        let mut machine = load(&compile(src, opt_level, &[]).unwrap(), vec![]);
+        let main = machine.globals[&machine.names["main"]];
+        let result = match machine.invoke(main, &[Value::Null]) {
+            Ok(value) => Ok(show(&mut machine, value)),
+            Err(e) => Err(e.to_string()),
+        };
+        results.push(result);
+    }
+    assert!(results.iter().all(|r| *r == results[0]), "{:?}", results);
+    results.remove(0)
+}
+
+/// Result of `main` or runtime error, same at every optimization level
+pub fn try_run(src: &str) -> Result<String, String> {
+    outcome(src, |m, value| value.to_String(m))
+}
+
+/// `<typename> <value>` of `main` or runtime error, same at every optimization level
+pub fn try_run_typed(src: &str) -> Result<String, String> {
+    outcome(src, |m, value| {
+        format!("{} {}", value.typename(m), value.to_String(m))
+    })
+}
+
+/// `try_run` of `src` with declarations of `prelude` before it
+pub fn run_with_prelude(prelude: &str, src: &str) -> Result<String, String> {
+    try_run(&format!("{}{}", prelude, src))
+}
+
+/// Source of `main` with `body`
+pub fn main(body: &str) -> String {
+    format!("func main() {{ {} }}", body)
+}
+
+pub fn eval(body: &str) -> String {
+    eval_with_prelude("", body)
+}
+
+/// `<typename> <value>` of `main` with `body`
+pub fn eval_typed(body: &str) -> String {
+    eval_typed_with_prelude("", body)
+}
+
+pub fn error(body: &str) -> String {
+    error_with_prelude("", body)
+}
+
+pub fn eval_with_prelude(prelude: &str, body: &str) -> String {
+    run_with_prelude(prelude, &main(body)).unwrap()
+}
+
+pub fn eval_typed_with_prelude(prelude: &str, body: &str) -> String {
+    try_run_typed(&format!("{}{}", prelude, main(body))).unwrap()
+}
+
+pub fn error_with_prelude(prelude: &str, body: &str) -> String {
+    run_with_prelude(prelude, &main(body)).unwrap_err()
+}
+
+pub fn parse_error(src: &str) -> String {
+    match parse(&mut lex(src)) {
+        Ok(ast) => panic!("{:?}", ast),
+        Err(e) => e.to_string(),
+    }
+}
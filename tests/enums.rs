@@ -0,0 +1,99 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{error_with_prelude, eval_typed_with_prelude, parse_error, SHAPE};
+use jazz::parser::{lex, parse};
+
+#[test]
+fn constructors() {
+    assert_eq!(
+        "Shape.Circle Shape.Circle(2)",
+        eval_typed_with_prelude(SHAPE, "return Shape.Circle(2);")
+    );
+    assert_eq!(
+        "Shape.Rect Shape.Rect(3, 4.5)",
+        eval_typed_with_prelude(SHAPE, "return Shape.Rect(3, 4.5);")
+    );
+    assert_eq!(
+        "Shape.Empty Shape.Empty",
+        eval_typed_with_prelude(SHAPE, "return Shape.Empty;")
+    );
+    assert_eq!(
+        "Shape.Rect Shape.Rect(w, h)",
+        eval_typed_with_prelude(SHAPE, "return Shape.Rect;")
+    );
+    assert_eq!(
+        "Shape enum Shape",
+        eval_typed_with_prelude(SHAPE, "return Shape;")
+    );
+    assert_eq!(
+        "Object [Shape.Circle(1),Shape.Empty]",
+        eval_typed_with_prelude(SHAPE, "return [Shape.Circle(1), Shape.Empty];")
+    );
+    assert_eq!(
+        "Str got Shape.Circle(1)",
+        eval_typed_with_prelude(SHAPE, "var c = Shape.Circle(1); return \"got ${c}\";")
+    );
+}
+
+#[test]
+fn fields_and_isa() {
+    assert_eq!(
+        "Int 4",
+        eval_typed_with_prelude(SHAPE, "var r = Shape.Rect(3, 4); return r.h;")
+    );
+    assert_eq!(
+        "Object [12,12,0]",
+        eval_typed_with_prelude(
+            SHAPE,
+            "return [area(Shape.Circle(2)), area(Shape.Rect(3, 4)), area(Shape.Empty)];"
+        )
+    );
+    assert_eq!(
+        "Object [true,false,true,false]",
+        eval_typed_with_prelude(SHAPE, "var c = Shape.Circle(1); return [c ~ Shape.Circle, c ~ Shape.Rect, c ~ Shape, c ~ Int];")
+    );
+    assert_eq!(
+        "Object [true,true,true]",
+        eval_typed_with_prelude(
+            SHAPE,
+            "var e = Shape.Empty; return [e ~ Shape.Empty, e ~ Shape, e == Shape.Empty];"
+        )
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        "Runtime Error: `Shape has no variant `Square``",
+        error_with_prelude(SHAPE, "return Shape.Square;")
+    );
+    assert_eq!(
+        "Runtime Error: `Shape.Circle has no field `x``",
+        error_with_prelude(SHAPE, "var c = Shape.Circle(1); return c.x;")
+    );
+    assert_eq!(
+        "Runtime Error: `Shape.Rect expects 2 values, found 1`",
+        error_with_prelude(SHAPE, "return Shape.Rect(1);")
+    );
+    assert_eq!(
+        "Runtime Error: `values of Shape.Circle are immutable`",
+        error_with_prelude(SHAPE, "var c = Shape.Circle(1); c.r = 3; return c;")
+    );
+    assert_eq!(
+        "Runtime Error: `Shape doesn't support assignment to fields or elements`",
+        error_with_prelude(SHAPE, "var s = Shape; s[0] = 3; return s;")
+    );
+    assert_eq!(
+        "Enum variant is declared twice",
+        parse_error("enum E { A, B, A }")
+    );
+    assert_eq!(
+        "Enum variant must be a name with optional parameters",
+        parse_error("enum E { A(1) }")
+    );
+    assert_eq!("Enum missing name", parse_error("enum { A }"));
+    assert!(parse(&mut lex("enum E { A, B(x, y), }\nenum F {}")).is_ok());
+}
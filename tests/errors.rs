@@ -0,0 +1,113 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::compile;
+use jazz::{
+    parser::{lex, parse, Position},
+    CompileError,
+};
+
+fn pos(line: usize, column: usize) -> Position {
+    Position { line, column }
+}
+
+#[test]
+fn undefined_names() {
+    assert_eq!(
+        vec![
+            CompileError::UndefinedName {
+                name: "x".to_owned(),
+                pos: pos(2, 12),
+            },
+            CompileError::UndefinedName {
+                name: "y".to_owned(),
+                pos: pos(3, 5),
+            },
+            CompileError::UndefinedName {
+                name: "f".to_owned(),
+                pos: pos(4, 12),
+            },
+        ],
+        compile(
+            "func main() {\n    return x;\n    y = 1;\n    return f(1);\n}",
+            0,
+            &[]
+        )
+        .err()
+        .unwrap()
+    );
+}
+
+#[test]
+fn errors_of_every_function() {
+    let errors = compile(
+        "func a() { return x; }\nfunc b() { loop { } }\nfunc main() { return this.(1); }",
+        0,
+        &[],
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        vec![
+            CompileError::UndefinedName {
+                name: "x".to_owned(),
+                pos: pos(1, 19),
+            },
+            CompileError::Unsupported {
+                construct: "`loop`",
+                pos: pos(2, 12),
+            },
+            CompileError::InvalidAccess { pos: pos(3, 26) },
+        ],
+        errors
+    );
+}
+
+#[test]
+fn display() {
+    let messages: Vec<String> =
+        compile("func f() { return z; }\nfunc g() { this.(1) = 2; }", 0, &[])
+            .err()
+            .unwrap()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+    assert_eq!(
+        vec![
+            "1:19: `z` is not defined",
+            "2:16: expected field name or method call after `.`"
+        ],
+        messages
+    );
+}
+
+#[test]
+fn global_variables() {
+    assert_eq!(
+        vec![CompileError::Unsupported {
+            construct: "global `var`",
+            pos: pos(1, 5),
+        }],
+        compile("var g = 1;\nfunc main() { return 1; }", 0, &[])
+            .err()
+            .unwrap()
+    );
+}
+
+#[test]
+fn parse_errors_stop_at_position() {
+    let mut tokens = lex("func main() {\n    return (1;\n}");
+    let error = parse(&mut tokens).unwrap_err();
+    assert_eq!(
+        "3:1: Expected ')'",
+        format!("{}: {}", tokens.position(), error)
+    );
+    let mut tokens = lex("return 1;");
+    let error = parse(&mut tokens).unwrap_err();
+    assert_eq!(
+        "1:1: Expected function, class, enum or variable declaration",
+        format!("{}: {}", tokens.position(), error)
+    );
+}
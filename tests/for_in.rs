@@ -0,0 +1,174 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{error_with_prelude, eval_with_prelude, parse_error, run_with_prelude};
+
+/// Class that iterates over numbers below `n` by itself and class that gives array to iterate over
+const CLASSES: &str = "
+class Countdown {
+    var n;
+    func init(n) {
+        this.n = n;
+        return this;
+    }
+    func iter() { return this; }
+    func next() {
+        if this.n == 0 { return null; }
+        this.n = this.n - 1;
+        return this.n;
+    }
+}
+
+class Bag {
+    var items;
+    func init() {
+        this.items = [];
+        return this;
+    }
+    func add(x) {
+        var items = this.items;
+        items.push(x);
+        return this;
+    }
+    func iter() { return this.items; }
+}
+";
+
+/// Elements that `for x in iterable` visits
+fn visit(iterable: &str) -> String {
+    let src = format!(
+        "func main() {{
+            var visited = [];
+            for x in {} {{
+                visited.push(x);
+            }}
+            return visited;
+        }}",
+        iterable
+    );
+    run_with_prelude(CLASSES, &src).unwrap()
+}
+
+#[test]
+fn arrays_strings_and_maps() {
+    assert_eq!("[1,two,3.5]", visit("[1, \"two\", 3.5]"));
+    assert_eq!("[]", visit("[]"));
+    assert_eq!("[a,ñ,b]", visit("\"añb\""));
+    assert_eq!("[]", visit("\"\""));
+    assert_eq!("[a,1,true]", visit("{\"a\": 1, 1: 2, true: 3}"));
+    assert_eq!(
+        "6",
+        eval_with_prelude(CLASSES, "var m = {\"a\": 1, \"b\": 2, \"c\": 3}; var sum = 0; for k in m { sum = sum + m[k]; } return sum;")
+    );
+}
+
+#[test]
+fn ranges() {
+    assert_eq!("[0,1,2,3,4]", visit("0..5"));
+    assert_eq!("[0,1,2,3,4,5]", visit("0..=5"));
+    assert_eq!("[]", visit("5..0"));
+    assert_eq!("[5]", visit("5..=5"));
+    assert_eq!("[-2,-1,0]", visit("-2..1"));
+    assert_eq!("[0,3,6,9]", visit("(0..10).step(3)"));
+    assert_eq!("[0,5,10]", visit("(0..=10).step(5)"));
+    assert_eq!("[5,4,3,2,1]", visit("(5..0).step(-1)"));
+    assert_eq!("[10,7,4,1]", visit("(10..=0).step(-3)"));
+    assert_eq!(
+        "[2,3,4]",
+        eval_with_prelude(CLASSES, "var n = 2; return (n..n + 3).toArray();")
+    );
+    assert_eq!("0..3", eval_with_prelude(CLASSES, "return 0..3;"));
+    assert_eq!(
+        "(1..=9).step(2)",
+        eval_with_prelude(CLASSES, "return (1..=9).step(2);")
+    );
+    assert_eq!(
+        "true",
+        eval_with_prelude(CLASSES, "var r = 1..4; return r ~ Range;")
+    );
+}
+
+#[test]
+fn classes() {
+    assert_eq!("[2,1,0]", visit("Countdown(3)"));
+    assert_eq!(
+        "ab",
+        eval_with_prelude(CLASSES, "var b = Bag(); b.add('a'); b.add('b'); var s = \"\"; for c in b { s = s + c; } return s;")
+    );
+    // `null` from `next()` ends iteration, `iter()` that returns array can give `null` elements
+    assert_eq!("[]", visit("Countdown(0)"));
+    assert_eq!(
+        "[1,null,2]",
+        eval_with_prelude(CLASSES, "var b = Bag(); b.add(1); b.add(null); b.add(2); var r = []; for x in b { r.push(x); } return r;")
+    );
+}
+
+#[test]
+fn loop_variable_and_nesting() {
+    assert_eq!(
+        "[11,12,21,22]",
+        eval_with_prelude(
+            CLASSES,
+            "var r = []; for i in 1..3 { for j in 1..3 { r.push(i * 10 + j); } } return r;"
+        )
+    );
+    assert_eq!(
+        "x",
+        eval_with_prelude(
+            CLASSES,
+            "var x = \"x\"; for x in [1, 2] { x = x + 1; } return x;"
+        )
+    );
+    assert_eq!(
+        "[0,1,2,3]",
+        eval_with_prelude(
+            CLASSES,
+            "var a = [0, 1]; for x in a { if x < 2 { a.push(x + 2); } } return a;"
+        )
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        "Runtime Error: `Int is not iterable`",
+        error_with_prelude(CLASSES, "for x in 3 { }")
+    );
+    assert_eq!(
+        "Runtime Error: `null is not iterable`",
+        error_with_prelude(CLASSES, "for x in null { }")
+    );
+    assert_eq!(
+        "Runtime Error: `Shape is not iterable`",
+        run_with_prelude(
+            CLASSES,
+            "class Shape { func init() { return this; } }\nfunc main() { for x in Shape() { } }"
+        )
+        .unwrap_err()
+    );
+    assert_eq!(
+        "Runtime Error: `range bound must be integer, found Double(1.5)`",
+        error_with_prelude(CLASSES, "return 0..1.5;")
+    );
+    assert_eq!(
+        "Runtime Error: `range step must not be zero`",
+        error_with_prelude(CLASSES, "return (0..3).step(0);")
+    );
+    assert_eq!(
+        "Runtime Error: `range is immutable`",
+        error_with_prelude(CLASSES, "var r = 0..3; r[0] = 1; return r;")
+    );
+    assert_eq!(
+        "Runtime Error: `division by zero`",
+        run_with_prelude(CLASSES,
+            "class Bad { func init() { return this; } func next() { return 1 / 0; } }\nfunc main() { for x in Bad() { } }"
+        )
+        .unwrap_err()
+    );
+    assert_eq!(
+        "Expected 'in'",
+        parse_error("func main() { for x of [1] { } }")
+    );
+}
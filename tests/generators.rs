@@ -0,0 +1,223 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{error_with_prelude, eval_with_prelude, run_with_prelude};
+
+/// Infinite, finite and class method generators, `holes` yields `null`
+const GENERATORS: &str = "
+func naturals() {
+    var i = 0;
+    while true {
+        yield i;
+        i += 1;
+    }
+}
+
+func upto(n) {
+    for i in 1..=n {
+        yield i;
+    }
+    return \"ignored\";
+}
+
+func evens(g) {
+    for x in g {
+        if x % 2 == 0 { yield x; }
+    }
+}
+
+func double(x, i) { return x * 2; }
+
+func holes() {
+    yield null;
+    yield 1;
+    yield null;
+}
+
+class Tree {
+    var items;
+    func init(items) {
+        this.items = items;
+        return this;
+    }
+    func walk() {
+        var items = this.items;
+        for x in items {
+            if x ~ Int {
+                yield x;
+            } else {
+                for y in Tree(x).walk() { yield y; }
+            }
+        }
+    }
+}
+";
+
+#[test]
+fn next_and_finish() {
+    assert_eq!(
+        "[1,2,3,null,null]",
+        eval_with_prelude(
+            GENERATORS,
+            "var g = upto(3); return [g.next(), g.next(), g.next(), g.next(), g.next()];"
+        )
+    );
+    assert_eq!(
+        "<generator>",
+        eval_with_prelude(GENERATORS, "return naturals();")
+    );
+    assert_eq!(
+        "true",
+        eval_with_prelude(GENERATORS, "var g = naturals(); return g ~ Generator;")
+    );
+    assert_eq!(
+        "[0,1,0]",
+        eval_with_prelude(GENERATORS, "var a = naturals(); var b = naturals(); a.next(); return [a.next() - 1, a.next() - 1, b.next()];")
+    );
+    assert_eq!(
+        "[null,null]",
+        run_with_prelude(
+            GENERATORS,
+            "func f() { yield; } func main() { var g = f(); return [g.next(), g.next()]; }"
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn yielded_null_is_not_finish() {
+    assert_eq!(
+        "[null,false,1,false,null,false,null,true]",
+        eval_with_prelude(GENERATORS, "var g = holes(); var r = []; for i in 0..4 { r.push(g.next()); r.push(g.done()); } return r;")
+    );
+    assert_eq!(
+        "[null,1,null]",
+        eval_with_prelude(GENERATORS, "return holes().toArray();")
+    );
+    assert_eq!(
+        "[null,1,null]",
+        eval_with_prelude(
+            GENERATORS,
+            "var r = []; for x in holes() { r.push(x); } return r;"
+        )
+    );
+    assert_eq!(
+        "[null,1]",
+        eval_with_prelude(GENERATORS, "return holes().take(2);")
+    );
+    assert_eq!(
+        "[false,true]",
+        eval_with_prelude(
+            GENERATORS,
+            "var g = upto(0); return [g.done(), g.next() == null && g.done()];"
+        )
+    );
+}
+
+#[test]
+fn returned_value_is_dropped() {
+    let src = "func record(log) { log.push(\"returned\"); return 42; }
+        func g(log) { yield 1; return record(log); }
+        func main() {
+            var log = [];
+            var it = g(log);
+            var all = g(log).toArray();
+            return [it.next(), it.next(), it.next(), all, log];
+        }";
+    assert_eq!(
+        "[1,null,null,[1],[returned,returned]]",
+        run_with_prelude(GENERATORS, src).unwrap()
+    );
+}
+
+#[test]
+fn loops_and_array_helpers() {
+    assert_eq!(
+        "[0,1,2,3,4]",
+        eval_with_prelude(
+            GENERATORS,
+            "var r = []; for x in naturals() { if x == 5 { return r; } r.push(x); }"
+        )
+    );
+    assert_eq!(
+        "[0,1,2,3]",
+        eval_with_prelude(GENERATORS, "return naturals().take(4);")
+    );
+    assert_eq!(
+        "[1,2,3]",
+        eval_with_prelude(GENERATORS, "return upto(3).take(10);")
+    );
+    assert_eq!(
+        "[]",
+        eval_with_prelude(GENERATORS, "return upto(0).toArray();")
+    );
+    assert_eq!(
+        "[2,4,6]",
+        eval_with_prelude(
+            GENERATORS,
+            "var a = upto(3).toArray(); return a.map(double);"
+        )
+    );
+    assert_eq!(
+        "[0,2,4]",
+        eval_with_prelude(GENERATORS, "return evens(naturals()).take(3);")
+    );
+    assert_eq!(
+        "15",
+        eval_with_prelude(
+            GENERATORS,
+            "var s = 0; for x in upto(5) { s = s + x; } return s;"
+        )
+    );
+    assert_eq!(
+        "[1,2,3,4,5]",
+        eval_with_prelude(
+            GENERATORS,
+            "var g = Tree([1, [2, [3, 4]], 5]).walk(); return g.toArray();"
+        )
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        "Runtime Error: `division by zero`",
+        run_with_prelude(
+            GENERATORS,
+            "func bad() { yield 1; yield 1 / 0; yield 3; }
+            func main() { return bad().toArray(); }"
+        )
+        .unwrap_err()
+    );
+    assert_eq!(
+        "Runtime Error: `generator is already running`",
+        run_with_prelude(
+            GENERATORS,
+            "class Selfish {
+                var g;
+                func init() { return this; }
+                func run() {
+                    var g = this.g;
+                    yield g.next();
+                }
+            }
+            func main() {
+                var s = Selfish();
+                var g = s.run();
+                s.g = g;
+                return g.next();
+            }"
+        )
+        .unwrap_err()
+    );
+    assert_eq!(
+        "Runtime Error: `Generator has no method `size``",
+        error_with_prelude(GENERATORS, "return naturals().size();")
+    );
+    assert_eq!(
+        "Runtime Error: `Generator::take expects non-negative integer, found Long(-1)`",
+        error_with_prelude(GENERATORS, "return naturals().take(-1);")
+    );
+}
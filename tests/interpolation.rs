@@ -0,0 +1,106 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{eval_with_prelude, parse_error, run_with_prelude};
+
+/// Point which defines `toString` with interpolation
+const POINT: &str = "
+class Point {
+    var x;
+    var y;
+    func init(a, b) {
+        this.x = a;
+        this.y = b;
+        return this;
+    }
+    func toString() { return \"Point(${this.x}, ${this.y})\"; }
+}
+";
+
+#[test]
+fn expressions() {
+    assert_eq!(
+        "1 + 2 = 3",
+        eval_with_prelude(POINT, "var a = 1; return \"${a} + 2 = ${a + 2}\";")
+    );
+    assert_eq!(
+        "[1,2] has 2",
+        eval_with_prelude(POINT, "var a = [1, 2]; return \"${a} has ${a.size()}\";")
+    );
+    assert_eq!(
+        "HI!",
+        eval_with_prelude(POINT, "var s = \"hi\"; return \"${s.toUpper()}!\";")
+    );
+    assert_eq!("x{}y", eval_with_prelude(POINT, "return \"x${ {} }y\";"));
+    assert_eq!(
+        "a} b",
+        eval_with_prelude(POINT, "return \"${\"a}\"} ${\"b\"}\";")
+    );
+    assert_eq!(
+        "outer inner 1",
+        eval_with_prelude(POINT, "var n = 1; return \"outer ${\"inner ${n}\"}\";")
+    );
+    assert_eq!(
+        "${a} $5 \u{263a}",
+        eval_with_prelude(POINT, "return \"\\${a} $5 \\u263a\";")
+    );
+    assert_eq!(
+        "null true",
+        eval_with_prelude(POINT, "return \"${null} ${true}\";")
+    );
+}
+
+#[test]
+fn to_string_method() {
+    assert_eq!(
+        "Point(2, -2)",
+        eval_with_prelude(POINT, "return Point(2, -2).toString();")
+    );
+    assert_eq!(
+        "at Point(1, 3)",
+        eval_with_prelude(POINT, "var p = Point(1, 3); return \"at ${p}\";")
+    );
+    assert_eq!(
+        "Runtime Error: `division by zero`",
+        run_with_prelude(POINT, "class Bad { func init() { return this; } func toString() { return 1 / 0; } }\nfunc main() { var b = Bad(); return \"${b}\"; }")
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn multi_line_and_raw() {
+    assert_eq!(
+        "first\n  second 2\n\"quoted\"",
+        eval_with_prelude(
+            POINT,
+            "var n = 2; return \"\"\"\nfirst\n  second ${n}\n\"quoted\"\"\"\";"
+        )
+    );
+    assert_eq!(
+        "a\\nb ${x}",
+        eval_with_prelude(POINT, "return r\"a\\nb ${x}\";")
+    );
+    assert_eq!(
+        "raw \"q\" \\t",
+        eval_with_prelude(POINT, "return r\"\"\"raw \"q\" \\t\"\"\";")
+    );
+    assert_eq!("r", eval_with_prelude(POINT, "var r = \"r\"; return r;"));
+}
+
+#[test]
+fn malformed() {
+    assert_eq!(
+        "Interpolation must contain a single expression",
+        parse_error("func main() { return \"${1 2}\"; }")
+    );
+    assert_eq!(
+        "Interpolation must contain a single expression",
+        parse_error("func main() { return \"${}\"; }")
+    );
+    assert_eq!(
+        "Unparseable characters in the input stream",
+        parse_error("func main() { return \"${1\"; }")
+    );
+}
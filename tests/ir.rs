@@ -0,0 +1,131 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::try_run;
+use jazz::{
+    ir::{self, BlockId, Const, Function, Inst, InstKind, Terminator, ValidateError, ValueId},
+    parser::{lex, parse, FnDef, Global},
+};
+use std::collections::HashMap;
+
+fn function(src: &str) -> FnDef {
+    let parsed = parse(&mut lex(src)).unwrap();
+    parsed
+        .into_iter()
+        .filter_map(|g| match g {
+            Global::FnDefenition(fun) => Some(fun),
+            _ => None,
+        })
+        .next()
+        .unwrap()
+}
+
+fn build(src: &str) -> Function {
+    let func = ir::build("f", &function(src), &HashMap::new()).unwrap();
+    ir::validate(&func).unwrap();
+    func
+}
+
+#[test]
+fn straight_line() {
+    let func = build("func f(a) { var b = a + 1; return b * a; }");
+    assert_eq!(
+        "func f:\nb0(v0, v1):\n    v2 = const 1\n    v3 = add v1, v2\n    v4 = mul v3, v1\n    return v4\n",
+        func.to_string()
+    );
+}
+
+#[test]
+fn loop_variables_are_block_params() {
+    let func = build("func f(n) { var i = 0; while (i < n) { i = i + 1; } return i; }");
+    let params: Vec<usize> = func.blocks.iter().map(|b| b.params.len()).collect();
+    // entry takes `this` and `n`, loop header takes `i` only
+    assert_eq!(2, params[0]);
+    assert_eq!(1, params.iter().skip(1).filter(|&&n| n == 1).count());
+    assert!(func.to_string().contains("lt "));
+}
+
+#[test]
+fn unchanged_variables_are_not_params() {
+    let func = build("func f(a, b) { if (a) { b = 1; } return a; }");
+    assert!(func.blocks.iter().skip(1).all(|b| b.params.len() <= 1));
+    let join = func
+        .blocks
+        .iter()
+        .find(|b| b.term == Some(Terminator::Return(Some(ValueId(1)))));
+    assert!(join.is_some());
+}
+
+#[test]
+fn rejects_wrong_argument_count() {
+    let mut func = Function::new("f", 0);
+    let target = func.new_block();
+    let param = func.new_value();
+    func.block_mut(target).params.push(param);
+    func.block_mut(target).term = Some(Terminator::Return(Some(param)));
+    func.block_mut(BlockId(0)).term = Some(Terminator::Jump(target, vec![]));
+    assert_eq!(
+        Err(ValidateError::ArgumentCount {
+            block: BlockId(0),
+            target,
+            expected: 1,
+            found: 0,
+        }),
+        ir::validate(&func)
+    );
+}
+
+#[test]
+fn rejects_use_before_definition() {
+    let mut func = Function::new("f", 0);
+    let a = func.new_value();
+    let b = func.new_value();
+    let entry = func.block_mut(BlockId(0));
+    entry.insts.push(Inst {
+        value: Some(a),
+        kind: InstKind::Binary(ir::BinOp::Add, b, b),
+    });
+    entry.insts.push(Inst {
+        value: Some(b),
+        kind: InstKind::Const(Const::Long(1)),
+    });
+    entry.term = Some(Terminator::Return(Some(a)));
+    assert_eq!(
+        Err(ValidateError::Undefined {
+            block: BlockId(0),
+            value: b,
+        }),
+        ir::validate(&func)
+    );
+}
+
+#[test]
+fn rejects_missing_terminator() {
+    let func = Function::new("f", 1);
+    assert_eq!(
+        Err(ValidateError::MissingTerminator(BlockId(0))),
+        ir::validate(&func)
+    );
+}
+
+#[test]
+fn if_else() {
+    assert_eq!("-90", try_run("func sign(x) { var s = 0; if (x < 0) { s = 0 - 1; } else { if (x > 0) { s = 1; } } return s; }
+         func main() { return sign(0 - 5) * 100 + sign(7) * 10 + sign(0); }").unwrap());
+}
+
+#[test]
+fn swap_in_loop() {
+    assert_eq!("832040", try_run("func fib(n) { var a = 0; var b = 1; for (var i = 0; i < n; i += 1) { var t = a; a = b; b = t + b; } return a; }
+         func main() { return fib(30); }").unwrap());
+}
+
+#[test]
+fn uninitialized_variable_is_null() {
+    assert_eq!(
+        "2",
+        try_run("func main() { var a; var b = 1; if (b == 1) { a = 2; } return a; }").unwrap()
+    );
+}
@@ -0,0 +1,80 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::run_with_prelude;
+
+/// Counter of calls, `hit` increments it and returns `result`
+const COUNTER: &str = "
+class Counter {
+    var n = 0;
+    func init() { return this; }
+    func hit(result) { this.n = this.n + 1; return result; }
+}
+";
+
+#[test]
+fn right_operand_is_skipped() {
+    let src = "func main() {
+        var c = Counter();
+        var a = false && c.hit(true);
+        var b = true || c.hit(false);
+        return concat(c.n, a, b);
+    }";
+    assert_eq!("0falsetrue", run_with_prelude(COUNTER, src).unwrap());
+}
+
+#[test]
+fn right_operand_is_evaluated() {
+    let src = "func main() {
+        var c = Counter();
+        var a = true && c.hit(false);
+        var b = false || c.hit(true);
+        return concat(c.n, a, b);
+    }";
+    assert_eq!("2falsetrue", run_with_prelude(COUNTER, src).unwrap());
+}
+
+#[test]
+fn chains() {
+    let src = "func main() {
+        var c = Counter();
+        var a = c.hit(true) && c.hit(false) && c.hit(true);
+        var b = c.hit(false) || c.hit(false) || c.hit(true) || c.hit(true);
+        return concat(c.n, a, b);
+    }";
+    assert_eq!("5falsetrue", run_with_prelude(COUNTER, src).unwrap());
+}
+
+#[test]
+fn in_conditions() {
+    let src = "func fib(n) { if (n == 0 || n == 1) { return n; } return fib(n - 1) + fib(n - 2); }
+               func main() {
+                   var c = Counter();
+                   var i = 0;
+                   while (i < 10 && c.hit(true)) { i = i + 1; }
+                   return concat(fib(15), \" \", c.n);
+               }";
+    assert_eq!("610 10", run_with_prelude(COUNTER, src).unwrap());
+}
+
+#[test]
+fn operands_of_any_type() {
+    let src = "func main() {
+        var c = Counter();
+        var s = \"s\";
+        return [null || 5, 0 && c.hit(1), 2 && s, 0.0 || null, '\\u0000' || 'a', [] && c.hit(3), c.n];
+    }";
+    assert_eq!(
+        "[5,0,s,null,a,3,1]",
+        run_with_prelude(COUNTER, src).unwrap()
+    );
+    let src = "func main() {
+        var a = [];
+        if a && a.size() == 0 { a.push(1); }
+        if null || a.size() == 1 { a.push(2); }
+        return a;
+    }";
+    assert_eq!("[1,2]", run_with_prelude(COUNTER, src).unwrap());
+}
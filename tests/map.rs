@@ -0,0 +1,100 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{error, eval};
+
+#[test]
+fn literals() {
+    assert_eq!("{}", eval("return {};"));
+    assert_eq!("{a: 1, b: 2}", eval("return {\"a\": 1, \"b\": 1 + 1};"));
+    assert_eq!(
+        "{1: x, y: {z: -1}}",
+        eval("return {1: \"x\", \"y\": {\"z\": -1},};")
+    );
+}
+
+#[test]
+fn index_get_and_set() {
+    let src = "
+        var m = {\"a\": 1};
+        m[\"a\"] += 10;
+        m[\"b\"] = 2;
+        return [m[\"a\"], m[\"b\"], m[\"missing\"], m];
+    ";
+    assert_eq!("[11,2,null,{a: 11, b: 2}]", eval(src));
+}
+
+#[test]
+fn key_equality() {
+    // integers of any width and floats equal to them are the same key
+    assert_eq!(
+        "{1: c}",
+        eval("var m = {1: \"a\"}; m[1.0] = \"b\"; m[bigint(1)] = \"c\"; return m;")
+    );
+    // strings by content, other objects by identity
+    let src = "
+        var a = [];
+        var m = {\"k\": 1, a: 2};
+        m[concat(\"k\")] = 3;
+        m[[]] = 4;
+        return [m.size(), m[\"k\"], m[a]];
+    ";
+    assert_eq!("[3,3,2]", eval(src));
+    assert_eq!(
+        "[1,2,3]",
+        eval("var m = {true: 1, null: 2, 0.5: 3}; return [m[true], m[null], m[0.5]];")
+    );
+}
+
+#[test]
+fn methods() {
+    let src = "
+        var m = {\"x\": 1, \"y\": 2, \"z\": 3};
+        var removed = m.remove(\"y\");
+        var missing = m.remove(\"y\");
+        return [removed, missing, m.has(\"x\"), m.has(\"y\"), m.size(), m.keys(), m.values()];
+    ";
+    assert_eq!("[2,null,true,false,2,[x,z],[1,3]]", eval(src));
+    // keys named like methods hide them, `m[k]`, `get` and `set` reach any key
+    let src = "
+        var m = {\"size\": 10};
+        m.set(\"keys\", 20);
+        return [m[\"size\"], m.size, m.get(\"keys\"), m.values()];
+    ";
+    assert_eq!("[10,10,20,[10,20]]", eval(src));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        "false",
+        eval("var m = {null: 1}; m.remove(null); return m.has();")
+    );
+    assert_eq!(
+        "Runtime Error: `Map::size expects Map as this`",
+        error("var m = {}; var f = m.size; return f();")
+    );
+    assert_eq!(
+        "Runtime Error: `Map::get expects Map as this`",
+        error("var m = {}; var f = m.get; return f(1);")
+    );
+}
+
+#[test]
+fn insertion_order() {
+    let src = "
+        var m = {};
+        var i = 5;
+        while i > 0 {
+            m[i] = i * i;
+            i -= 1;
+        }
+        m[3] = 0;
+        m.remove(4);
+        m[4] = 1;
+        return [m.keys(), m.values()];
+    ";
+    assert_eq!("[[5,3,2,1,4],[25,0,4,1,1]]", eval(src));
+}
@@ -0,0 +1,151 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{parse_error, run_with_prelude, SHAPE};
+
+/// Results of `describe` applied to each of `values`
+fn describe(arms: &str, values: &str) -> String {
+    let src = format!(
+        "func describe(v) {{ match v {{ {} }} }}
+        func main() {{
+            var values = [{}];
+            var results = [];
+            var i = 0;
+            while i < values.size() {{
+                var v = values[i];
+                results.push(describe(v));
+                i = i + 1;
+            }}
+            return results;
+        }}",
+        arms, values
+    );
+    run_with_prelude(SHAPE, &src).unwrap()
+}
+
+#[test]
+fn literals_and_wildcard() {
+    let arms = "
+        0 => return \"zero\";
+        -1 => return \"minus one\";
+        1.5 => return \"one and a half\";
+        \"a\" => return \"string a\";
+        'a' => return \"char a\";
+        true => return \"true\";
+        null => return \"null\";
+        _ => return \"other\";";
+    assert_eq!(
+        "[zero,minus one,one and a half,string a,char a,true,null,other,other]",
+        describe(arms, "0, -1, 1.5, \"a\", 'a', true, null, 2, false")
+    );
+}
+
+#[test]
+fn types_and_bindings() {
+    let arms = "
+        n ~ Int if n < 0 => { return \"negative ${n}\"; }
+        n ~ Int => { return \"int ${n}\"; }
+        ~Float => { return \"float\"; }
+        s ~ Str => { return \"str ${s.len()}\"; }
+        x => { return \"something ${x}\"; }";
+    assert_eq!(
+        "[negative -2,int 3,float,str 3,something true]",
+        describe(arms, "-2, 3, 2.5, \"abc\", true")
+    );
+}
+
+#[test]
+fn arrays() {
+    let arms = "
+        [] => return \"empty\",
+        [x] => return \"one ${x}\",
+        [0, y] => return \"zero and ${y}\",
+        [x, y] if x == y => return \"pair of ${x}\",
+        [[a, b], c] => return \"nested ${a} ${b} ${c}\",
+        [x, _] => return \"starts with ${x}\",
+        _ => return \"not a pair\",";
+    assert_eq!(
+        "[empty,one 1,zero and 5,pair of 2,nested 1 2 3,starts with 1,not a pair,not a pair]",
+        describe(
+            arms,
+            "[], [1], [0, 5], [2, 2], [[1, 2], 3], [1, 3], [1, 2, 3], \"ab\""
+        )
+    );
+}
+
+#[test]
+fn enum_variants() {
+    let arms = "
+        Shape.Circle(r) => return \"circle ${r}\";
+        Shape.Rect(w, w2) if w == w2 => return \"square ${w}\";
+        Shape.Rect(w, h) => return \"rect ${w}x${h}\";
+        Shape.Empty => return \"empty\";
+        ~Shape => return \"shape\";";
+    assert_eq!(
+        "[circle 1,square 2,rect 2x3,empty]",
+        describe(
+            arms,
+            "Shape.Circle(1), Shape.Rect(2, 2), Shape.Rect(2, 3), Shape.Empty"
+        )
+    );
+    assert_eq!(
+        "[[1, a],[2, b]]",
+        describe(
+            "Shape.Rect([x, y], _) => return \"[${x}, ${y}]\";",
+            "Shape.Rect([1, 'a'], 0), Shape.Rect([2, 'b'], 0)"
+        )
+    );
+}
+
+#[test]
+fn arms_fall_through_to_code_after_match() {
+    let src = "
+        func classify(v) {
+            var kind = \"unknown\";
+            match v {
+                [a, b] => { kind = \"pair\"; }
+                Shape.Circle(r) => kind = \"circle ${r}\";
+                _ => {}
+            }
+            return kind;
+        }
+        func main() { return [classify([1, 2]), classify(Shape.Circle(5)), classify(3)]; }";
+    assert_eq!(
+        "[pair,circle 5,unknown]",
+        run_with_prelude(SHAPE, src).unwrap()
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        "Runtime Error: `no match arm for 3`",
+        run_with_prelude(
+            SHAPE,
+            "func main() { match 3 { 1 => return 1; 2 => return 2; } }"
+        )
+        .unwrap_err()
+    );
+    assert_eq!(
+        "Runtime Error: `no match arm for Shape.Empty`",
+        run_with_prelude(SHAPE, "func main() { match Shape.Empty { } }").unwrap_err()
+    );
+    assert_eq!(
+        "Runtime Error: `pattern of Shape.Rect has 1 values, variant has 2`",
+        run_with_prelude(
+            SHAPE,
+            "func main() { match Shape.Rect(1, 2) { Shape.Rect(w) => return w; } }"
+        )
+        .unwrap_err()
+    );
+    assert_eq!(
+        "Expected '=>'",
+        parse_error("func main() { match 1 { 1 return 1; } }")
+    );
+    assert_eq!(
+        "Malformed pattern",
+        parse_error("func main() { match 1 { + => return 1; } }")
+    );
+}
@@ -0,0 +1,131 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{compile, compile_with};
+use jazz::{module::LinkError, Compiler, Load};
+use jazz_vm::{
+    machine::Machine, object::ObjectAddon, opcodes::Instruction, value::Value,
+    verifier::VerifyError,
+};
+
+#[test]
+fn compiling_does_not_run() {
+    let module = compile(
+        "func main() { print(1); return 2; } class A { var x = 1; func get() { return this.x; } }",
+        0,
+        &[],
+    )
+    .unwrap();
+    let mut names: Vec<&str> = module.definitions().collect();
+    names.sort();
+    assert_eq!(vec!["A", "main"], names);
+    assert!(module.globals.contains_key("print"));
+    assert!(!module.globals.contains_key("readln"));
+    assert_eq!(
+        vec![("x".to_owned(), jazz::module::Constant::Int(1))],
+        module.classes[0].fields
+    );
+}
+
+#[test]
+fn modules_are_linked_by_name() {
+    let mut compiler = Compiler::new(false);
+    let lib = compile_with(
+        &mut compiler,
+        "func square(x) { return x * x; } class Point { var x = 3; }",
+    )
+    .unwrap();
+    let app = compile_with(
+        &mut compiler,
+        "func main() { var p = Point; return square(p.x) + square(4); }",
+    )
+    .unwrap();
+
+    let mut machine = Machine::new();
+    machine.load(&lib).unwrap();
+    machine.load(&app).unwrap();
+    let value = machine.call("main", &[]).unwrap();
+    assert_eq!("25", value.to_String(&mut machine));
+}
+
+#[test]
+fn any_function_is_entry_point() {
+    let module = compile(
+        "func add(a, b) { return a + b; } func twice(a) { return add(a, a); }",
+        0,
+        &[],
+    )
+    .unwrap();
+    let mut machine = Machine::new();
+    machine.load(&module).unwrap();
+    let value = machine.call("twice", &[Value::Long(21)]).unwrap();
+    assert_eq!("42", value.to_String(&mut machine));
+    let value = machine
+        .call("add", &[Value::Long(2), Value::Long(3)])
+        .unwrap();
+    assert_eq!("5", value.to_String(&mut machine));
+    assert_eq!(
+        Err(LinkError::Undefined("main".to_owned())),
+        machine.call("main", &[]).map(|_| ())
+    );
+}
+
+#[test]
+fn link_errors() {
+    let mut compiler = Compiler::new(false);
+    let lib = compile_with(&mut compiler, "func helper() { return 1; }").unwrap();
+    let app = compile_with(&mut compiler, "func main() { return helper(); }").unwrap();
+
+    let mut machine = Machine::new();
+    assert_eq!(
+        Err(LinkError::Undefined("helper".to_owned())),
+        machine.load(&app)
+    );
+    assert!(machine.names.is_empty());
+    machine.load(&lib).unwrap();
+    assert_eq!(
+        Err(LinkError::Redefined("helper".to_owned())),
+        machine.load(&lib)
+    );
+    machine.load(&app).unwrap();
+    let value = machine.call("main", &[]).unwrap();
+    assert_eq!("1", value.to_String(&mut machine));
+}
+
+#[test]
+fn malformed_code_is_not_loaded() {
+    let mut compiler = Compiler::new(false);
+    let mut app = compile_with(&mut compiler, "func main() { return 2; }").unwrap();
+    let mut machine = Machine::new();
+
+    app.functions[0].code = vec![Instruction::LoadConst(1, 9999), Instruction::Ret(1)];
+    assert_eq!(
+        Err(LinkError::Verify(
+            "main".to_owned(),
+            VerifyError::UnknownConstant { ip: 0, id: 9999 }
+        )),
+        machine.load(&app)
+    );
+    app.functions[0].code = vec![Instruction::Move(300, 1), Instruction::Ret(1)];
+    assert_eq!(
+        Err(LinkError::Verify(
+            "main".to_owned(),
+            VerifyError::RegisterOutOfBounds {
+                ip: 0,
+                register: 300
+            }
+        )),
+        machine.load(&app)
+    );
+    assert!(machine.names.is_empty());
+    assert!(machine.globals.is_empty());
+
+    let mut class = compile_with(&mut compiler, "class A { func get() { return 1; } }").unwrap();
+    class.classes[0].methods[0].code = vec![Instruction::Goto(7)];
+    assert_eq!(
+        "function `get` is malformed: 0000: label `7` not found",
+        machine.load(&class).unwrap_err().to_string()
+    );
+}
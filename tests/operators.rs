@@ -0,0 +1,64 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::try_run;
+
+fn eval(expr: &str) -> String {
+    try_run(&format!("func main() {{ return {}; }}", expr)).unwrap()
+}
+
+#[test]
+fn remainder_and_integer_division() {
+    assert_eq!("1", eval("7 % 3"));
+    assert_eq!("-1", eval("-7 % 3"));
+    assert_eq!("3", eval("7 div 2"));
+    assert_eq!("-3", eval("-7 div 2"));
+    assert_eq!("1.5", eval("7.5 % 2"));
+    assert_eq!("3", eval("7.5 div 2"));
+}
+
+#[test]
+fn power() {
+    assert_eq!("1024", eval("2 ** 10"));
+    assert_eq!("0.5", eval("2 ** -1"));
+    assert_eq!("2", eval("4.0 ** 0.5"));
+    // right associative
+    assert_eq!("512", eval("2 ** 3 ** 2"));
+}
+
+#[test]
+fn unary_operators() {
+    assert_eq!("-6", eval("~5"));
+    assert_eq!("5", eval("~~5"));
+    assert_eq!("3", eval("- -3"));
+    assert_eq!("-3", eval("+-3"));
+    assert_eq!("-2.5", eval("-(1.0 + 1.5)"));
+    assert_eq!(
+        "-4",
+        try_run("func neg(x) { return -x; } func main() { return neg(4); }").unwrap()
+    );
+}
+
+#[test]
+fn precedence() {
+    assert_eq!("2", eval("2 * 3 % 4"));
+    assert_eq!("17", eval("1 + 2 ** 3 * 2"));
+    assert_eq!("7", eval("1 + 13 div 2"));
+    assert_eq!("true", eval("5 % 2 == 1"));
+    assert_eq!("true", eval("2 ~ Int"));
+}
+
+#[test]
+fn compound_assignment() {
+    let src = "func main() {
+        var x = 3;
+        x **= 3;
+        x %= 10;
+        x *= 6;
+        x /= 4;
+        return x;
+    }";
+    assert_eq!("10", try_run(src).unwrap());
+}
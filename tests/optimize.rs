@@ -0,0 +1,24 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::try_run;
+
+#[test]
+fn constants() {
+    assert_eq!(
+        "99",
+        try_run("func main() { var a = 2 * 3; var b = a + 4; return b * 10 - 1; }").unwrap()
+    );
+}
+
+#[test]
+fn loops() {
+    assert_eq!("4950", try_run("func main() { var acc = 0; for (var i = 0; i < 100; i += 1) { acc = acc + i; } return acc; }").unwrap());
+}
+
+#[test]
+fn calls() {
+    assert_eq!("12", try_run("func add(a,b) { return a + b; } func main() { var x = 5; var y = x; return add(y, 7); }").unwrap());
+}
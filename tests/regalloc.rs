@@ -0,0 +1,58 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{compile, run_main};
+use jazz::CompileError;
+use jazz_vm::function::VirtualFunction;
+
+/// Result of `main` and frame size of it
+fn run(src: &str) -> Result<(String, usize), CompileError> {
+    let module = compile(src, 0, &[]).map_err(|mut errors| errors.remove(0))?;
+    let main = module.functions.iter().find(|f| f.name == "main").unwrap();
+    let nregs = VirtualFunction::new(main.code.clone(), main.argc).nregs;
+    Ok((run_main(&module), nregs))
+}
+
+#[test]
+fn registers_of_dead_locals_are_reused() {
+    let mut src = String::from("func main() { var a0 = 1;");
+    for i in 1..400 {
+        src.push_str(&format!("var a{} = a{} + 1;", i, i - 1));
+    }
+    src.push_str("return a399; }");
+
+    let (result, nregs) = run(&src).unwrap();
+    assert_eq!("400", result);
+    assert!(nregs < 8, "frame of {} registers", nregs);
+}
+
+#[test]
+fn values_live_across_calls() {
+    let src = "func id(x) { return x; }
+               func main() { var a = 1; var b = id(2); var c = id(a + id(3)); return a + b + c + id(a); }";
+    let (result, _) = run(src).unwrap();
+    assert_eq!("8", result);
+}
+
+#[test]
+fn nested_calls() {
+    let src = "func add(a, b) { return a + b; }
+               func main() { return add(add(1, add(2, 3)), add(add(4, 5), 6)); }";
+    let (result, _) = run(src).unwrap();
+    assert_eq!("21", result);
+}
+
+#[test]
+fn too_many_registers() {
+    let args: Vec<String> = (0..300).map(|i| i.to_string()).collect();
+    let src = format!(
+        "func f() {{ return 0; }} func main() {{ return f({}); }}",
+        args.join(",")
+    );
+    match run(&src) {
+        Err(CompileError::TooManyRegisters { function, .. }) => assert_eq!("main", function),
+        r => panic!("expected error, found {:?}", r),
+    }
+}
@@ -0,0 +1,88 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{compile, try_run};
+use jazz::{parser::Position, CompileError};
+use jazz_vm::function::VirtualFunction;
+
+#[test]
+fn inner_block_shadows() {
+    assert_eq!(
+        "15",
+        try_run("func main() { var x = 1; if (x == 1) { var x = 10; x = x + 4; } return x + 14; }")
+            .unwrap()
+    );
+    assert_eq!(
+        "3",
+        try_run("func main() { var x = 1; { var x = 2; { var x = 3; return x; } } }").unwrap()
+    );
+}
+
+#[test]
+fn assignment_reaches_outer_variable() {
+    assert_eq!(
+        "10",
+        try_run("func main() { var s = 0; for (var i = 0; i < 5; i += 1) { var d = i; s = s + d; } return s; }").unwrap()
+    );
+}
+
+#[test]
+fn local_shadows_global() {
+    assert_eq!(
+        "7",
+        try_run("func f() { return 1; } func main() { var f = 7; return f; }").unwrap()
+    );
+}
+
+#[test]
+fn out_of_scope_read_is_error() {
+    assert_eq!(
+        Err(vec![
+            CompileError::UndefinedName {
+                name: "i".to_owned(),
+                pos: Position { line: 1, column: 58 },
+            },
+            CompileError::UndefinedName {
+                name: "y".to_owned(),
+                pos: Position { line: 1, column: 93 },
+            },
+        ]),
+        compile("func main() { for (var i = 0; i < 3; i += 1) { } var a = i; if (true) { var y = 1; } return y; }", 0, &[])
+        .map(|_| ())
+    );
+}
+
+#[test]
+fn redeclaration_in_same_block_is_error() {
+    assert_eq!(
+        Err(vec![CompileError::Redeclared {
+            name: "a".to_owned(),
+            pos: Position {
+                line: 1,
+                column: 30
+            },
+        }]),
+        compile(
+            "func main() { var a = 1; var a = 2; { var a = 3; } }",
+            0,
+            &[]
+        )
+        .map(|_| ())
+    );
+}
+
+#[test]
+fn registers_of_finished_blocks_are_reused() {
+    let mut src = String::from("func main() { var s = 0;");
+    for _ in 0..100 {
+        src.push_str("{ var a = s + 1; var b = a * 2; var c = b - a; s = c; }");
+    }
+    src.push_str("return s; }");
+    let module = compile(&src, 0, &[]).unwrap();
+    let main = &module.functions[0];
+    let nregs = VirtualFunction::new(main.code.clone(), main.argc).nregs;
+    assert!(nregs < 8, "frame of {} registers", nregs);
+    assert_eq!("100", try_run(&src).unwrap());
+}
@@ -0,0 +1,81 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{error, eval};
+
+#[test]
+fn operators() {
+    assert_eq!("foobar", eval("var s = \"foo\"; return s + \"bar\";"));
+    assert_eq!(
+        "[true,false,false,true]",
+        eval("var a = \"ab\"; return [a == \"ab\", a == \"abc\", a != \"ab\", a != \"b\"];")
+    );
+    assert_eq!(
+        "[true,true,false,true,true]",
+        eval("return [\"abc\" < \"abd\", \"ab\" < \"abc\", \"b\" < \"abc\", \"b\" >= \"b\", \"Z\" < \"a\"];")
+    );
+    assert!(error("return \"a\" + 1;").contains("`+` cannot be applied to Object("));
+    assert!(error("return \"a\" < 1;").contains("`<` cannot be applied to Object("));
+}
+
+#[test]
+fn unicode_indexing() {
+    let src =
+        "var s = \"añb€\"; return [s.len(), s[1], s[3], s.substring(1, 3), s.indexOf(\"b\")];";
+    assert_eq!("[4,ñ,€,ñb,2]", eval(src));
+    assert_eq!("[a,ñ,b,€]", eval("return \"añb€\".chars();"));
+    assert_eq!(
+        "Runtime Error: `index 4 is out of bounds for Str of size 4`",
+        error("var s = \"añb€\"; return s[4];")
+    );
+    assert_eq!(
+        "Runtime Error: `strings are immutable`",
+        error("var s = \"abc\"; s[0] = \"x\"; return s;")
+    );
+    assert_eq!(
+        "Runtime Error: `index 5 is out of bounds for Str of size 3`",
+        error("return \"abc\".substring(1, 5);")
+    );
+}
+
+#[test]
+fn methods() {
+    assert_eq!("[ab,,c]", eval("return \"ab,,c\".split(\",\");"));
+    assert_eq!("[a,b,c]", eval("return \" a  b\tc \".split();"));
+    assert_eq!("[x,y]", eval("return \"xy\".split(\"\");"));
+    assert_eq!("a-b-c", eval("return \"a b c\".replace(\" \", \"-\");"));
+    assert_eq!("x y", eval("return \"  x y \n\".trim();"));
+    assert_eq!(
+        "[ÉCOLE,école]",
+        eval("var s = \"École\"; return [s.toUpper(), s.toLower()];")
+    );
+    assert_eq!(
+        "[true,false,true,false]",
+        eval("var s = \"jazz\"; return [s.startsWith(\"ja\"), s.startsWith(\"zz\"), s.endsWith(\"zz\"), s.endsWith(\"j\")];")
+    );
+    assert_eq!("ababab", eval("return \"ab\".repeat(3);"));
+    assert_eq!(
+        "[-1,0]",
+        eval("return [\"abc\".indexOf(\"x\"), \"abc\".indexOf(\"\")];")
+    );
+    assert_eq!(
+        "Runtime Error: `Str has no method `size``",
+        error("return \"a\".size();")
+    );
+    assert!(error("return \"a\".repeat(-1);").contains("non-negative integer"));
+    assert!(error("return \"a\".startsWith(1);").contains("expects Str argument"));
+}
+
+#[test]
+fn parsing() {
+    assert_eq!(
+        "[42,-7,123456789012345678901234567890,null,null]",
+        eval("return [\"42\".parseInt(), \" -7 \".parseInt(), \"123456789012345678901234567890\".parseInt(), \"4x\".parseInt(), \"\".parseInt()];")
+    );
+    assert_eq!(
+        "[1.5,-20,null,null]",
+        eval("return [\"1.5\".parseFloat(), \"-2e1\".parseFloat(), \"1.5.2\".parseFloat(), \"abc\".parseFloat()];")
+    );
+}
@@ -0,0 +1,153 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::compile;
+use jazz_vm::{
+    frame::Continuation,
+    function::Function,
+    machine::{Machine, Step},
+    object::ObjectAddon,
+    value::Value,
+};
+
+/// Host native that pauses script, host resumes it once "timer" fires
+fn sleep(m: &mut Machine, _: &[Value]) -> Value {
+    m.pause();
+    Value::Null
+}
+
+/// Machine with `src` loaded and `sleep` defined by host
+fn load(src: &str, opt_level: u8) -> Machine {
+    let module = compile(src, opt_level, &["sleep"]).unwrap();
+    common::load(
+        &module,
+        vec![("sleep", Function::from_native(Box::new(sleep)))],
+    )
+}
+
+fn start(machine: &mut Machine, name: &str) -> Result<Step, String> {
+    let f = machine.globals[&machine.names[name]];
+    machine.start(f, &[Value::Null]).map_err(|e| e.to_string())
+}
+
+fn suspended(step: Step) -> Continuation {
+    match step {
+        Step::Suspended(continuation) => continuation,
+        Step::Finished(v) => panic!("finished with {:?}", v),
+    }
+}
+
+fn finished(machine: &mut Machine, step: Step) -> String {
+    match step {
+        Step::Finished(v) => v.to_String(machine),
+        Step::Suspended(_) => panic!("suspended"),
+    }
+}
+
+/// Run `main`, resuming it with each of `results` in turn
+fn run(src: &str, results: &[Value]) -> Result<String, String> {
+    let mut outcomes = vec![];
+    for opt_level in 0..3 {
+        let mut machine = load(src, opt_level);
+        let mut step = start(&mut machine, "main");
+        for result in results {
+            let continuation = suspended(step.unwrap());
+            assert!(machine.stack.is_empty());
+            step = continuation
+                .resume(&mut machine, *result)
+                .map_err(|e| e.to_string());
+        }
+        outcomes.push(step.map(|step| finished(&mut machine, step)));
+    }
+    assert!(outcomes.iter().all(|o| *o == outcomes[0]), "{:?}", outcomes);
+    outcomes.remove(0)
+}
+
+#[test]
+fn native_result_comes_from_host() {
+    let src = "func main() { var a = sleep(1); var b = sleep(2); return [a, b]; }";
+    assert_eq!(
+        "[10,20]",
+        run(src, &[Value::Long(10), Value::Long(20)]).unwrap()
+    );
+    assert_eq!(
+        "[null,x]",
+        run(
+            "func main() { var r = [sleep(1)]; r.push('x'); return r; }",
+            &[Value::Null]
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn frames_and_registers_survive() {
+    let src = "
+        func wait(n) { return sleep(n); }
+        func add(n) {
+            var before = n * 2;
+            var got = sleep(n);
+            return before + got + n;
+        }
+        func main() {
+            var x = 100;
+            var y = add(3) + wait(1);
+            return x + y;
+        }";
+    assert_eq!("144", run(src, &[Value::Long(35), Value::Long(0)]).unwrap());
+}
+
+#[test]
+fn scripts_interleave() {
+    let src = "
+        func first() { return sleep(1) + 1; }
+        func second() { var s = sleep(2); return s * 2; }";
+    let mut machine = load(src, 2);
+    let a = suspended(start(&mut machine, "first").unwrap());
+    let b = suspended(start(&mut machine, "second").unwrap());
+    let step = b.resume(&mut machine, Value::Long(21)).unwrap();
+    assert_eq!("42", finished(&mut machine, step));
+    let step = a.resume(&mut machine, Value::Long(41)).unwrap();
+    assert_eq!("42", finished(&mut machine, step));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        "Runtime Error: `division by zero`",
+        run("func main() { return sleep(1) / 0; }", &[Value::Long(1)]).unwrap_err()
+    );
+    assert_eq!(
+        "Runtime Error: `pause outside of resumable script`",
+        run(
+            "func wait(x, i) { return sleep(x); } func main() { return [1].map(wait); }",
+            &[]
+        )
+        .unwrap_err()
+    );
+    assert_eq!(
+        "Runtime Error: `pause outside of resumable script`",
+        run(
+            "func g() { yield sleep(1); } func main() { return g().next(); }",
+            &[]
+        )
+        .unwrap_err()
+    );
+
+    let mut machine = load("func main() { return sleep(1); }", 2);
+    let main = machine.globals[&machine.names["main"]];
+    assert_eq!(
+        "Runtime Error: `pause outside of resumable script`",
+        machine
+            .invoke(main, &[Value::Null])
+            .unwrap_err()
+            .to_string()
+    );
+    assert!(machine.stack.is_empty());
+    let step = start(&mut machine, "main").unwrap();
+    let continuation = suspended(step);
+    let step = continuation.resume(&mut machine, Value::Long(7)).unwrap();
+    assert_eq!("7", finished(&mut machine, step));
+}
@@ -0,0 +1,78 @@
+extern crate jazz;
+extern crate jazz_vm;
+
+mod common;
+
+use common::{compile, load};
+use jazz::{Load, Module};
+use jazz_vm::{
+    function::Function, machine::Machine, object::ObjectAddon, opcodes::Instruction, value::Value,
+};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// Self tail call and method tail call, every call of `count` and `down` calls host native `probe`
+const SRC: &str = "
+func count(n, acc) {
+    probe();
+    if n == 0 { return acc; }
+    return count(n - 1, acc + 2);
+}
+
+class Counter {
+    func init() { return this; }
+    func down(n, acc) {
+        probe();
+        if n == 0 { return acc; }
+        return this.down(n - 1, acc + 3);
+    }
+}
+
+func self_call() { return count(100000, 0); }
+func method_call() {
+    var c = Counter();
+    return c.down(100000, 0);
+}
+";
+
+/// Result of `name` and maximum depth of call stack which `probe` saw
+fn run(module: &Module, name: &str) -> (String, usize) {
+    let deepest = Arc::new(AtomicUsize::new(0));
+    let seen = deepest.clone();
+    let probe = Function::from_native(Box::new(move |m: &mut Machine, _: &[Value]| {
+        seen.fetch_max(m.stack.len(), Ordering::SeqCst);
+        Value::Null
+    }));
+    let mut machine = load(module, vec![("probe", probe)]);
+    let value = machine.call(name, &[]).unwrap();
+    assert!(machine.stack.is_empty());
+    (
+        value.to_String(&mut machine),
+        deepest.load(Ordering::SeqCst),
+    )
+}
+
+#[test]
+fn returned_calls_are_tail_calls() {
+    for opt_level in 0..3 {
+        let module = compile(SRC, opt_level, &["probe"]).unwrap();
+        let count = module.functions.iter().find(|f| f.name == "count");
+        let down = module.classes[0].methods.iter().find(|f| f.name == "down");
+        for fun in [count.unwrap(), down.unwrap()].iter() {
+            let tail_calls = fun
+                .code
+                .iter()
+                .filter(|ins| matches!(ins, Instruction::TailCall(_, _)));
+            assert_eq!(1, tail_calls.count(), "{}: {:?}", fun.name, fun.code);
+        }
+    }
+}
+
+#[test]
+fn tail_calls_run_in_constant_stack() {
+    let module = compile(SRC, 2, &["probe"]).unwrap();
+    assert_eq!((String::from("200000"), 1), run(&module, "self_call"));
+    assert_eq!((String::from("300000"), 1), run(&module, "method_call"));
+}
@@ -0,0 +1,534 @@
+//! Growable array of values
+//!
+//! Methods are native functions looked up by name, see `array_method`. Methods that take callbacks call
+//! script functions through `Machine::invoke` and stop at first error.
+
+use crate::{
+    error::VmError,
+    function::{Function, Method},
+    iterator::{not_iterable, Iterate},
+    machine::Machine,
+    numeric,
+    object::{Object, ObjectAddon},
+    object_pool::ObjectPool,
+    opcodes::Cmp,
+    value::Value,
+};
+use std::{any::Any, cell::RefCell};
+
+#[derive(Clone)]
+pub struct Array
+{
+    elements: RefCell<Vec<Value>>,
+}
+
+pub(crate) fn error(message: String) -> VmError
+{
+    VmError::RuntimeError(message)
+}
+
+/// `Array` passed as `this`
+fn this_array<'a>(m: &'a Machine, args: &[Value], method: &str) -> Result<&'a Array, VmError>
+{
+    let array = match args[0] {
+        Value::Object(id) => m.pool.get_direct_typed::<Array>(id),
+        _ => None,
+    };
+    array.ok_or_else(|| error(format!("Array::{} expects Array as this", method)))
+}
+
+/// Argument `n` of method, `null` if it's missing
+pub(crate) fn arg(args: &[Value], n: usize) -> Value
+{
+    args.get(n + 1).cloned().unwrap_or(Value::Null)
+}
+
+/// `v` as index of `what` of size `len`, `end` allows index right after last element
+pub(crate) fn index(v: Value, len: usize, end: bool, what: &str) -> Result<usize, VmError>
+{
+    let idx = match v {
+        Value::Int(i) => i64::from(i),
+        Value::Long(l) => l,
+        v => return Err(error(format!("{} index must be integer, found {:?}", what, v))),
+    };
+    let limit = if end { len + 1 } else { len };
+    if idx < 0 || idx as usize >= limit {
+        return Err(error(format!("index {} is out of bounds for {} of size {}", idx, what, len)));
+    }
+    Ok(idx as usize)
+}
+
+/// Call script function `f`, `this` is null
+fn callback(m: &mut Machine, f: Value, args: &[Value]) -> Result<Value, VmError>
+{
+    let mut call_args = vec![Value::Null];
+    call_args.extend_from_slice(args);
+    m.invoke(f, &call_args)
+}
+
+/// Result of predicate `f`
+fn test(m: &mut Machine, f: Value, args: &[Value], method: &str) -> Result<bool, VmError>
+{
+    match callback(m, f, args)? {
+        Value::Bool(b) => Ok(b),
+        v => Err(error(format!(
+            "Array::{} expects Bool from callback, found {:?}",
+            method, v
+        ))),
+    }
+}
+
+pub fn array_pop(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let array = this_array(m, args, "pop")?;
+    Ok(array.pop())
+}
+
+pub fn array_push(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let array = this_array(m, args, "push")?;
+    for v in &args[1..] {
+        array.push(*v);
+    }
+    Ok(Value::Null)
+}
+
+pub fn array_size(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let array = this_array(m, args, "size")?;
+    Ok(Value::Int(array.len() as i32))
+}
+
+pub fn array_get(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let array = this_array(m, args, "get")?;
+    let idx = index(arg(args, 0), array.len(), false, "Array")?;
+    Ok(array.get(idx))
+}
+
+pub fn array_set(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let array = this_array(m, args, "set")?;
+    let idx = index(arg(args, 0), array.len(), false, "Array")?;
+    array.set(idx, arg(args, 1));
+    Ok(Value::Null)
+}
+
+/// `a.insert(i, v)`: put `v` before element `i`, `i` can be size of array
+pub fn array_insert(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let array = this_array(m, args, "insert")?;
+    let idx = index(arg(args, 0), array.len(), true, "Array")?;
+    array.elements.borrow_mut().insert(idx, arg(args, 1));
+    Ok(Value::Null)
+}
+
+/// `a.remove(i)`: remove element `i` and return it
+pub fn array_remove(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let array = this_array(m, args, "remove")?;
+    let idx = index(arg(args, 0), array.len(), false, "Array")?;
+    Ok(array.elements.borrow_mut().remove(idx))
+}
+
+/// `a.slice(start, end)`: new array of elements `start..end`, `end` is size of array by default
+pub fn array_slice(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let array = this_array(m, args, "slice")?;
+    let len = array.len();
+    let start = index(arg(args, 0), len, true, "Array")?;
+    let end = match arg(args, 1) {
+        Value::Null => len,
+        v => index(v, len, true, "Array")?,
+    };
+    if start > end {
+        return Err(error(format!(
+            "slice start {} is greater than end {}",
+            start, end
+        )));
+    }
+    let elements = array.elements.borrow()[start..end].to_vec();
+    Ok(Array::allocate(m, elements))
+}
+
+/// `a.concat(b, ...)`: new array of elements of `a` followed by elements of every argument
+pub fn array_concat(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let mut elements = this_array(m, args, "concat")?.elements.borrow().clone();
+    for v in &args[1..] {
+        let other = this_array(m, &[*v], "concat")
+            .map_err(|_| error(format!("Array::concat expects Array, found {:?}", v)))?;
+        elements.extend_from_slice(&other.elements.borrow());
+    }
+    Ok(Array::allocate(m, elements))
+}
+
+/// `a.reverse()`: reverse in place and return `a`
+pub fn array_reverse(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    this_array(m, args, "reverse")?
+        .elements
+        .borrow_mut()
+        .reverse();
+    Ok(args[0])
+}
+
+/// `a.indexOf(v)`: index of first element equal to `v` or -1
+pub fn array_index_of(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let elements = this_array(m, args, "indexOf")?.elements.borrow().clone();
+    for (i, element) in elements.iter().enumerate() {
+        if numeric::compare(m, Cmp::Eq, *element, arg(args, 0))? {
+            return Ok(Value::Int(i as i32));
+        }
+    }
+    Ok(Value::Int(-1))
+}
+
+pub fn array_contains(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    match array_index_of(m, args)? {
+        Value::Int(i) => Ok(Value::Bool(i >= 0)),
+        _ => unreachable!(),
+    }
+}
+
+/// `a.join(sep)`: string of all elements with `sep` between them, `sep` is "," by default
+pub fn array_join(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let elements = this_array(m, args, "join")?.elements.borrow().clone();
+    let sep = match arg(args, 0) {
+        Value::Null => String::from(","),
+        v => v.to_String(m),
+    };
+    let strings: Vec<String> = elements.iter().map(|v| v.to_String(m)).collect();
+    let string = strings.join(&sep);
+    Ok(Value::Object(m.pool.allocate(Box::new(string))))
+}
+
+pub fn array_clear(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    this_array(m, args, "clear")?.elements.borrow_mut().clear();
+    Ok(Value::Null)
+}
+
+/// `a.fill(v)`: replace every element with `v` and return `a`
+pub fn array_fill(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let array = this_array(m, args, "fill")?;
+    for element in array.elements.borrow_mut().iter_mut() {
+        *element = arg(args, 0);
+    }
+    Ok(args[0])
+}
+
+/// `a.map(f)`: new array of `f(element, index)`
+pub fn array_map(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let elements = this_array(m, args, "map")?.elements.borrow().clone();
+    let mut result = Vec::with_capacity(elements.len());
+    for (i, element) in elements.into_iter().enumerate() {
+        result.push(callback(m, arg(args, 0), &[element, Value::Int(i as i32)])?);
+    }
+    Ok(Array::allocate(m, result))
+}
+
+/// `a.filter(f)`: new array of elements for which `f(element, index)` is true
+pub fn array_filter(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let elements = this_array(m, args, "filter")?.elements.borrow().clone();
+    let mut result = vec![];
+    for (i, element) in elements.into_iter().enumerate() {
+        if test(m, arg(args, 0), &[element, Value::Int(i as i32)], "filter")? {
+            result.push(element);
+        }
+    }
+    Ok(Array::allocate(m, result))
+}
+
+/// `a.reduce(f, initial)`: fold elements with `f(accumulator, element, index)`
+///
+/// Without `initial` first element is the initial accumulator
+pub fn array_reduce(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let elements = this_array(m, args, "reduce")?.elements.borrow().clone();
+    let (mut acc, start) = match args.get(2) {
+        Some(initial) => (*initial, 0),
+        None => match elements.first() {
+            Some(first) => (*first, 1),
+            None => {
+                return Err(error(String::from(
+                    "reduce of empty Array with no initial value",
+                )))
+            }
+        },
+    };
+    for (i, element) in elements.into_iter().enumerate().skip(start) {
+        acc = callback(m, arg(args, 0), &[acc, element, Value::Int(i as i32)])?;
+    }
+    Ok(acc)
+}
+
+/// `a.forEach(f)`: call `f(element, index)` for every element
+pub fn array_for_each(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let elements = this_array(m, args, "forEach")?.elements.borrow().clone();
+    for (i, element) in elements.into_iter().enumerate() {
+        callback(m, arg(args, 0), &[element, Value::Int(i as i32)])?;
+    }
+    Ok(Value::Null)
+}
+
+/// `a.find(f)`: first element for which `f(element, index)` is true or null
+pub fn array_find(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let elements = this_array(m, args, "find")?.elements.borrow().clone();
+    for (i, element) in elements.into_iter().enumerate() {
+        if test(m, arg(args, 0), &[element, Value::Int(i as i32)], "find")? {
+            return Ok(element);
+        }
+    }
+    Ok(Value::Null)
+}
+
+/// `a.sort(cmp)`: stable sort in place and return `a`
+///
+/// `cmp(x, y)` returns negative number if `x` goes before `y`, positive if after and 0 if they are equal.
+/// Without `cmp` elements are ordered by `<`
+pub fn array_sort(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let mut elements = this_array(m, args, "sort")?.elements.borrow().clone();
+    let cmp = arg(args, 0);
+    let mut before = |m: &mut Machine, x: Value, y: Value| -> Result<bool, VmError> {
+        if let Value::Null = cmp {
+            return numeric::compare(m, Cmp::Lt, x, y);
+        }
+        let order = callback(m, cmp, &[x, y])?;
+        numeric::compare(m, Cmp::Lt, order, Value::Int(0))
+    };
+    merge_sort(m, &mut elements, &mut before)?;
+    *this_array(m, args, "sort")?.elements.borrow_mut() = elements;
+    Ok(args[0])
+}
+
+/// Stable sort which stops at first error of `before`
+fn merge_sort<F>(m: &mut Machine, elements: &mut Vec<Value>, before: &mut F) -> Result<(), VmError>
+where
+    F: FnMut(&mut Machine, Value, Value) -> Result<bool, VmError>,
+{
+    if elements.len() < 2 {
+        return Ok(());
+    }
+    let mut right = elements.split_off(elements.len() / 2);
+    merge_sort(m, elements, before)?;
+    merge_sort(m, &mut right, before)?;
+    let left = std::mem::take(elements);
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        if before(m, right[j], left[i])? {
+            elements.push(right[j]);
+            j += 1;
+        } else {
+            elements.push(left[i]);
+            i += 1;
+        }
+    }
+    elements.extend_from_slice(&left[i..]);
+    elements.extend_from_slice(&right[j..]);
+    Ok(())
+}
+
+/// Method `name` of Array
+pub fn array_method(name: &str) -> Option<Method>
+{
+    let method: Method = match name {
+        "pop" => array_pop,
+        "push" => array_push,
+        "size" => array_size,
+        "get" => array_get,
+        "set" => array_set,
+        "insert" => array_insert,
+        "remove" => array_remove,
+        "slice" => array_slice,
+        "concat" => array_concat,
+        "reverse" => array_reverse,
+        "indexOf" => array_index_of,
+        "contains" => array_contains,
+        "join" => array_join,
+        "clear" => array_clear,
+        "fill" => array_fill,
+        "map" => array_map,
+        "filter" => array_filter,
+        "reduce" => array_reduce,
+        "forEach" => array_for_each,
+        "find" => array_find,
+        "sort" => array_sort,
+        _ => return None,
+    };
+    Some(method)
+}
+
+impl Array
+{
+    pub fn new() -> Array
+    {
+        Array {
+            elements: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Store array of `elements` in object pool
+    pub fn allocate(m: &mut Machine, elements: Vec<Value>) -> Value
+    {
+        let array = Array {
+            elements: RefCell::new(elements),
+        };
+        Value::Object(m.pool.allocate(Box::new(array)))
+    }
+
+    /// Copy of elements
+    pub fn elements(&self) -> Vec<Value>
+    {
+        self.elements.borrow().clone()
+    }
+
+    pub fn push(&self, v: Value)
+    {
+        self.elements.borrow_mut().push(v);
+    }
+
+    pub fn pop(&self) -> Value
+    {
+        self.elements.borrow_mut().pop().unwrap_or(Value::Null)
+    }
+
+    pub fn set(&self, idx: usize, v: Value)
+    {
+        self.elements.borrow_mut()[idx] = v;
+    }
+
+    pub fn get(&self, idx: usize) -> Value
+    {
+        self.elements.borrow()[idx]
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.elements.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+}
+
+/// Elements of array `id` in order, elements pushed during iteration are visited as well
+struct Elements
+{
+    id: usize,
+    index: usize,
+}
+
+impl Iterate for Elements
+{
+    fn next(&mut self, m: &mut Machine) -> Result<Option<Value>, VmError>
+    {
+        let array = match m.pool.get_direct_typed::<Array>(self.id) {
+            Some(array) => array,
+            None => return Ok(None),
+        };
+        if self.index >= array.len() {
+            return Ok(None);
+        }
+        self.index += 1;
+        Ok(Some(array.get(self.index - 1)))
+    }
+}
+
+impl Default for Array
+{
+    fn default() -> Array
+    {
+        Array::new()
+    }
+}
+
+impl ObjectAddon for Array
+{
+    fn to_String(&self, m: &mut Machine) -> String
+    {
+        let elements = self.elements.borrow();
+
+        let mut string = String::new();
+        string.push('[');
+        for (i, element) in elements.iter().enumerate() {
+            string.push_str(&element.to_String(m));
+            if i != elements.len() - 1 {
+                string.push(',');
+            }
+        }
+        string.push(']');
+
+        string
+    }
+}
+
+impl Object for Array
+{
+    fn initialize(&mut self, _: &mut ObjectPool)
+    {
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        self as &dyn Any
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    {
+        self as &mut dyn Any
+    }
+
+    fn get_children(&self) -> Vec<usize>
+    {
+        Vec::new()
+    }
+
+    fn iterator(&self, m: &mut Machine, this: Value) -> Result<Box<dyn Iterate>, VmError>
+    {
+        match this {
+            Value::Object(id) => Ok(Box::new(Elements { id, index: 0 })),
+            _ => Err(not_iterable(self.typename(m))),
+        }
+    }
+
+    fn load_at(&self, m: &mut Machine, args: Vec<Value>, rindex: usize)
+    {
+        let _this = args[0];
+        if let Value::Object(id) = &args[1] {
+            let name = m.pool.get(*id).to_String(m);
+            match array_method(&name) {
+                Some(method) => {
+                    let function = Function::from_method(method);
+                    let function_id = Value::Object(m.pool.allocate(Box::new(function)));
+                    m.set(rindex, function_id);
+                }
+                None => m.raise(error(format!("Array has no method `{}`", name))),
+            }
+            return;
+        }
+        match index(args[1], self.len(), false, "Array") {
+            Ok(idx) => m.set(rindex, self.get(idx)),
+            Err(e) => m.raise(e),
+        }
+    }
+
+    fn store_at(&self, m: &mut Machine, args: Vec<Value>, _rindex: usize)
+    {
+        match index(args[1], self.len(), false, "Array") {
+            Ok(idx) => self.set(idx, args[2]),
+            Err(e) => m.raise(e),
+        }
+    }
+}
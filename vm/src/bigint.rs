@@ -0,0 +1,117 @@
+//! Arbitrary-precision integers
+//!
+//! `Long` arithmetic that overflows produces `BigInt` object, see `numeric`. Once an operand is `BigInt` result
+//! is `BigInt` too, even if it would fit into `Long`.
+
+use crate::{
+    machine::Machine,
+    object::{Object, ObjectAddon},
+    object_pool::ObjectPool,
+    value::Value,
+};
+use num_bigint::BigInt as Big;
+use num_traits::{ToPrimitive, Zero};
+use std::any::Any;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BigInt(pub Big);
+
+impl BigInt
+{
+    /// Parse decimal integer with optional sign, surrounding whitespace is ignored
+    pub fn parse(s: &str) -> Option<BigInt>
+    {
+        let s = s.trim();
+        let digits = s.strip_prefix('-').or_else(|| s.strip_prefix('+')).unwrap_or(s);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        s.parse().ok().map(BigInt)
+    }
+
+    /// Value if it fits into `Long`
+    pub fn to_i64(&self) -> Option<i64>
+    {
+        self.0.to_i64()
+    }
+
+    /// Store `value` in object pool
+    pub fn allocate(m: &mut Machine, value: Big) -> Value
+    {
+        Value::Object(m.pool.allocate(Box::new(BigInt(value))))
+    }
+
+    /// `BigInt` stored in `value`
+    pub fn get(m: &Machine, value: Value) -> Option<&BigInt>
+    {
+        match value {
+            Value::Object(id) => m.pool.get_direct_typed::<BigInt>(id),
+            _ => None,
+        }
+    }
+}
+
+impl From<i64> for BigInt
+{
+    fn from(value: i64) -> BigInt
+    {
+        BigInt(Big::from(value))
+    }
+}
+
+impl ObjectAddon for BigInt
+{
+    fn typename(&self, _: &mut Machine) -> String
+    {
+        String::from("Int")
+    }
+
+    fn to_String(&self, _: &mut Machine) -> String
+    {
+        self.0.to_string()
+    }
+
+    fn to_double(&self, _: &mut Machine) -> f64
+    {
+        self.0.to_f64().unwrap_or(f64::NAN)
+    }
+
+    fn to_float(&self, _: &mut Machine) -> f32
+    {
+        self.0.to_f32().unwrap_or(f32::NAN)
+    }
+
+    fn to_int(&self, _: &mut Machine) -> i32
+    {
+        let saturated = if self.0 < Big::zero() { i32::MIN } else { i32::MAX };
+        self.0.to_i32().unwrap_or(saturated)
+    }
+
+    fn to_long(&self, _: &mut Machine) -> i64
+    {
+        let saturated = if self.0 < Big::zero() { i64::MIN } else { i64::MAX };
+        self.0.to_i64().unwrap_or(saturated)
+    }
+}
+
+impl Object for BigInt
+{
+    fn initialize(&mut self, _: &mut ObjectPool)
+    {
+    }
+
+    fn get_children(&self) -> Vec<usize>
+    {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        self as &dyn Any
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    {
+        self as &mut dyn Any
+    }
+}
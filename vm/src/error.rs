@@ -1,49 +1,49 @@
+use crate::verifier::VerifyError;
 use std::error::Error;
 
-#[derive(Debug)]
-pub enum VmError 
+#[derive(Clone, Debug, PartialEq)]
+pub enum VmError
 {
     RuntimeError(String),
     LabelNotFound(usize),
     GlobalNotFound(usize),
-    Expected(String,String),
+    Expected(String, String),
+    StackOverflow,
+    /// Code rejected by verifier
+    Verify(VerifyError),
 }
 
-
-impl VmError {
-    fn as_str(&self) -> String {
-        match self.clone() {
-            VmError::RuntimeError(cause) => format!("Runtime Error: `{}`",cause),
-            VmError::LabelNotFound(id) => format!("Label `{}` not found",id),
-            VmError::GlobalNotFound(id) => format!("Global `{}` not found",id),
-            VmError::Expected(expected,found) => format!("Expected `{}` found `{}`",expected,found),
+impl VmError
+{
+    fn as_str(&self) -> String
+    {
+        match self {
+            VmError::RuntimeError(cause) => format!("Runtime Error: `{}`", cause),
+            VmError::LabelNotFound(id) => format!("Label `{}` not found", id),
+            VmError::GlobalNotFound(id) => format!("Global `{}` not found", id),
+            VmError::Expected(expected, found) => format!("Expected `{}` found `{}`", expected, found),
+            VmError::StackOverflow => String::from("Stack overflow"),
+            VmError::Verify(e) => format!("Verify Error: {}", e),
         }
     }
 }
 
-impl Error for VmError {
-    fn description(&self) -> &str {
-        match self {
-            &VmError::Expected(_,_) => "Expected: ",
-            &VmError::GlobalNotFound(_) => "GlobalNotFound:",
-            &VmError::LabelNotFound(_) => "LabelNotFound:",
-            &VmError::RuntimeError(_) => "RuntimeError:",
-        }
-    }
+impl Error for VmError {}
 
-    fn cause(&self) -> Option<&dyn Error> {
-        None
+impl From<VerifyError> for VmError
+{
+    fn from(e: VerifyError) -> VmError
+    {
+        VmError::Verify(e)
     }
 }
 
 use std::fmt;
 
-impl fmt::Display for VmError {
-    fn fmt(&self,f: &mut fmt::Formatter<'_>) -> fmt::Result 
+impl fmt::Display for VmError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
     {
-        write!(f,"{}",self.as_str())
+        write!(f, "{}", self.as_str())
     }
 }
-
-
-
@@ -1,143 +1,105 @@
-use crate::{opcodes::Instruction, value::Value};
+use crate::{
+    error::VmError,
+    machine::{Machine, Step},
+    opcodes::Instruction,
+    value::Value,
+};
+use std::{collections::HashMap, sync::Arc};
 
 ///CallFrame
-/// Stores register values, instructions, registers and arguments stack
-#[derive(Clone, Debug,Default)]
+/// Stores instruction pointer, instructions and location of frame registers
+///
+/// Registers of a frame are a window into `Machine::registers`: R(0) is `this` and R(1)..R(argc) are arguments.
+/// Caller places `this` and arguments into consecutive registers and callee frame starts right on them,
+/// so arguments are never copied
+#[derive(Clone, Debug, Default)]
 pub struct CallFrame
 {
-    /// pointer to current block
+    /// pointer to current instruction
     pub ip: usize,
     /// Instructions
-    pub code: Vec<Instruction>,
-    /// registers stored in stack, default size of `stack` is 256
-    pub stack: Vec<Value>,
-    /// `arguments stack`: used by Call instruction
-    pub arg_stack: Vec<Value>,
+    pub code: Arc<Vec<Instruction>>,
+    /// Labels of `code`
+    pub labels: Arc<HashMap<usize, usize>>,
+    /// index of R(0) in register file
+    pub base: usize,
+    /// count of registers used by frame
+    pub size: usize,
+    /// register of caller frame that receives returned value
+    pub dest: usize,
 }
 
-/// CallStack
-/// Stores frames
+///Fiber
+/// Frames removed from call stack together with their registers, execution continues where it stopped
+/// once they are put back by `Machine::resume`
+///
+/// `base` of every frame is an index into `registers` instead of register file
 #[derive(Clone, Debug)]
-pub struct CallStack
+pub struct Fiber
 {
     pub frames: Vec<CallFrame>,
-    pub n_frames: usize,
-    pub limit: Option<usize>,
+    pub registers: Vec<Value>,
 }
 
-impl CallStack
+///Continuation
+/// Script paused by native function, see `Machine::pause`
+///
+/// Host keeps it while result of the native is not ready yet and resumes script with that result later
+#[derive(Clone, Debug)]
+pub struct Continuation
 {
-    pub fn new(len: usize) -> CallStack
-    {
-        let mut frames = Vec::with_capacity(len);
-        for _ in 0..len {
-            frames.push(CallFrame::new());
-        }
-
-        CallStack {
-            frames,
-            n_frames: 1,
-            limit: None,
-        }
-    }
-
-    pub fn push(&mut self)
-    {
-        if self.n_frames >= self.frames.len() {
-            panic!("Virtual stack overflow");
-        }
-        if let Some(limit) = self.limit {
-            if self.n_frames >= limit {
-                panic!("Maximum stack depth exceeded");
-            }
-        }
-
-        self.n_frames += 1;
-    }
-
-    /// Reset last frame
-    pub fn pop(&mut self)
-    {
-        if self.n_frames == 0 {
-            panic!("Virtual stack underflow");
-        }
-        self.frames[self.n_frames - 1].reset();
-        self.n_frames -= 1;
-    }
-    /// Get top frame
-    pub fn top(&self) -> &CallFrame
-    {
-        if self.n_frames == 0 {
-            panic!("Virtual stack underflow");
-        }
-        &self.frames[self.n_frames - 1]
-    }
-    /// Get &mut CallFrame
-    pub fn top_mut(&mut self) -> &mut CallFrame
-    {
-        if self.n_frames == 0 {
-            panic!("Virtual stack underflow");
-        }
-        &mut self.frames[self.n_frames - 1]
-    }
+    pub(crate) fiber: Fiber,
+    /// Register of top frame that receives result of the native, `None` if native was called by `TailCall`
+    pub(crate) dest: Option<usize>,
 }
 
-///Fiber
-/// Should be used in future
-///
-/// Fiber must need to store upvalues and have a object pool
-#[derive(Clone, Debug)]
-pub struct Fiber
+impl Continuation
 {
-    pub frames: Vec<CallFrame>,
+    /// Continue script on `m` as if paused native returned `value`
+    pub fn resume(self, m: &mut Machine, value: Value) -> Result<Step, VmError>
+    {
+        m.proceed(self, value)
+    }
 }
 
 impl CallFrame
 {
-    pub fn new() -> CallFrame
+    pub fn new(
+        code: Arc<Vec<Instruction>>,
+        labels: Arc<HashMap<usize, usize>>,
+        base: usize,
+        size: usize,
+        dest: usize,
+    ) -> CallFrame
     {
-        let mut vec = Vec::with_capacity(256);
-        for _ in 0..256 {
-            vec.push(Value::Null);
-        }
-
         CallFrame {
             ip: 0,
-
-            code: vec![],
-            stack: vec,
-            arg_stack: vec![],
+            code,
+            labels,
+            base,
+            size,
+            dest,
         }
     }
 
-    pub fn get(&self, r: usize) -> Value
+    /// Index of first register above this frame
+    pub fn top(&self) -> usize
     {
-        self.stack[r]
-    }
-
-    pub fn set(&mut self, r: usize, v: Value)
-    {
-        self.stack[r] = v;
-    }
-
-    pub fn init_with_args(&mut self, args: &[Value])
-    {
-        for arg in args {
-            self.arg_stack.push(*arg);
-        }
+        self.base + self.size
     }
+}
 
-    pub fn reset(&mut self)
+impl Fiber
+{
+    /// Fiber that starts `code` with `this` and arguments in `args`, frame has `size` registers
+    pub fn new(code: Arc<Vec<Instruction>>, labels: Arc<HashMap<usize, usize>>, args: &[Value], size: usize) -> Fiber
     {
-        for i in 0..self.stack.len() {
-            self.stack[i] = Value::Null;
+        let size = size.max(args.len());
+        let mut registers = args.to_vec();
+        registers.resize(size, Value::Null);
+        Fiber {
+            frames: vec![CallFrame::new(code, labels, 0, size, 0)],
+            registers,
         }
-        self.arg_stack.clear();
-        self.code.clear();
-    }
-
-    pub fn jit_run(&mut self) -> Value
-    {
-        Value::Null
     }
 }
@@ -1,5 +1,5 @@
-use crate::{machine::Machine, object::Object, opcodes::*, value::Value};
-use std::any::Any;
+use crate::{error::VmError, generator::Generator, jit::JitState, machine::Machine, object::Object, opcodes::*, value::Value};
+use std::{any::Any, collections::HashMap, sync::Arc};
 
 #[derive(Debug)]
 pub enum Function
@@ -21,8 +21,9 @@ impl Clone for Function
 
 impl crate::object::ObjectAddon for Function
 {
-    fn typename(&self,_: &mut Machine) -> String {
-        return "Func".into()
+    fn typename(&self, _: &mut Machine) -> String
+    {
+        "Func".into()
     }
 
     fn as_function(&self) -> &Function
@@ -83,24 +84,20 @@ impl Object for Function
     }
 
     /// Call object
-    fn call(&self, m: &mut Machine, args: Vec<Value>) -> Value
+    fn call(&self, m: &mut Machine, args: &[Value]) -> Value
     {
         match self {
+            Function::Virtual(ref vf) if vf.generator => Generator::allocate(m, vf, args),
             Function::Virtual(ref vf) => {
-                //println!("{:?}",args);
-                let func = vf.clone();
-                //println!("{:?}",args[0].to_String(m));
-                m.last_frame_mut().stack[0] = args[0];
-                for i in 0..args.len() {
-                    m.last_frame_mut().stack[i] = args[i];
-                }
-                let code = func.code;
-                let v = m.run_code(code);
+                let base = m.top();
+                let v = m
+                    .enter_at(vf, base, args)
+                    .and_then(|_| m.execute_op());
                 match v {
-                    Ok(v) => return v,
+                    Ok(v) => v,
                     Err(e) => {
-                        eprintln!("{}",e);
-                        panic!("");
+                        m.raise(e);
+                        Value::Null
                     }
                 }
             }
@@ -113,36 +110,99 @@ impl Object for Function
 #[derive(Clone, Debug)]
 pub struct VirtualFunction
 {
-    pub code: Vec<Instruction>,
+    pub code: Arc<Vec<Instruction>>,
     pub argc: usize,
+    /// Frame size: count of registers used by `code`
+    pub nregs: usize,
+    /// Label id to instruction index, resolved once when function is created
+    pub labels: Arc<HashMap<usize, usize>>,
+    /// Call counter and native code, shared by clones of function
+    pub jit: Arc<JitState>,
+    /// Call creates `Generator` that runs `code` instead of running it right away
+    pub generator: bool,
+}
+
+impl VirtualFunction
+{
+    pub fn new(code: Vec<Instruction>, argc: usize) -> VirtualFunction
+    {
+        let nregs = code
+            .iter()
+            .flat_map(|ins| ins.registers())
+            .max()
+            .map_or(0, |r| r + 1)
+            .max(argc + 1);
+        let labels = code
+            .iter()
+            .enumerate()
+            .filter_map(|(i, ins)| match ins {
+                Instruction::Label(id) => Some((*id, i)),
+                _ => None,
+            })
+            .collect();
+
+        VirtualFunction {
+            code: Arc::new(code),
+            argc,
+            nregs,
+            labels: Arc::new(labels),
+            jit: Arc::new(JitState::default()),
+            generator: false,
+        }
+    }
 }
 
 impl Function
 {
     pub fn from_instructions(code: Vec<Instruction>, args: usize) -> Function
     {
-        Function::Virtual(VirtualFunction { code, argc: args })
+        Function::Virtual(VirtualFunction::new(code, args))
     }
 
-    pub fn from_native(f: Box<dyn Fn(&mut Machine, Vec<Value>) -> Value + Send>) -> Function
+    /// Function which call creates generator, see `generator`
+    pub fn from_generator(code: Vec<Instruction>, args: usize) -> Function
+    {
+        let mut vf = VirtualFunction::new(code, args);
+        vf.generator = true;
+        Function::Virtual(vf)
+    }
+
+    pub fn from_native(f: Box<NativeFn>) -> Function
     {
         Function::Native(NativeFunction(f))
     }
+
+    /// Native function that calls `method` and raises its error, see `Machine::raise`
+    pub fn from_method(method: Method) -> Function
+    {
+        Function::from_native(Box::new(move |m: &mut Machine, args: &[Value]| {
+            method(m, args).unwrap_or_else(|e| {
+                m.raise(e);
+                Value::Null
+            })
+        }))
+    }
 }
 
 impl From<Vec<Instruction>> for Function
 {
     fn from(f: Vec<Instruction>) -> Function
     {
-        Function::Virtual(VirtualFunction { code: f, argc: 0 })
+        Function::Virtual(VirtualFunction::new(f, 0))
     }
 }
 
-pub struct NativeFunction(pub Box<dyn Fn(&mut Machine, Vec<Value>) -> Value + Send>);
+/// Native function receives `this` as first element of arguments slice and actual arguments after it
+pub type NativeFn = dyn Fn(&mut Machine, &[Value]) -> Value + Send;
+
+/// Native function which can fail
+pub type Method = fn(&mut Machine, &[Value]) -> Result<Value, VmError>;
+
+pub struct NativeFunction(pub Box<NativeFn>);
 
 impl NativeFunction
 {
-    pub fn invoke(&self, m: &mut Machine, args: Vec<Value>) -> Value
+    pub fn invoke(&self, m: &mut Machine, args: &[Value]) -> Value
     {
         self.0(m, args)
     }
@@ -0,0 +1,226 @@
+//! Generators
+//!
+//! Calling a function that contains `yield` doesn't run it, it creates a `Generator` holding a suspended frame
+//! of the function. Every resume runs that frame until next `Yield`, which suspends it again and passes yielded
+//! value to whoever resumed it. Generator is finished once the function returns. Expression of `return` is
+//! evaluated but its value is dropped, resume of finished generator gives `None` (`null` in scripts).
+//! `done()` tells that `null` apart from a yielded one.
+//!
+//! Generators are iterable and have methods `next()`, `done()`, `take(n)` and `toArray()`.
+
+use crate::{
+    array::{arg, error, Array},
+    error::VmError,
+    frame::Fiber,
+    function::{Function, Method, VirtualFunction},
+    iterator::Iterate,
+    machine::Machine,
+    object::{Object, ObjectAddon},
+    object_pool::ObjectPool,
+    value::Value,
+};
+use std::{any::Any, cell::RefCell};
+
+enum State
+{
+    /// Waits for next resume
+    Suspended(Fiber),
+    /// Frame of generator is on call stack
+    Running,
+    Finished,
+}
+
+pub struct Generator
+{
+    state: RefCell<State>,
+}
+
+impl Generator
+{
+    /// Store generator that runs `func` with `this` and arguments in `args` in object pool
+    pub fn allocate(m: &mut Machine, func: &VirtualFunction, args: &[Value]) -> Value
+    {
+        let fiber = Fiber::new(func.code.clone(), func.labels.clone(), args, func.nregs);
+        let generator = Generator {
+            state: RefCell::new(State::Suspended(fiber)),
+        };
+        Value::Object(m.pool.allocate(Box::new(generator)))
+    }
+
+    /// Run generator at `id` of object pool until its next `yield`, `None` once it has finished
+    ///
+    /// Generator that fails is finished as well. Value returned by the function isn't passed on
+    pub fn resume(m: &mut Machine, id: usize) -> Result<Option<Value>, VmError>
+    {
+        let fiber = match Generator::at(m, id).state.replace(State::Running) {
+            State::Suspended(fiber) => fiber,
+            State::Running => return Err(error(String::from("generator is already running"))),
+            State::Finished => {
+                Generator::at(m, id).state.replace(State::Finished);
+                return Ok(None);
+            }
+        };
+        let (state, result) = match m.resume(fiber) {
+            Ok((value, Some(fiber))) => (State::Suspended(fiber), Ok(Some(value))),
+            Ok((_, None)) => (State::Finished, Ok(None)),
+            Err(e) => (State::Finished, Err(e)),
+        };
+        Generator::at(m, id).state.replace(state);
+        result
+    }
+
+    /// Whether generator at `id` has finished, it is not before a resume runs past its last `yield`
+    pub fn is_finished(m: &Machine, id: usize) -> bool
+    {
+        matches!(*Generator::at(m, id).state.borrow(), State::Finished)
+    }
+
+    /// Generator at `id`, fetched anew after every run of code as it may change object pool
+    fn at(m: &Machine, id: usize) -> &Generator
+    {
+        m.pool.get_direct_typed(id).unwrap()
+    }
+}
+
+/// Id of `Generator` passed as `this`
+fn this_generator(m: &Machine, args: &[Value], method: &str) -> Result<usize, VmError>
+{
+    match args[0] {
+        Value::Object(id) if m.pool.get_direct_typed::<Generator>(id).is_some() => Ok(id),
+        _ => Err(error(format!("Generator::{} expects Generator as this", method))),
+    }
+}
+
+/// `g.next()`: next yielded value, null once generator has finished, even if its function returns a value
+pub fn generator_next(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let generator = this_generator(m, args, "next")?;
+    Ok(Generator::resume(m, generator)?.unwrap_or(Value::Null))
+}
+
+/// `g.done()`: whether generator has finished, so `null` that `next()` gave wasn't yielded
+pub fn generator_done(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let generator = this_generator(m, args, "done")?;
+    Ok(Value::Bool(Generator::is_finished(m, generator)))
+}
+
+/// `g.take(n)`: array of at most `n` next yielded values
+pub fn generator_take(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let generator = this_generator(m, args, "take")?;
+    let n = match arg(args, 0) {
+        Value::Int(i) if i >= 0 => i as usize,
+        Value::Long(l) if l >= 0 => l as usize,
+        v => return Err(error(format!("Generator::take expects non-negative integer, found {:?}", v))),
+    };
+    let mut values = Vec::new();
+    while values.len() < n {
+        match Generator::resume(m, generator)? {
+            Some(v) => values.push(v),
+            None => break,
+        }
+    }
+    Ok(Array::allocate(m, values))
+}
+
+/// `g.toArray()`: array of every value generator yields until it finishes
+pub fn generator_to_array(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let generator = this_generator(m, args, "toArray")?;
+    let mut values = Vec::new();
+    while let Some(v) = Generator::resume(m, generator)? {
+        values.push(v);
+    }
+    Ok(Array::allocate(m, values))
+}
+
+/// Method `name` of Generator
+pub fn generator_method(name: &str) -> Option<Method>
+{
+    let method: Method = match name {
+        "next" => generator_next,
+        "done" => generator_done,
+        "take" => generator_take,
+        "toArray" => generator_to_array,
+        _ => return None,
+    };
+    Some(method)
+}
+
+/// Values yielded by generator `this`
+struct Resume
+{
+    this: Value,
+}
+
+impl Iterate for Resume
+{
+    fn next(&mut self, m: &mut Machine) -> Result<Option<Value>, VmError>
+    {
+        let generator = this_generator(m, &[self.this], "iter")?;
+        Generator::resume(m, generator)
+    }
+}
+
+impl ObjectAddon for Generator
+{
+    fn typename(&self, _: &mut Machine) -> String
+    {
+        String::from("Generator")
+    }
+
+    fn to_String(&self, _: &mut Machine) -> String
+    {
+        String::from("<generator>")
+    }
+}
+
+impl Object for Generator
+{
+    fn initialize(&mut self, _: &mut ObjectPool)
+    {
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        self as &dyn Any
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    {
+        self as &mut dyn Any
+    }
+
+    fn get_children(&self) -> Vec<usize>
+    {
+        match &*self.state.borrow() {
+            State::Suspended(fiber) => fiber
+                .registers
+                .iter()
+                .filter_map(|v| match v {
+                    Value::Object(id) => Some(*id),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn iterator(&self, _: &mut Machine, this: Value) -> Result<Box<dyn Iterate>, VmError>
+    {
+        Ok(Box::new(Resume { this }))
+    }
+
+    fn load_at(&self, m: &mut Machine, args: Vec<Value>, rindex: usize)
+    {
+        let name = args[1].to_String(m);
+        match generator_method(&name) {
+            Some(method) => {
+                let function = Value::Object(m.pool.allocate(Box::new(Function::from_method(method))));
+                m.set(rindex, function);
+            }
+            None => m.raise(error(format!("Generator has no method `{}`", name))),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Iteration protocol of `for x in v`
+//!
+//! Value is iterable if `Object::iterator` of its object gives an `Iterate`. Native objects implement it in
+//! Rust, instances of classes do it through `iter` and `next` methods, `null` from `next` ends the loop. Loop
+//! keeps state of iteration in an `Iter` object, `advance` moves it to the next element and `current` is that
+//! element.
+
+use crate::{
+    error::VmError,
+    machine::Machine,
+    object::{Object, ObjectAddon},
+    object_pool::ObjectPool,
+    value::Value,
+};
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+};
+
+/// State of iteration over some value
+pub trait Iterate: Send
+{
+    /// Next element, `None` when there are no more elements
+    fn next(&mut self, m: &mut Machine) -> Result<Option<Value>, VmError>;
+}
+
+/// Error for value of type `typename` that can't be iterated
+pub fn not_iterable(typename: String) -> VmError
+{
+    VmError::RuntimeError(format!("{} is not iterable", typename))
+}
+
+/// Start iteration over `v`
+pub fn iterator(m: &mut Machine, v: Value) -> Result<Box<dyn Iterate>, VmError>
+{
+    match v {
+        Value::Object(id) => {
+            let object = m.pool.get(id);
+            object.iterator(m, v)
+        }
+        v => Err(not_iterable(v.typename(m))),
+    }
+}
+
+/// Iteration in progress, element it's at is stored after each `advance`
+pub struct Iter
+{
+    state: RefCell<Box<dyn Iterate>>,
+    current: Cell<Value>,
+}
+
+impl Iter
+{
+    /// Store iterator of `v` in object pool
+    pub fn allocate(m: &mut Machine, v: Value) -> Result<Value, VmError>
+    {
+        let iter = Iter {
+            state: RefCell::new(iterator(m, v)?),
+            current: Cell::new(Value::Null),
+        };
+        Ok(Value::Object(m.pool.allocate(Box::new(iter))))
+    }
+}
+
+/// `Iter` stored in `v`
+fn this_iter<'a>(m: &mut Machine, v: Value) -> Result<&'a Iter, VmError>
+{
+    let iter = match v {
+        Value::Object(id) => {
+            let object: &'a dyn Object = *m.pool.get(id);
+            object.as_any().downcast_ref::<Iter>()
+        }
+        _ => None,
+    };
+    iter.ok_or_else(|| VmError::RuntimeError(format!("expected iterator, found {:?}", v)))
+}
+
+/// Move iterator `it` to the next element, false if there is none
+pub fn advance(m: &mut Machine, it: Value) -> Result<bool, VmError>
+{
+    let iter = this_iter(m, it)?;
+    let next = iter.state.borrow_mut().next(m)?;
+    match next {
+        Some(v) => {
+            iter.current.set(v);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Element iterator `it` is at
+pub fn current(m: &mut Machine, it: Value) -> Result<Value, VmError>
+{
+    Ok(this_iter(m, it)?.current.get())
+}
+
+impl ObjectAddon for Iter
+{
+    fn typename(&self, _: &mut Machine) -> String
+    {
+        String::from("Iterator")
+    }
+
+    fn to_String(&self, _: &mut Machine) -> String
+    {
+        String::from("<iterator>")
+    }
+}
+
+impl Object for Iter
+{
+    fn initialize(&mut self, _: &mut ObjectPool)
+    {
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        self as &dyn Any
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    {
+        self as &mut dyn Any
+    }
+
+    fn get_children(&self) -> Vec<usize>
+    {
+        Vec::new()
+    }
+}
@@ -0,0 +1,367 @@
+//! Baseline compiler from bytecode to x86-64 machine code
+//!
+//! Only functions working on `Long` and `Bool` values without calls and object access are compiled.
+//! Every register lives in its own stack slot, arithmetic that overflows makes compiled code bail out
+//! so function is executed by interpreter instead.
+
+use super::{
+    cpu::{
+        x64::{
+            asm::{Assembler, Cond, Fixup},
+            reg::*,
+        },
+        Reg,
+    },
+    memory::ExecutableMemory,
+    JitFunction, STATUS_BAIL, STATUS_BOOL, STATUS_LONG,
+};
+use crate::{
+    function::VirtualFunction,
+    opcodes::{Cmp, Instruction},
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Ty
+{
+    Long,
+    Bool,
+}
+
+/// Registers read and written by supported instruction, `None` for unsupported instruction
+fn operands(ins: &Instruction) -> Option<(Vec<usize>, Option<usize>)>
+{
+    use self::Instruction::*;
+
+    Some(match *ins {
+        LoadLong(d, _) | LoadBool(d, _) => (vec![], Some(d)),
+        Move(d, s) | AddI(d, s, _) => (vec![s], Some(d)),
+        Inc(r) => (vec![r], Some(r)),
+        Add(d, a, b)
+        | Sub(d, a, b)
+        | Mul(d, a, b)
+        | Gt(d, a, b)
+        | Lt(d, a, b)
+        | Ge(d, a, b)
+        | Le(d, a, b)
+        | Eq(d, a, b)
+        | Neq(d, a, b) => (vec![a, b], Some(d)),
+        JumpF(r, _) | GotoF(r, _) | JumpCmpIF(_, r, _, _) | Ret(r) => (vec![r], None),
+        JumpCmpF(_, a, b, _) => (vec![a, b], None),
+        Jump(_) | Goto(_) | Label(_) => (vec![], None),
+        _ => return None,
+    })
+}
+
+/// Instructions that may execute after instruction `i`
+fn successors(func: &VirtualFunction, i: usize) -> Option<Vec<usize>>
+{
+    use self::Instruction::*;
+
+    let label = |id: &usize| func.labels.get(id).cloned();
+    Some(match &func.code[i] {
+        Jump(t) => vec![*t],
+        Goto(l) => vec![label(l)?],
+        JumpF(_, t) | JumpCmpF(_, _, _, t) | JumpCmpIF(_, _, _, t) => vec![i + 1, *t],
+        GotoF(_, l) => vec![i + 1, label(l)?],
+        Ret(_) => vec![],
+        _ => vec![i + 1],
+    })
+}
+
+/// Check that every register is read only after it was written on every path and compute register types
+fn analyze(func: &VirtualFunction) -> Option<Vec<Option<Ty>>>
+{
+    let code = &func.code;
+    let nregs = func.nregs;
+    let mut succs = Vec::with_capacity(code.len());
+    for i in 0..code.len() {
+        let s = successors(func, i)?;
+        // falling off the end returns null
+        if s.iter().any(|&t| t >= code.len()) {
+            return None;
+        }
+        succs.push(s);
+    }
+
+    // definitely written registers at entry of each instruction
+    let mut defined = vec![vec![true; nregs]; code.len()];
+    defined[0] = (0..nregs).map(|r| r >= 1 && r <= func.argc).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..code.len() {
+            let (reads, write) = operands(&code[i])?;
+            if reads.iter().any(|&r| !defined[i][r]) {
+                return None;
+            }
+            let mut out = defined[i].clone();
+            if let Some(d) = write {
+                out[d] = true;
+            }
+            for &s in &succs[i] {
+                for r in 0..nregs {
+                    if defined[s][r] && !out[r] {
+                        defined[s][r] = false;
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut types: Vec<Option<Ty>> = (0..nregs)
+        .map(|r| if r >= 1 && r <= func.argc { Some(Ty::Long) } else { None })
+        .collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for ins in code.iter() {
+            use self::Instruction::*;
+
+            let (d, ty) = match *ins {
+                LoadLong(d, _) | Add(d, _, _) | Sub(d, _, _) | Mul(d, _, _) | AddI(d, _, _) | Inc(d) => {
+                    (d, Some(Ty::Long))
+                }
+                LoadBool(d, _) | Gt(d, _, _) | Lt(d, _, _) | Ge(d, _, _) | Le(d, _, _) | Eq(d, _, _)
+                | Neq(d, _, _) => (d, Some(Ty::Bool)),
+                Move(d, s) => (d, types[s]),
+                _ => continue,
+            };
+            match (types[d], ty) {
+                (_, None) => {}
+                (None, ty) => {
+                    types[d] = ty;
+                    changed = true;
+                }
+                (Some(t1), Some(t2)) if t1 != t2 => return None,
+                _ => {}
+            }
+        }
+    }
+
+    let is = |r: usize, ty: Ty| types[r] == Some(ty);
+    for ins in code.iter() {
+        use self::Instruction::*;
+
+        let ok = match *ins {
+            Add(_, a, b) | Sub(_, a, b) | Mul(_, a, b) | JumpCmpF(_, a, b, _) => {
+                is(a, Ty::Long) && is(b, Ty::Long)
+            }
+            Gt(_, a, b) | Lt(_, a, b) | Ge(_, a, b) | Le(_, a, b) | Eq(_, a, b) | Neq(_, a, b) => {
+                is(a, Ty::Long) && is(b, Ty::Long)
+            }
+            AddI(_, a, _) | Inc(a) | JumpCmpIF(_, a, _, _) => is(a, Ty::Long),
+            JumpF(r, _) | GotoF(r, _) => is(r, Ty::Bool),
+            Ret(r) => types[r].is_some(),
+            _ => true,
+        };
+        if !ok {
+            return None;
+        }
+    }
+    Some(types)
+}
+
+fn cond(cmp: Cmp) -> Cond
+{
+    match cmp {
+        Cmp::Gt => Cond::Greater,
+        Cmp::Lt => Cond::Less,
+        Cmp::Ge => Cond::GreaterEq,
+        Cmp::Le => Cond::LessEq,
+        Cmp::Eq => Cond::Equal,
+        Cmp::Neq => Cond::NotEqual,
+    }
+}
+
+/// Stack slot of register `r`
+fn slot(r: usize) -> i32
+{
+    -8 * (r as i32 + 1)
+}
+
+struct Codegen
+{
+    asm: Assembler,
+    /// Jumps to instruction index
+    jumps: Vec<(Fixup, usize)>,
+    /// Jumps to bail out path
+    bails: Vec<Fixup>,
+    /// Jumps to epilogue
+    returns: Vec<Fixup>,
+}
+
+impl Codegen
+{
+    fn load(&mut self, dest: Reg, r: usize)
+    {
+        self.asm.load(dest, RBP, slot(r));
+    }
+
+    fn store(&mut self, r: usize, src: Reg)
+    {
+        self.asm.store(RBP, slot(r), src);
+    }
+
+    fn jump(&mut self, fixup: Fixup, target: usize)
+    {
+        self.jumps.push((fixup, target));
+    }
+
+    fn arith(&mut self, d: usize, a: usize, b: usize, op: fn(&mut Assembler, Reg, Reg))
+    {
+        self.load(RAX, a);
+        self.load(RCX, b);
+        op(&mut self.asm, RAX, RCX);
+        let bail = self.asm.jcc(Cond::Overflow);
+        self.bails.push(bail);
+        self.store(d, RAX);
+    }
+
+    fn add_imm(&mut self, d: usize, a: usize, imm: i64)
+    {
+        self.load(RAX, a);
+        self.asm.mov_ri(RCX, imm);
+        self.asm.add_rr(RAX, RCX);
+        let bail = self.asm.jcc(Cond::Overflow);
+        self.bails.push(bail);
+        self.store(d, RAX);
+    }
+
+    fn compare(&mut self, d: usize, a: usize, b: usize, cmp: Cmp)
+    {
+        self.load(RAX, a);
+        self.load(RCX, b);
+        self.asm.cmp_rr(RAX, RCX);
+        self.asm.set(cond(cmp), RAX);
+        self.store(d, RAX);
+    }
+}
+
+/// Compile `func`, returns `None` if function uses unsupported instructions or values
+pub fn compile(func: &VirtualFunction) -> Option<JitFunction>
+{
+    use self::Instruction::*;
+
+    if func.code.is_empty() {
+        return None;
+    }
+    let types = analyze(func)?;
+
+    let mut gen = Codegen {
+        asm: Assembler::new(),
+        jumps: Vec::new(),
+        bails: Vec::new(),
+        returns: Vec::new(),
+    };
+
+    // prologue, RDI points to arguments
+    gen.asm.push(RBP);
+    gen.asm.mov_rr(RBP, RSP);
+    let frame = (8 * func.nregs as i32 + 15) & !15;
+    gen.asm.sub_ri(RSP, frame);
+    for r in 1..=func.argc {
+        gen.asm.load(RAX, RDI, 8 * (r as i32 - 1));
+        gen.store(r, RAX);
+    }
+
+    let mut offsets = Vec::with_capacity(func.code.len());
+    for ins in func.code.iter() {
+        offsets.push(gen.asm.pos());
+        match *ins {
+            LoadLong(d, long) => {
+                gen.asm.mov_ri(RAX, long);
+                gen.store(d, RAX);
+            }
+            LoadBool(d, b) => {
+                gen.asm.mov_ri(RAX, b as i64);
+                gen.store(d, RAX);
+            }
+            Move(d, s) => {
+                gen.load(RAX, s);
+                gen.store(d, RAX);
+            }
+            Add(d, a, b) => gen.arith(d, a, b, Assembler::add_rr),
+            Sub(d, a, b) => gen.arith(d, a, b, Assembler::sub_rr),
+            Mul(d, a, b) => gen.arith(d, a, b, Assembler::imul_rr),
+            AddI(d, a, imm) => gen.add_imm(d, a, imm),
+            Inc(r) => gen.add_imm(r, r, 1),
+            Gt(d, a, b) => gen.compare(d, a, b, Cmp::Gt),
+            Lt(d, a, b) => gen.compare(d, a, b, Cmp::Lt),
+            Ge(d, a, b) => gen.compare(d, a, b, Cmp::Ge),
+            Le(d, a, b) => gen.compare(d, a, b, Cmp::Le),
+            Eq(d, a, b) => gen.compare(d, a, b, Cmp::Eq),
+            Neq(d, a, b) => gen.compare(d, a, b, Cmp::Neq),
+            JumpF(r, t) => {
+                gen.load(RAX, r);
+                gen.asm.test_rr(RAX, RAX);
+                let fixup = gen.asm.jcc(Cond::Equal);
+                gen.jump(fixup, t);
+            }
+            GotoF(r, l) => {
+                gen.load(RAX, r);
+                gen.asm.test_rr(RAX, RAX);
+                let fixup = gen.asm.jcc(Cond::Equal);
+                gen.jump(fixup, func.labels[&l]);
+            }
+            JumpCmpF(cmp, a, b, t) => {
+                gen.load(RAX, a);
+                gen.load(RCX, b);
+                gen.asm.cmp_rr(RAX, RCX);
+                let fixup = gen.asm.jcc(cond(cmp).negate());
+                gen.jump(fixup, t);
+            }
+            JumpCmpIF(cmp, a, imm, t) => {
+                gen.load(RAX, a);
+                gen.asm.mov_ri(RCX, imm);
+                gen.asm.cmp_rr(RAX, RCX);
+                let fixup = gen.asm.jcc(cond(cmp).negate());
+                gen.jump(fixup, t);
+            }
+            Jump(t) => {
+                let fixup = gen.asm.jmp();
+                gen.jump(fixup, t);
+            }
+            Goto(l) => {
+                let fixup = gen.asm.jmp();
+                gen.jump(fixup, func.labels[&l]);
+            }
+            Label(_) => {}
+            Ret(r) => {
+                let status = if types[r] == Some(Ty::Bool) {
+                    STATUS_BOOL
+                } else {
+                    STATUS_LONG
+                };
+                gen.load(RAX, r);
+                gen.asm.mov_ri(RDX, status);
+                let fixup = gen.asm.jmp();
+                gen.returns.push(fixup);
+            }
+            _ => return None,
+        }
+    }
+
+    let bail = gen.asm.pos();
+    gen.asm.mov_ri(RDX, STATUS_BAIL);
+    let epilogue = gen.asm.pos();
+    gen.asm.mov_rr(RSP, RBP);
+    gen.asm.pop(RBP);
+    gen.asm.ret();
+
+    for (fixup, target) in std::mem::take(&mut gen.jumps) {
+        gen.asm.bind(fixup, offsets[target]);
+    }
+    for fixup in std::mem::take(&mut gen.bails) {
+        gen.asm.bind(fixup, bail);
+    }
+    for fixup in std::mem::take(&mut gen.returns) {
+        gen.asm.bind(fixup, epilogue);
+    }
+
+    let memory = ExecutableMemory::new(&gen.asm.code)?;
+    Some(JitFunction {
+        memory,
+        argc: func.argc,
+    })
+}
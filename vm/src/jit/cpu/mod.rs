@@ -17,14 +17,14 @@ impl fmt::Debug for State
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
     {
-        println!("State:");
-        println!("\t pc = {:?}", self.pc as *const u8);
-        println!("\t sp = {:?}", self.sp as *const u8);
-        println!("\t ra = {:?}", self.ra as *const u8);
+        writeln!(f, "State:")?;
+        writeln!(f, "\t pc = {:?}", self.pc as *const u8)?;
+        writeln!(f, "\t sp = {:?}", self.sp as *const u8)?;
+        writeln!(f, "\t ra = {:?}", self.ra as *const u8)?;
         for (ind, &val) in self.regs.iter().enumerate() {
-            println!("R[{:2}] = {:-20?} {:-20}", ind, val as *const u8, val)
+            writeln!(f, "R[{:2}] = {:-20?} {:-20}", ind, val as *const u8, val)?;
         }
-        write!(f, "")
+        Ok(())
     }
 }
 
@@ -0,0 +1,249 @@
+use crate::jit::cpu::{x64::reg::*, Reg};
+
+/// Condition codes used by `jcc` and `setcc`
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Cond
+{
+    Overflow,
+    Equal,
+    NotEqual,
+    Less,
+    GreaterEq,
+    LessEq,
+    Greater,
+}
+
+impl Cond
+{
+    fn code(self) -> u8
+    {
+        match self {
+            Cond::Overflow => 0x0,
+            Cond::Equal => 0x4,
+            Cond::NotEqual => 0x5,
+            Cond::Less => 0xc,
+            Cond::GreaterEq => 0xd,
+            Cond::LessEq => 0xe,
+            Cond::Greater => 0xf,
+        }
+    }
+
+    /// Condition that holds when `self` doesn't
+    pub fn negate(self) -> Cond
+    {
+        match self {
+            Cond::Equal => Cond::NotEqual,
+            Cond::NotEqual => Cond::Equal,
+            Cond::Less => Cond::GreaterEq,
+            Cond::GreaterEq => Cond::Less,
+            Cond::LessEq => Cond::Greater,
+            Cond::Greater => Cond::LessEq,
+            Cond::Overflow => panic!("Overflow condition can't be negated"),
+        }
+    }
+}
+
+/// Position of jump whose 32-bit displacement is patched later
+#[derive(Copy, Clone, Debug)]
+pub struct Fixup(usize);
+
+/// x86-64 machine code emitter
+///
+/// Only 64-bit register/register and register/memory forms needed by baseline JIT are supported
+#[derive(Default)]
+pub struct Assembler
+{
+    pub code: Vec<u8>,
+}
+
+impl Assembler
+{
+    pub fn new() -> Assembler
+    {
+        Assembler { code: Vec::new() }
+    }
+
+    pub fn pos(&self) -> usize
+    {
+        self.code.len()
+    }
+
+    fn emit(&mut self, byte: u8)
+    {
+        self.code.push(byte);
+    }
+
+    fn emit32(&mut self, value: i32)
+    {
+        self.code.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn emit64(&mut self, value: i64)
+    {
+        self.code.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// REX prefix with W bit, `reg` extends ModRM.reg and `rm` extends ModRM.rm
+    fn rex_w(&mut self, reg: Reg, rm: Reg)
+    {
+        self.emit(0x48 | (reg.msb() << 2) | rm.msb());
+    }
+
+    fn modrm(&mut self, mode: u8, reg: u8, rm: u8)
+    {
+        self.emit((mode << 6) | (reg << 3) | rm);
+    }
+
+    /// ModRM and displacement for [base + disp]
+    fn mem(&mut self, reg: Reg, base: Reg, disp: i32)
+    {
+        self.modrm(0b10, reg.and7(), base.and7());
+        if base.and7() == RSP.and7() {
+            // SIB byte: no index, base
+            self.emit(0x24);
+        }
+        self.emit32(disp);
+    }
+
+    fn op_rr(&mut self, opcode: u8, dest: Reg, src: Reg)
+    {
+        self.rex_w(src, dest);
+        self.emit(opcode);
+        self.modrm(0b11, src.and7(), dest.and7());
+    }
+
+    /// push reg
+    pub fn push(&mut self, reg: Reg)
+    {
+        if reg.msb() != 0 {
+            self.emit(0x41);
+        }
+        self.emit(0x50 + reg.and7());
+    }
+
+    /// pop reg
+    pub fn pop(&mut self, reg: Reg)
+    {
+        if reg.msb() != 0 {
+            self.emit(0x41);
+        }
+        self.emit(0x58 + reg.and7());
+    }
+
+    pub fn ret(&mut self)
+    {
+        self.emit(0xc3);
+    }
+
+    /// mov dest, src
+    pub fn mov_rr(&mut self, dest: Reg, src: Reg)
+    {
+        self.op_rr(0x89, dest, src);
+    }
+
+    /// mov dest, imm64
+    pub fn mov_ri(&mut self, dest: Reg, imm: i64)
+    {
+        self.emit(0x48 | dest.msb());
+        self.emit(0xb8 + dest.and7());
+        self.emit64(imm);
+    }
+
+    /// mov dest, [base + disp]
+    pub fn load(&mut self, dest: Reg, base: Reg, disp: i32)
+    {
+        self.rex_w(dest, base);
+        self.emit(0x8b);
+        self.mem(dest, base, disp);
+    }
+
+    /// mov [base + disp], src
+    pub fn store(&mut self, base: Reg, disp: i32, src: Reg)
+    {
+        self.rex_w(src, base);
+        self.emit(0x89);
+        self.mem(src, base, disp);
+    }
+
+    /// add dest, src
+    pub fn add_rr(&mut self, dest: Reg, src: Reg)
+    {
+        self.op_rr(0x01, dest, src);
+    }
+
+    /// sub dest, src
+    pub fn sub_rr(&mut self, dest: Reg, src: Reg)
+    {
+        self.op_rr(0x29, dest, src);
+    }
+
+    /// imul dest, src
+    pub fn imul_rr(&mut self, dest: Reg, src: Reg)
+    {
+        self.rex_w(dest, src);
+        self.emit(0x0f);
+        self.emit(0xaf);
+        self.modrm(0b11, dest.and7(), src.and7());
+    }
+
+    /// sub dest, imm32
+    pub fn sub_ri(&mut self, dest: Reg, imm: i32)
+    {
+        self.rex_w(RAX, dest);
+        self.emit(0x81);
+        self.modrm(0b11, 5, dest.and7());
+        self.emit32(imm);
+    }
+
+    /// cmp lhs, rhs
+    pub fn cmp_rr(&mut self, lhs: Reg, rhs: Reg)
+    {
+        self.op_rr(0x39, lhs, rhs);
+    }
+
+    /// test lhs, rhs
+    pub fn test_rr(&mut self, lhs: Reg, rhs: Reg)
+    {
+        self.op_rr(0x85, lhs, rhs);
+    }
+
+    /// setcc dest; movzx dest, dest
+    ///
+    /// `dest` must be one of RAX, RBX, RCX, RDX
+    pub fn set(&mut self, cond: Cond, dest: Reg)
+    {
+        assert!(dest.is_basic_reg());
+        self.emit(0x0f);
+        self.emit(0x90 + cond.code());
+        self.modrm(0b11, 0, dest.and7());
+        self.rex_w(dest, dest);
+        self.emit(0x0f);
+        self.emit(0xb6);
+        self.modrm(0b11, dest.and7(), dest.and7());
+    }
+
+    /// jmp rel32, target is set by `bind`
+    pub fn jmp(&mut self) -> Fixup
+    {
+        self.emit(0xe9);
+        self.emit32(0);
+        Fixup(self.pos())
+    }
+
+    /// jcc rel32, target is set by `bind`
+    pub fn jcc(&mut self, cond: Cond) -> Fixup
+    {
+        self.emit(0x0f);
+        self.emit(0x80 + cond.code());
+        self.emit32(0);
+        Fixup(self.pos())
+    }
+
+    /// Make jump `fixup` go to `target` offset in code
+    pub fn bind(&mut self, fixup: Fixup, target: usize)
+    {
+        let Fixup(end) = fixup;
+        let disp = (target as i64 - end as i64) as i32;
+        self.code[end - 4..end].copy_from_slice(&disp.to_le_bytes());
+    }
+}
@@ -0,0 +1,63 @@
+use libc;
+use std::ptr;
+
+/// Executable copy of machine code
+///
+/// Code is written into fresh read-write pages which are then switched to read-execute,
+/// so memory is never writable and executable at the same time
+pub struct ExecutableMemory
+{
+    ptr: *mut u8,
+    size: usize,
+}
+
+// memory is immutable after creation
+unsafe impl Send for ExecutableMemory {}
+unsafe impl Sync for ExecutableMemory {}
+
+impl ExecutableMemory
+{
+    /// Map `code` as executable memory, returns `None` if mapping failed
+    pub fn new(code: &[u8]) -> Option<ExecutableMemory>
+    {
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let size = code.len().div_ceil(page).max(1) * page;
+
+        unsafe {
+            let ptr = libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANON,
+                -1,
+                0,
+            );
+            if ptr == libc::MAP_FAILED {
+                return None;
+            }
+            let ptr = ptr as *mut u8;
+            ptr::copy_nonoverlapping(code.as_ptr(), ptr, code.len());
+
+            if libc::mprotect(ptr as *mut libc::c_void, size, libc::PROT_READ | libc::PROT_EXEC) != 0 {
+                libc::munmap(ptr as *mut libc::c_void, size);
+                return None;
+            }
+            Some(ExecutableMemory { ptr, size })
+        }
+    }
+
+    pub fn as_ptr(&self) -> *const u8
+    {
+        self.ptr
+    }
+}
+
+impl Drop for ExecutableMemory
+{
+    fn drop(&mut self)
+    {
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.size);
+        }
+    }
+}
@@ -1 +1,120 @@
+//! Baseline JIT
+//!
+//! Function called more than `JIT_THRESHOLD` times is compiled to native code if it only works
+//! with `Long` and `Bool` values. Compiled code is entered only when all arguments are `Long`,
+//! otherwise and when compiled code bails out (e.g. on overflow) function runs in interpreter.
+
 pub mod cpu;
+#[cfg(all(target_arch = "x86_64", unix))]
+pub mod compiler;
+#[cfg(unix)]
+pub mod memory;
+
+use crate::{function::VirtualFunction, value::Value};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    OnceLock,
+};
+
+/// Count of calls after which function is compiled
+pub const JIT_THRESHOLD: usize = 1000;
+
+/// Compiled code returned `Long`
+pub const STATUS_LONG: i64 = 0;
+/// Compiled code can't continue, function should be executed by interpreter
+pub const STATUS_BAIL: i64 = 1;
+/// Compiled code returned `Bool`
+pub const STATUS_BOOL: i64 = 2;
+
+/// Value returned by compiled code in RAX:RDX
+#[repr(C)]
+struct JitResult
+{
+    value: i64,
+    status: i64,
+}
+
+/// Native code of function
+pub struct JitFunction
+{
+    #[cfg(unix)]
+    memory: memory::ExecutableMemory,
+    pub argc: usize,
+}
+
+impl JitFunction
+{
+    /// Run compiled code, returns `None` if code bailed out
+    pub fn run(&self, args: &[i64]) -> Option<Value>
+    {
+        assert_eq!(args.len(), self.argc);
+        #[cfg(unix)]
+        {
+            let f: extern "C" fn(*const i64) -> JitResult =
+                unsafe { std::mem::transmute(self.memory.as_ptr()) };
+            let result = f(args.as_ptr());
+            match result.status {
+                STATUS_LONG => Some(Value::Long(result.value)),
+                STATUS_BOOL => Some(Value::Bool(result.value != 0)),
+                _ => None,
+            }
+        }
+        #[cfg(not(unix))]
+        None
+    }
+}
+
+/// Compile `func` to native code
+///
+/// Returns `None` if function can't be compiled or JIT isn't supported on this platform
+pub fn compile(func: &VirtualFunction) -> Option<JitFunction>
+{
+    #[cfg(all(target_arch = "x86_64", unix))]
+    {
+        compiler::compile(func)
+    }
+    #[cfg(not(all(target_arch = "x86_64", unix)))]
+    {
+        let _ = func;
+        None
+    }
+}
+
+/// Call counter and compiled code of function
+#[derive(Default)]
+pub struct JitState
+{
+    calls: AtomicUsize,
+    code: OnceLock<Option<JitFunction>>,
+}
+
+impl JitState
+{
+    /// Whether function was compiled to native code
+    pub fn compiled(&self) -> bool
+    {
+        matches!(self.code.get(), Some(Some(_)))
+    }
+
+    /// Count call of `func`, returns compiled code when function is hot
+    pub fn hot(&self, func: &VirtualFunction) -> Option<&JitFunction>
+    {
+        if let Some(code) = self.code.get() {
+            return code.as_ref();
+        }
+        if self.calls.fetch_add(1, Ordering::Relaxed) + 1 < JIT_THRESHOLD {
+            return None;
+        }
+        self.code.get_or_init(|| compile(func)).as_ref()
+    }
+}
+
+use std::fmt;
+
+impl fmt::Debug for JitState
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "JitState {{ calls: {:?} }}", self.calls)
+    }
+}
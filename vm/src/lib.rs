@@ -19,21 +19,28 @@
 #![warn(rust_2018_idioms)]
 #![allow(non_snake_case)]
 
+pub mod array;
+pub mod bigint;
 pub mod frame;
 pub mod function;
+pub mod generator;
 pub mod index;
+pub mod iterator;
 pub mod jit;
 pub mod machine;
+pub mod map;
+pub mod numeric;
 pub mod object;
 pub mod object_info;
 pub mod object_pool;
 pub mod opcodes;
+pub mod optimizer;
+pub mod range;
 pub mod static_root;
 pub mod string;
 pub mod value;
 pub mod error;
-
-use time;
+pub mod verifier;
 
 pub mod prelude
 {
@@ -1,25 +1,68 @@
-use crate::{frame::*, object::ObjectAddon, object_pool::ObjectPool, opcodes::*, value::Value};
+use crate::{
+    error::VmError, frame::*, numeric::{self, Arith, Bits}, opcodes::Cmp, function::{Function, VirtualFunction}, generator::Generator, object::ObjectAddon, object_pool::ObjectPool, opcodes::*, string, value::Value
+};
 use std::collections::HashMap;
-use crate::error::VmError;
-
-macro_rules! for_c {
-    ($v:ident = $v1:expr; $e:expr;$ex:expr, $b: block) => {
-        let mut $v = $v1;
-        while $e {
-            $b
-            $ex
-        }
-    };
-}
+
+/// Capacity of register file
+///
+/// Register file is allocated once and never reallocated: native functions borrow their arguments straight from it
+pub const MAX_REGISTERS: usize = 1 << 20;
 
 ///Machine that executes code
-#[derive(Default)]
 pub struct Machine
 {
     pub stack: Vec<CallFrame>,
+    /// Register file, every frame owns a window of it
+    pub registers: Vec<Value>,
     pub pool: ObjectPool,
     pub globals: HashMap<usize, Value>,
-    pub labels: HashMap<usize, usize>,
+    /// Id of every named global, names are bound by loader of compiled modules
+    pub names: HashMap<String, usize>,
+    /// Compile hot functions to native code, see `jit`
+    pub jit: bool,
+    /// Error raised by native code, see `raise`
+    error: Option<VmError>,
+    /// Frames suspended by `Yield`, see `resume`
+    suspended: Option<Fiber>,
+    /// Native function asked to suspend script, see `pause`
+    pausing: bool,
+    /// Script suspended by native function, see `start`
+    paused: Option<Continuation>,
+}
+
+/// Way dispatch loop was entered, decides what can suspend it
+#[derive(Copy, Clone, PartialEq)]
+enum Mode
+{
+    /// Called from Rust code which waits for result
+    Call,
+    /// Resumed generator, `Yield` suspends it
+    Generator,
+    /// Started by host, native function can suspend it
+    Host,
+}
+
+/// Result of script run by `Machine::start` or `Continuation::resume`
+#[derive(Debug)]
+pub enum Step
+{
+    /// Value returned by script
+    Finished(Value),
+    /// Native function paused script, host resumes it with result of that native
+    Suspended(Continuation),
+}
+
+fn cannot_pause() -> VmError
+{
+    VmError::RuntimeError("pause outside of resumable script".into())
+}
+
+impl Default for Machine
+{
+    fn default() -> Machine
+    {
+        Machine::new()
+    }
 }
 
 impl Machine
@@ -28,9 +71,15 @@ impl Machine
     {
         Machine {
             stack: Vec::with_capacity(4096),
+            registers: Vec::with_capacity(MAX_REGISTERS),
             pool: ObjectPool::new(),
             globals: HashMap::new(),
-            labels: HashMap::new(),
+            names: HashMap::new(),
+            jit: true,
+            error: None,
+            suspended: None,
+            pausing: false,
+            paused: None,
         }
     }
     /// Get last frame in CallStack
@@ -47,84 +96,405 @@ impl Machine
     /// Get value for register
     pub fn get(&mut self, rnum: usize) -> Value
     {
-        self.last_frame().get(rnum)
+        self.registers[self.last_frame().base + rnum]
     }
 
     /// Set `this` value
     pub fn set_this(&mut self, v: Value)
     {
-        self.last_frame_mut().stack[0] = v;
+        self.set(0, v);
     }
     /// Set R(r) = v
     pub fn set(&mut self, r: usize, v: Value)
     {
-        self.last_frame_mut().set(r, v);
+        let base = self.last_frame().base;
+        self.registers[base + r] = v;
     }
     /// Update instruction pointer
     pub fn dispatch(&mut self)
     {
         self.last_frame_mut().ip += 1;
     }
+    /// Index of first free register in register file
+    pub fn top(&self) -> usize
+    {
+        self.stack.last().map_or(0, CallFrame::top)
+    }
     /// Invoke callable object
-    pub fn invoke(&mut self, callable: Value, args: Vec<Value>) -> Value
+    ///
+    /// `args[0]` is `this` value, other values are arguments
+    pub fn invoke(&mut self, callable: Value, args: &[Value]) -> Result<Value, VmError>
     {
         let id = match callable {
             Value::Object(id) => id,
-            v => {
-                panic!("Not callable {:?}", v);
+            v => return Err(VmError::RuntimeError(format!("Not callable {:?}", v))),
+        };
+
+        let obj = self.pool.get(id);
+        let value = obj.call(self, args);
+        self.raised()?;
+        if self.pausing {
+            self.pausing = false;
+            return Err(cannot_pause());
+        }
+        Ok(value)
+    }
+
+    /// Invoke callable object as script that native functions can pause
+    ///
+    /// `args[0]` is `this` value, other values are arguments. Script function runs until it returns or some
+    /// native it calls uses `pause`, any other callable runs to completion as with `invoke`
+    pub fn start(&mut self, callable: Value, args: &[Value]) -> Result<Step, VmError>
+    {
+        let id = match callable {
+            Value::Object(id) => id,
+            v => return Err(VmError::RuntimeError(format!("Not callable {:?}", v))),
+        };
+
+        let obj = self.pool.get(id);
+        match obj.as_any().downcast_ref::<Function>() {
+            Some(Function::Virtual(vf)) if !vf.generator => {
+                let depth = self.stack.len() + 1;
+                self.enter_at(vf, self.top(), args)?;
+                let value = self.run(depth, Mode::Host)?;
+                Ok(self.step(value))
+            }
+            _ => Ok(Step::Finished(self.invoke(callable, args)?)),
+        }
+    }
+
+    /// Continue script of `continuation`, native function that paused it returns `value`
+    pub(crate) fn proceed(&mut self, continuation: Continuation, value: Value) -> Result<Step, VmError>
+    {
+        let depth = self.stack.len() + 1;
+        self.attach(continuation.fiber)?;
+        match continuation.dest {
+            Some(dest) => self.set(dest, value),
+            None => {
+                if let Some(ret) = self.leave(value, depth) {
+                    return Ok(Step::Finished(ret));
+                }
             }
+        }
+        let value = self.run(depth, Mode::Host)?;
+        Ok(self.step(value))
+    }
+
+    /// Outcome of host run that stopped with `value`
+    fn step(&mut self, value: Value) -> Step
+    {
+        match self.paused.take() {
+            Some(continuation) => Step::Suspended(continuation),
+            None => Step::Finished(value),
+        }
+    }
+
+    /// Suspend script that called native function
+    ///
+    /// Native calls it and returns any value. Script stops right after the call and host gets it as
+    /// `Step::Suspended`, value host resumes it with becomes result of the call. Only script started by
+    /// `start` can be paused: natives called by script function which Rust code waits for, e.g. callback of
+    /// `Array::map` or body of generator, get an error instead.
+    pub fn pause(&mut self)
+    {
+        self.pausing = true;
+    }
+
+    /// Stop dispatch loop after native paused the script, R(dest) of current frame receives result of the
+    /// native or current frame returns it if `dest` is `None`
+    fn stop(&mut self, depth: usize, mode: Mode, dest: Option<usize>) -> Result<Value, VmError>
+    {
+        self.pausing = false;
+        if mode != Mode::Host {
+            return Err(cannot_pause());
+        }
+        let fiber = self.detach(depth);
+        self.paused = Some(Continuation { fiber, dest });
+        Ok(Value::Null)
+    }
+
+    /// Report error from native code
+    ///
+    /// Natives return plain values, so they raise error here and return any value right after. Error is
+    /// returned by instruction or `invoke` that called the native, only the first one is kept.
+    pub fn raise(&mut self, error: VmError)
+    {
+        self.error.get_or_insert(error);
+    }
+
+    /// Error raised since last check
+    fn raised(&mut self) -> Result<(), VmError>
+    {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Copy of registers `base..base + len`, callee that gets it may change register file
+    fn arguments(&self, base: usize, len: usize) -> Vec<Value>
+    {
+        self.registers[base..base + len].to_vec()
+    }
+
+    /// Make sure registers `base..base + size` exist
+    fn reserve(&mut self, base: usize, size: usize) -> Result<(), VmError>
+    {
+        if base + size > MAX_REGISTERS {
+            return Err(VmError::StackOverflow);
+        }
+        if self.registers.len() < base + size {
+            self.registers.resize(base + size, Value::Null);
+        }
+        Ok(())
+    }
+
+    /// Push frame for `func` which registers start at `base`, first `nargs` registers
+    /// already hold `this` and arguments, the rest is cleared
+    fn push_frame(
+        &mut self,
+        func: &VirtualFunction,
+        base: usize,
+        nargs: usize,
+        dest: usize,
+    ) -> Result<(), VmError>
+    {
+        let size = func.nregs.max(nargs);
+        self.reserve(base, size)?;
+        for reg in &mut self.registers[base + nargs..base + size] {
+            *reg = Value::Null;
+        }
+        self.stack.push(CallFrame::new(
+            func.code.clone(),
+            func.labels.clone(),
+            base,
+            size,
+            dest,
+        ));
+        Ok(())
+    }
+
+    /// Copy `args` into registers starting at `base` and push frame for `func` onto them
+    pub fn enter_at(&mut self, func: &VirtualFunction, base: usize, args: &[Value]) -> Result<(), VmError>
+    {
+        self.reserve(base, args.len())?;
+        self.registers[base..base + args.len()].copy_from_slice(args);
+        self.push_frame(func, base, args.len(), 0)
+    }
+
+    /// Call `callee` with `this` and arguments stored in registers `base..base + nargs`
+    ///
+    /// Virtual function gets new frame mapped onto these registers, that frame is executed by dispatch loop.
+    /// Any other object is called right away and result is stored into R(dest)
+    fn enter(&mut self, callee: Value, base: usize, nargs: usize, dest: usize) -> Result<(), VmError>
+    {
+        let id = match callee {
+            Value::Object(id) => id,
+            v => return Err(VmError::RuntimeError(format!("Not callable {:?}", v))),
+        };
+
+        let obj = self.pool.get(id);
+        if let Some(Function::Virtual(vf)) = obj.as_any().downcast_ref::<Function>() {
+            if vf.generator {
+                let value = Generator::allocate(self, vf, &self.arguments(base, nargs));
+                self.set(dest, value);
+                return Ok(());
+            }
+            if let Some(value) = self.run_jit(vf, base, nargs) {
+                self.set(dest, value);
+                return Ok(());
+            }
+            return self.push_frame(vf, base, nargs, dest);
+        }
+
+        let args = self.arguments(base, nargs);
+        let value = obj.call(self, &args);
+        self.raised()?;
+        self.set(dest, value);
+        Ok(())
+    }
+
+    /// Call `callee` in place of current frame with `this` and arguments stored in registers `base..base + nargs`
+    ///
+    /// Virtual function gets current frame: arguments are moved down to its R(0) and returned value goes
+    /// to the caller of current frame. Any other object is called right away and its result is returned.
+    /// Returns `Some` when dispatch loop should stop, same as `leave`
+    fn tail_enter(&mut self, callee: Value, base: usize, nargs: usize, depth: usize) -> Result<Option<Value>, VmError>
+    {
+        let id = match callee {
+            Value::Object(id) => id,
+            v => return Err(VmError::RuntimeError(format!("Not callable {:?}", v))),
         };
 
         let obj = self.pool.get(id);
-        self.stack.push(CallFrame::new());
+        if let Some(Function::Virtual(vf)) = obj.as_any().downcast_ref::<Function>() {
+            if vf.generator {
+                let value = Generator::allocate(self, vf, &self.arguments(base, nargs));
+                return Ok(self.leave(value, depth));
+            }
+            if let Some(value) = self.run_jit(vf, base, nargs) {
+                return Ok(self.leave(value, depth));
+            }
+            let frame = self.stack.pop().unwrap();
+            self.registers
+                .copy_within(base..base + nargs, frame.base);
+            self.push_frame(vf, frame.base, nargs, frame.dest)?;
+            return Ok(None);
+        }
+
+        let args = self.arguments(base, nargs);
+        let value = obj.call(self, &args);
+        self.raised()?;
+        if self.pausing {
+            return Ok(None);
+        }
+        Ok(self.leave(value, depth))
+    }
 
-        self.last_frame_mut().init_with_args(&args.as_slice());
-        obj.call(self, args)
+    /// Run native code of `func` if it's hot and all arguments in registers `base + 1..base + nargs` are `Long`
+    ///
+    /// Returns `None` when function should be executed by interpreter
+    fn run_jit(&mut self, func: &VirtualFunction, base: usize, nargs: usize) -> Option<Value>
+    {
+        if !self.jit || nargs != func.argc + 1 {
+            return None;
+        }
+        let code = func.jit.hot(func)?;
+        let mut args = Vec::with_capacity(func.argc);
+        for value in &self.registers[base + 1..base + nargs] {
+            match value {
+                Value::Long(l) => args.push(*l),
+                _ => return None,
+            }
+        }
+        code.run(&args)
     }
-    /// Goto 
+
+    /// Pop current frame and pass returned value to caller
+    ///
+    /// Returns `Some` when frame was entered from Rust code and dispatch loop should stop
+    fn leave(&mut self, value: Value, depth: usize) -> Option<Value>
+    {
+        let frame = self.stack.pop().unwrap();
+        if self.stack.len() < depth {
+            return Some(value);
+        }
+        self.set(frame.dest, value);
+        None
+    }
+
+    /// Result of comparison for fused compare-and-jump instructions, Long values are compared right away
+    fn test(&mut self, cmp: Cmp, v1: Value, v2: Value) -> Result<bool, VmError>
+    {
+        if let (Value::Long(l1), Value::Long(l2)) = (v1, v2) {
+            return Ok(match cmp {
+                Cmp::Gt => l1 > l2,
+                Cmp::Lt => l1 < l2,
+                Cmp::Ge => l1 >= l2,
+                Cmp::Le => l1 <= l2,
+                Cmp::Eq => l1 == l2,
+                Cmp::Neq => l1 != l2,
+            });
+        }
+        numeric::compare(self, cmp, v1, v2)
+    }
+
+    /// Index of label `id` in code of current frame
+    fn label(&self, id: usize) -> Result<usize, VmError>
+    {
+        self.last_frame()
+            .labels
+            .get(&id)
+            .cloned()
+            .ok_or(VmError::LabelNotFound(id))
+    }
+
+    /// Goto
     pub fn branch(&mut self, idx: usize)
     {
         self.last_frame_mut().ip = idx;
     }
-    /// Run instructions
-    pub fn run_code(&mut self, code: Vec<Instruction>) -> Result<Value,VmError>
+    /// Verify instructions and run them in new frame
+    pub fn run_code(&mut self, code: Vec<Instruction>) -> Result<Value, VmError>
+    {
+        crate::verifier::verify(&code, self)?;
+        let func = VirtualFunction::new(code, 0);
+        let base = self.top();
+        self.enter_at(&func, base, &[])?;
+        self.execute_op()
+    }
+    /// Execute opcodes until current frame returns
+    pub fn execute_op(&mut self) -> Result<Value, VmError>
     {
-        for_c!(i = 0;i < code.len();i += 1, {
-            if let Instruction::Label(lbl_id) = code[i] {
-                
-                self.labels.insert(lbl_id, i);
-                
-            };
-        });
+        self.run(self.stack.len(), Mode::Call)
+    }
 
-        self.last_frame_mut().code = code;
-        self.last_frame_mut().ip = 0;
+    /// Execute opcodes until frame at `depth` returns or `mode` allows to suspend it
+    fn run(&mut self, depth: usize, mode: Mode) -> Result<Value, VmError>
+    {
+        let result = self.dispatch_loop(depth, mode);
+        if result.is_err() {
+            self.stack.truncate(depth - 1);
+            self.pausing = false;
+        }
+        result
+    }
 
-        self.execute_op()
+    /// Remove frames from `depth` up together with their registers
+    fn detach(&mut self, depth: usize) -> Fiber
+    {
+        let mut frames = self.stack.split_off(depth - 1);
+        let start = frames[0].base;
+        let end = frames.iter().map(CallFrame::top).max().unwrap_or(start);
+        for frame in &mut frames {
+            frame.base -= start;
+        }
+        Fiber {
+            frames,
+            registers: self.registers[start..end].to_vec(),
+        }
+    }
+
+    /// Continue execution of `fiber` on top of call stack
+    ///
+    /// Returns value passed to `Yield` together with fiber suspended by it, or value returned by bottom
+    /// frame of fiber and `None`
+    pub fn resume(&mut self, fiber: Fiber) -> Result<(Value, Option<Fiber>), VmError>
+    {
+        let depth = self.stack.len() + 1;
+        self.attach(fiber)?;
+        let value = self.run(depth, Mode::Generator)?;
+        Ok((value, self.suspended.take()))
     }
-    /// Execute all opcodes in current frame
-    pub fn execute_op(&mut self) -> Result<Value,VmError>
+
+    /// Push frames of `fiber` on top of call stack
+    fn attach(&mut self, fiber: Fiber) -> Result<(), VmError>
     {
-        let mut returns = false;
-        let mut ret = Value::Null;
-        let start = super::time::PreciseTime::now();
+        let base = self.top();
+        self.reserve(base, fiber.registers.len())?;
+        self.registers[base..base + fiber.registers.len()].copy_from_slice(&fiber.registers);
+        for mut frame in fiber.frames {
+            frame.base += base;
+            self.stack.push(frame);
+        }
+        Ok(())
+    }
 
-        while self.last_frame().ip < self.last_frame().code.len() {
-            if returns {
-                break;
+    fn dispatch_loop(&mut self, depth: usize, mode: Mode) -> Result<Value, VmError>
+    {
+        loop {
+            if self.last_frame().ip >= self.last_frame().code.len() {
+                if let Some(ret) = self.leave(Value::Null, depth) {
+                    return Ok(ret);
+                }
+                continue;
             }
 
             let opcode = self.last_frame().code[self.last_frame().ip].clone();
             self.last_frame_mut().ip += 1;
-            //println!("{:?}",self.last_frame().code[self.last_frame().ip]);
             match &opcode {
                 Instruction::Label(_label_id) => {}
 
-                Instruction::LoadArg(r1) => {
-                    let value = self.get(*r1);
-                    self.last_frame_mut().arg_stack.push(value);
-                }
-
                 Instruction::LoadBool(dest, boolean) => {
                     self.set(*dest, Value::Bool(*boolean));
                 }
@@ -143,6 +513,10 @@ impl Machine
                     self.set(*dest, Value::Double(*double));
                 }
 
+                Instruction::LoadChar(dest, c) => {
+                    self.set(*dest, Value::Char(*c));
+                }
+
                 Instruction::LoadLong(dest, long) => {
                     self.set(*dest, Value::Long(*long));
                 }
@@ -154,135 +528,125 @@ impl Machine
                 Instruction::Isa(dest,r1,r2) => {
                     let (v1,v2) = (self.get(*r1),self.get(*r2));
                     let n = v2.typename(self);
-                    println!("{:?}",v2.typename(self));                  
                     let result = v1.isa(n,self);
                     self.set(*dest,Value::Bool(result));
                 }
 
                 Instruction::Add(dest, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-
-                    let result = match (v1, v2) {
-                        (Value::Int(i), Value::Int(i2)) => Value::Int(i + i2),
-                        (Value::Float(f), Value::Float(f2)) => Value::Float(f + f2),
-                        (Value::Long(i), Value::Long(i2)) => Value::Long(i + i2),
-                        (Value::Double(f), Value::Double(f2)) => Value::Double(f + f2),
-                        (Value::Int(i), Value::Long(i2)) => Value::Long((i as i64) + i2),
-                        (Value::Long(i), Value::Int(i2)) => Value::Long(i + (i2 as i64)),
-                        (Value::Float(f), Value::Double(f2)) => Value::Double((f as f64) + f2),
-                        (Value::Double(f), Value::Float(f2)) => Value::Double(f + (f2 as f64)),
-                        (Value::Long(l), v) => Value::Long(l + v.to_long(self)),
-                        (Value::Int(i), v) => Value::Int(i + v.to_int(self)),
-                        (Value::Double(d), v) => Value::Double(d + v.to_double(self)),
-                        (Value::Float(f), v) => Value::Float(f + v.to_float(self)),
-                        (v, Value::Null) => v,
-                        (Value::Null, v) => v,
-                        v => panic!("{:?}", v),
+                    let result = match string::concat(self, v1, v2) {
+                        Some(s) => s,
+                        None => numeric::arith(self, Arith::Add, v1, v2)?,
                     };
-
                     self.set(*dest, result);
                 }
 
-                Instruction::Call(dest, r2, argc) => {
-                    let args = {
-                        let mut temp: Vec<Value> = vec![];
-                        let this = self
-                            .last_frame_mut()
-                            .arg_stack
-                            .pop()
-                            .expect("Expected this value");
-
-                        temp.push(this);
+                Instruction::AddI(dest, r1, imm) => {
+                    let v = self.get(*r1);
+                    let fast = match v {
+                        Value::Long(l) => l.checked_add(*imm),
+                        _ => None,
+                    };
+                    let result = match fast {
+                        Some(l) => Value::Long(l),
+                        None => numeric::arith(self, Arith::Add, v, Value::Long(*imm))?,
+                    };
+                    self.set(*dest, result);
+                }
 
-                        for _ in 0..*argc {
-                            let v = self.last_frame_mut().arg_stack.pop();
+                Instruction::Inc(r1) => {
+                    let v = self.get(*r1);
+                    let fast = match v {
+                        Value::Long(l) => l.checked_add(1),
+                        _ => None,
+                    };
+                    let result = match fast {
+                        Some(l) => Value::Long(l),
+                        None => numeric::arith(self, Arith::Add, v, Value::Long(1))?,
+                    };
+                    self.set(*r1, result);
+                }
 
-                            match v {
-                                None => temp.push(Value::Null), // if less arguments are passed then fill the holes with Null values
-                                Some(v) => temp.push(v),
-                            };
-                        }
+                Instruction::JumpCmpF(cmp, r1, r2, idx) => {
+                    let (v1, v2) = (self.get(*r1), self.get(*r2));
+                    if !self.test(*cmp, v1, v2)? {
+                        self.branch(*idx);
+                    }
+                }
 
-                        temp
-                    };
+                Instruction::JumpCmpIF(cmp, r1, imm, idx) => {
+                    let v1 = self.get(*r1);
+                    if !self.test(*cmp, v1, Value::Long(*imm))? {
+                        self.branch(*idx);
+                    }
+                }
 
-                    let value = self.get(*r2);
-                    let v = self.invoke(value, args);
-                    self.stack.pop();
-                    self.set(*dest, v);
+                Instruction::Call(dest, r1, argc) => {
+                    let callee = self.get(*r1);
+                    let base = self.last_frame().base + r1 + 1;
+                    self.enter(callee, base, argc + 1, *dest)?;
+                    if self.pausing {
+                        return self.stop(depth, mode, Some(*dest));
+                    }
+                }
+                Instruction::TailCall(r1, argc) => {
+                    let callee = self.get(*r1);
+                    let base = self.last_frame().base + r1 + 1;
+                    if let Some(ret) = self.tail_enter(callee, base, argc + 1, depth)? {
+                        return Ok(ret);
+                    }
+                    if self.pausing {
+                        return self.stop(depth, mode, None);
+                    }
                 }
                 Instruction::Sub(dest, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-
-                    let result = match (v1, v2) {
-                        (Value::Int(i), Value::Int(i2)) => Value::Int(i - i2),
-                        (Value::Float(f), Value::Float(f2)) => Value::Float(f - f2),
-                        (Value::Long(i), Value::Long(i2)) => Value::Long(i - i2),
-                        (Value::Double(f), Value::Double(f2)) => Value::Double(f - f2),
-                        (Value::Int(i), Value::Long(i2)) => Value::Long((i as i64) - i2),
-                        (Value::Long(i), Value::Int(i2)) => Value::Long(i - (i2 as i64)),
-                        (Value::Float(f), Value::Double(f2)) => Value::Double((f as f64) - f2),
-                        (Value::Double(f), Value::Float(f2)) => Value::Double(f - (f2 as f64)),
-                        (Value::Long(l), v) => Value::Long(l - v.to_long(self)),
-                        (Value::Int(i), v) => Value::Int(i - v.to_int(self)),
-                        (Value::Double(d), v) => Value::Double(d - v.to_double(self)),
-                        (Value::Float(f), v) => Value::Float(f - v.to_float(self)),
-                        (v, Value::Null) => v,
-                        (Value::Null, v) => v,
-                        _ => unimplemented!(),
-                    };
-
+                    let result = numeric::arith(self, Arith::Sub, v1, v2)?;
                     self.set(*dest, result);
                 }
 
                 Instruction::Div(dest, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-
-                    let result = match (v1, v2) {
-                        (Value::Int(i), Value::Int(i2)) => Value::Int(i / i2),
-                        (Value::Float(f), Value::Float(f2)) => Value::Float(f / f2),
-                        (Value::Long(i), Value::Long(i2)) => Value::Long(i / i2),
-                        (Value::Double(f), Value::Double(f2)) => Value::Double(f / f2),
-                        (Value::Int(i), Value::Long(i2)) => Value::Long((i as i64) / i2),
-                        (Value::Long(i), Value::Int(i2)) => Value::Long(i / (i2 as i64)),
-                        (Value::Float(f), Value::Double(f2)) => Value::Double((f as f64) / f2),
-                        (Value::Double(f), Value::Float(f2)) => Value::Double(f / (f2 as f64)),
-                        (Value::Long(l), v) => Value::Long(l / v.to_long(self)),
-                        (Value::Int(i), v) => Value::Int(i / v.to_int(self)),
-                        (Value::Double(d), v) => Value::Double(d / v.to_double(self)),
-                        (Value::Float(f), v) => Value::Float(f / v.to_float(self)),
-                        (v, Value::Null) => v,
-                        (Value::Null, v) => v,
-                        _ => unimplemented!(),
-                    };
-
+                    let result = numeric::arith(self, Arith::Div, v1, v2)?;
                     self.set(*dest, result);
                 }
 
                 Instruction::Mul(dest, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
+                    let result = numeric::arith(self, Arith::Mul, v1, v2)?;
+                    self.set(*dest, result);
+                }
 
-                    let result = match (v1, v2) {
-                        (Value::Int(i), Value::Int(i2)) => Value::Int(i * i2),
-                        (Value::Float(f), Value::Float(f2)) => Value::Float(f * f2),
-                        (Value::Long(i), Value::Long(i2)) => Value::Long(i * i2),
-                        (Value::Double(f), Value::Double(f2)) => Value::Double(f * f2),
-                        (Value::Int(i), Value::Long(i2)) => Value::Long((i as i64) * i2),
-                        (Value::Long(i), Value::Int(i2)) => Value::Long(i * (i2 as i64)),
-                        (Value::Float(f), Value::Double(f2)) => Value::Double((f as f64) * f2),
-                        (Value::Double(f), Value::Float(f2)) => Value::Double(f * (f2 as f64)),
-                        (Value::Long(l), v) => Value::Long(l * v.to_long(self)),
-                        (Value::Int(i), v) => Value::Int(i * v.to_int(self)),
-                        (Value::Double(d), v) => Value::Double(d * v.to_double(self)),
-                        (Value::Float(f), v) => Value::Float(f * v.to_float(self)),
-                        (v, Value::Null) => v,
-                        (Value::Null, v) => v,
-                        v => panic!("Cannot mul {:?}", v),
-                    };
+                Instruction::Rem(dest, r1, r2) => {
+                    let (v1, v2) = (self.get(*r1), self.get(*r2));
+                    let result = numeric::arith(self, Arith::Rem, v1, v2)?;
+                    self.set(*dest, result);
+                }
 
+                Instruction::IntDiv(dest, r1, r2) => {
+                    let (v1, v2) = (self.get(*r1), self.get(*r2));
+                    let result = numeric::arith(self, Arith::IntDiv, v1, v2)?;
                     self.set(*dest, result);
                 }
 
+                Instruction::Pow(dest, r1, r2) => {
+                    let (v1, v2) = (self.get(*r1), self.get(*r2));
+                    let result = numeric::arith(self, Arith::Pow, v1, v2)?;
+                    self.set(*dest, result);
+                }
+
+                Instruction::Neg(r1, r2) => {
+                    let v = self.get(*r2);
+                    let result = numeric::neg(self, v)?;
+                    self.set(*r1, result);
+                }
+
+                Instruction::BitNot(r1, r2) => {
+                    let v = self.get(*r2);
+                    let result = numeric::bit_not(self, v)?;
+                    self.set(*r1, result);
+                }
+
                 Instruction::LoadConst(r1, idx) => {
                     self.set(*r1, Value::Object(*idx));
                 }
@@ -295,228 +659,73 @@ impl Machine
 
                 Instruction::Gt(dest, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-                    let result = match (v1, v2) {
-                        (Value::Int(i), Value::Int(i2)) => Value::Bool(i > i2),
-                        (Value::Long(i), Value::Long(i2)) => Value::Bool(i > i2),
-                        (Value::Float(f), Value::Float(f2)) => Value::Bool(f > f2),
-                        (Value::Double(f), Value::Double(f2)) => Value::Bool(f > f2),
-                        (Value::Int(i), Value::Long(i2)) => Value::Bool((i as i64) > i2),
-                        (Value::Long(i), Value::Int(i2)) => Value::Bool(i > (i2 as i64)),
-                        (Value::Float(f), Value::Double(f2)) => Value::Bool((f as f64) > f2),
-                        (Value::Double(f), Value::Float(f2)) => Value::Bool(f > (f2 as f64)),
-                        (Value::Long(l), v) => Value::Bool(l > v.to_long(self)),
-                        (Value::Int(i), v) => Value::Bool(i > v.to_int(self)),
-                        (Value::Double(d), v) => Value::Bool(d > v.to_double(self)),
-                        (Value::Float(f), v) => Value::Bool(f > v.to_float(self)),
-                        (v, Value::Null) => v,
-                        (Value::Null, v) => v,
-                        v => panic!("{:?}", v),
-                    };
-
-                    self.set(*dest, result);
+                    let result = numeric::compare(self, Cmp::Gt, v1, v2)?;
+                    self.set(*dest, Value::Bool(result));
                 }
                 Instruction::Ge(dest, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-                    let result = match (v1, v2) {
-                        (Value::Int(i), Value::Int(i2)) => Value::Bool(i >= i2),
-                        (Value::Long(i), Value::Long(i2)) => Value::Bool(i >= i2),
-                        (Value::Float(f), Value::Float(f2)) => Value::Bool(f >= f2),
-                        (Value::Double(f), Value::Double(f2)) => Value::Bool(f >= f2),
-                        (Value::Int(i), Value::Long(i2)) => Value::Bool((i as i64) >= i2),
-                        (Value::Long(i), Value::Int(i2)) => Value::Bool(i >= (i2 as i64)),
-                        (Value::Float(f), Value::Double(f2)) => Value::Bool((f as f64) >= f2),
-                        (Value::Double(f), Value::Float(f2)) => Value::Bool(f >= (f2 as f64)),
-                        (Value::Long(l), v) => Value::Bool(l >= v.to_long(self)),
-                        (Value::Int(i), v) => Value::Bool(i >= v.to_int(self)),
-                        (Value::Double(d), v) => Value::Bool(d >= v.to_double(self)),
-                        (Value::Float(f), v) => Value::Bool(f >= v.to_float(self)),
-                        (v, Value::Null) => v,
-                        (Value::Null, v) => v,
-                        _ => unimplemented!(),
-                    };
-
-                    self.set(*dest, result);
+                    let result = numeric::compare(self, Cmp::Ge, v1, v2)?;
+                    self.set(*dest, Value::Bool(result));
                 }
 
                 Instruction::Le(dest, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-                    let result = match (v1, v2) {
-                        (Value::Int(i), Value::Int(i2)) => Value::Bool(i <= i2),
-                        (Value::Long(i), Value::Long(i2)) => Value::Bool(i <= i2),
-                        (Value::Float(f), Value::Float(f2)) => Value::Bool(f <= f2),
-                        (Value::Double(f), Value::Double(f2)) => Value::Bool(f <= f2),
-                        (Value::Int(i), Value::Long(i2)) => Value::Bool((i as i64) <= i2),
-                        (Value::Long(i), Value::Int(i2)) => Value::Bool(i <= (i2 as i64)),
-                        (Value::Float(f), Value::Double(f2)) => Value::Bool((f as f64) <= f2),
-                        (Value::Double(f), Value::Float(f2)) => Value::Bool(f <= (f2 as f64)),
-                        (Value::Long(l), v) => Value::Bool(l <= v.to_long(self)),
-                        (Value::Int(i), v) => Value::Bool(i <= v.to_int(self)),
-                        (Value::Double(d), v) => Value::Bool(d <= v.to_double(self)),
-                        (Value::Float(f), v) => Value::Bool(f <= v.to_float(self)),
-                        (v, Value::Null) => v,
-                        (Value::Null, v) => v,
-                        v => panic!("Unimplemented {:?}",v),
-                    };
-
-                    self.set(*dest, result);
+                    let result = numeric::compare(self, Cmp::Le, v1, v2)?;
+                    self.set(*dest, Value::Bool(result));
                 }
 
                 Instruction::Lt(dest, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-                    let result = match (v1, v2) {
-                        (Value::Int(i), Value::Int(i2)) => Value::Bool(i < i2),
-                        (Value::Long(i), Value::Long(i2)) => Value::Bool(i < i2),
-                        (Value::Float(f), Value::Float(f2)) => Value::Bool(f < f2),
-                        (Value::Double(f), Value::Double(f2)) => Value::Bool(f < f2),
-                        (Value::Int(i), Value::Long(i2)) => Value::Bool((i as i64) < i2),
-                        (Value::Long(i), Value::Int(i2)) => Value::Bool(i < (i2 as i64)),
-                        (Value::Float(f), Value::Double(f2)) => Value::Bool((f as f64) < f2),
-                        (Value::Double(f), Value::Float(f2)) => Value::Bool(f < (f2 as f64)),
-                        (Value::Long(l), v) => Value::Bool(l < v.to_long(self)),
-                        (Value::Int(i), v) => Value::Bool(i < v.to_int(self)),
-                        (Value::Double(d), v) => Value::Bool(d < v.to_double(self)),
-                        (Value::Float(f), v) => Value::Bool(f < v.to_float(self)),
-                        (v, Value::Null) => v,
-                        (Value::Null, v) => v,
-                        (v, v1) => panic!("{:?} < {:?}", v.to_String(self), v1.to_String(self)),
-                    };
-
-                    self.set(*dest, result);
+                    let result = numeric::compare(self, Cmp::Lt, v1, v2)?;
+                    self.set(*dest, Value::Bool(result));
                 }
                 Instruction::BitAnd(r3, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-
-                    let result = match (v1, v2) {
-                        (Value::Long(l), Value::Long(l1)) => Value::Long(l & l1),
-                        (Value::Int(i), Value::Int(i2)) => Value::Int(i & i2),
-                        v => panic!("BitAnd cannot be aplied to {:?}", v),
-                    };
+                    let result = numeric::bits(self, Bits::And, v1, v2)?;
                     self.set(*r3, result);
                 }
                 Instruction::BitOr(r3, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-
-                    let result = match (v1, v2) {
-                        (Value::Long(l), Value::Long(l1)) => Value::Long(l | l1),
-                        (Value::Int(i), Value::Int(i2)) => Value::Int(i | i2),
-                        v => panic!("BitOr cannot be aplied to {:?}", v),
-                    };
+                    let result = numeric::bits(self, Bits::Or, v1, v2)?;
                     self.set(*r3, result);
                 }
                 Instruction::BitXor(r3, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-
-                    let result = match (v1, v2) {
-                        (Value::Long(l), Value::Long(l1)) => Value::Long(l ^ l1),
-                        (Value::Int(i), Value::Int(i2)) => Value::Int(i ^ i2),
-                        v => panic!("BitOr cannot be aplied to {:?}", v),
-                    };
+                    let result = numeric::bits(self, Bits::Xor, v1, v2)?;
                     self.set(*r3, result);
                 }
                 Instruction::Shl(r3, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-
-                    let result = match (v1, v2) {
-                        (Value::Long(l), Value::Long(l1)) => Value::Long(l << l1),
-                        (Value::Int(i), Value::Int(i2)) => Value::Int(i << i2),
-                        v => panic!("BitOr cannot be aplied to {:?}", v),
-                    };
+                    let result = numeric::bits(self, Bits::Shl, v1, v2)?;
                     self.set(*r3, result);
                 }
                 Instruction::Shr(r3, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-
-                    let result = match (v1, v2) {
-                        (Value::Long(l), Value::Long(l1)) => Value::Long(l >> l1),
-                        (Value::Int(i), Value::Int(i2)) => Value::Int(i >> i2),
-                        v => panic!("BitOr cannot be aplied to {:?}", v),
-                    };
-                    self.set(*r3, result);
-                }
-                Instruction::And(r3, r1, r2) => {
-                    let (v1, v2) = (self.get(*r1), self.get(*r2));
-
-                    let result = match (v1, v2) {
-                        (Value::Bool(b), Value::Bool(b2)) => Value::Bool(b && b2),
-                        v => panic!("And cannot be aplied to {:?}", v),
-                    };
-
-                    self.set(*r3, result);
-                }
-                Instruction::Or(r3, r1, r2) => {
-                    let (v1, v2) = (self.get(*r1), self.get(*r2));
-
-                    let result = match (v1, v2) {
-                        (Value::Bool(b), Value::Bool(b2)) => Value::Bool(b || b2),
-                        v => panic!("Or cannot be aplied to {:?}", v),
-                    };
-
+                    let result = numeric::bits(self, Bits::Shr, v1, v2)?;
                     self.set(*r3, result);
                 }
                 Instruction::Eq(r3, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-                    let result = match (v1, v2) {
-                        (Value::Int(i), Value::Int(i2)) => Value::Bool(i == i2),
-                        (Value::Long(i), Value::Long(i2)) => Value::Bool(i == i2),
-                        (Value::Float(f), Value::Float(f2)) => Value::Bool(f == f2),
-                        (Value::Double(f), Value::Double(f2)) => Value::Bool(f == f2),
-                        (Value::Int(i), Value::Long(i2)) => Value::Bool((i as i64) == i2),
-                        (Value::Long(i), Value::Int(i2)) => Value::Bool(i == (i2 as i64)),
-                        (Value::Float(f), Value::Double(f2)) => Value::Bool((f as f64) == f2),
-                        (Value::Double(f), Value::Float(f2)) => Value::Bool(f == (f2 as f64)),
-                        (Value::Long(l), v) => Value::Bool(l == v.to_long(self)),
-                        (Value::Int(i), v) => Value::Bool(i == v.to_int(self)),
-                        (Value::Double(d), v) => Value::Bool(d == v.to_double(self)),
-                        (Value::Float(f), v) => Value::Bool(f == v.to_float(self)),
-                        (_v, Value::Null) => Value::Bool(false),
-                        (Value::Null, _v) => Value::Bool(false),
-                        _ => unimplemented!(),
-                    };
-
-                    self.set(*r3, result);
+                    let result = numeric::compare(self, Cmp::Eq, v1, v2)?;
+                    self.set(*r3, Value::Bool(result));
                 }
 
                 Instruction::Neq(r3, r1, r2) => {
                     let (v1, v2) = (self.get(*r1), self.get(*r2));
-                    let result = match (v1, v2) {
-                        (Value::Int(i), Value::Int(i2)) => Value::Bool(i != i2),
-                        (Value::Long(i), Value::Long(i2)) => Value::Bool(i != i2),
-                        (Value::Float(f), Value::Float(f2)) => Value::Bool(f != f2),
-                        (Value::Double(f), Value::Double(f2)) => Value::Bool(f != f2),
-                        (Value::Int(i), Value::Long(i2)) => Value::Bool((i as i64) != i2),
-                        (Value::Long(i), Value::Int(i2)) => Value::Bool(i == (i2 as i64)),
-                        (Value::Float(f), Value::Double(f2)) => Value::Bool((f as f64) != f2),
-                        (Value::Double(f), Value::Float(f2)) => Value::Bool(f != (f2 as f64)),
-                        (Value::Long(l), v) => Value::Bool(l != v.to_long(self)),
-                        (Value::Int(i), v) => Value::Bool(i != v.to_int(self)),
-                        (Value::Double(d), v) => Value::Bool(d != v.to_double(self)),
-                        (Value::Float(f), v) => Value::Bool(f != v.to_float(self)),
-                        (_v, Value::Null) => Value::Bool(false),
-                        (Value::Null, _v) => Value::Bool(false),
-                        _ => unimplemented!(),
-                    };
-
-                    self.set(*r3, result);
+                    let result = numeric::compare(self, Cmp::Neq, v1, v2)?;
+                    self.set(*r3, Value::Bool(result));
                 }
 
                 Instruction::Goto(lbl_id) => {
-                    if self.labels.contains_key(lbl_id) {
-                        let idx = &self.labels[lbl_id];
-                        self.branch(*idx + 1);
-                    } else {
-                        return Err(VmError::LabelNotFound(*lbl_id));
-                    }
+                    let idx = self.label(*lbl_id)?;
+                    self.branch(idx + 1);
                 }
 
                 Instruction::GotoF(r1, lbl_id) => match self.get(*r1) {
                     Value::Bool(b) => {
                         if !b {
-                            if self.labels.contains_key(lbl_id) {
-                                let idx = &self.labels[lbl_id];
-                                self.branch(*idx + 1);
-                            } else {
-                                return Err(VmError::LabelNotFound(*lbl_id));
-                            }
+                            let idx = self.label(*lbl_id)?;
+                            self.branch(idx + 1);
                         }
                     }
 
@@ -554,18 +763,29 @@ impl Machine
 
                 Instruction::Move(r1, r2) => {
                     let v = self.get(*r2);
-
-                    self.last_frame_mut().stack[*r1] = v;
+                    self.set(*r1, v);
                 }
 
                 Instruction::Ret(idx) => {
-
-                    ret = self.get(*idx);
-                    returns = true;
+                    let value = self.get(*idx);
+                    if let Some(ret) = self.leave(value, depth) {
+                        return Ok(ret);
+                    }
                 }
 
                 Instruction::Ret0 => {
-                    returns = true;
+                    if let Some(ret) = self.leave(Value::Null, depth) {
+                        return Ok(ret);
+                    }
+                }
+
+                Instruction::Yield(r1) => {
+                    if mode != Mode::Generator || self.stack.len() != depth {
+                        return Err(VmError::RuntimeError("yield outside of generator".into()));
+                    }
+                    let value = self.get(*r1);
+                    self.suspended = Some(self.detach(depth));
+                    return Ok(value);
                 }
 
                 Instruction::LoadAt(r1, r2, r3) => {
@@ -576,6 +796,7 @@ impl Machine
                         let obj = self.pool.get(obj_id);
                         let this = self.get(*r2);
                         obj.load_at(self, vec![this, v3], *r1);
+                        self.raised()?;
                     } else {
                         return Err(VmError::Expected("Value::Object".into(),format!("{:?}",v2)));
                     }
@@ -588,6 +809,7 @@ impl Machine
                     if let Value::Object(obj_id) = &target {
                         let obj = self.pool.get(*obj_id);
                         obj.store_at(self, vec![target, key, value], 0);
+                        self.raised()?;
                     } else {
                         return Err(VmError::Expected("Value::Object".into(),format!("{:?}",&target)));
                     }
@@ -595,12 +817,6 @@ impl Machine
 
                 v => panic!("{:?}", v),
             }
-            
         }
-        let end = super::time::PreciseTime::now();
-
-        let _result = start.to(end).num_milliseconds();
-
-        Ok(ret)
     }
 }
@@ -20,10 +20,9 @@ fn main()
     let code = vec![
         Instruction::LoadConst(2, func),
         Instruction::LoadConst(1, string),
-        Instruction::LoadArg(2),
-        Instruction::LoadAt(2, 2, 1),
-        Instruction::LoadArg(2),
-        Instruction::Call(2, 2, 0),
+        Instruction::LoadAt(3, 2, 1),
+        Instruction::Move(4, 2),
+        Instruction::Call(2, 3, 0),
         Instruction::Ret(2),
     ];
 
@@ -32,7 +31,7 @@ fn main()
             .pool
             .allocate(Box::new(Function::from_instructions(code, 0))),
     );
-    let v = machine.invoke(func, vec![]);
+    let v = machine.invoke(func, &[]).unwrap();
     let obj = if let Value::Object(id) = v {
         machine.pool.get(id)
     } else {
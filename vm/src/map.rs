@@ -0,0 +1,311 @@
+//! Associative container
+//!
+//! `m[k]` and `m.name` both look up entries first, methods are found by name only if there is no such key, see
+//! `map_method`. Keys named like a method hide it, `get`, `set` and `m[k]` reach any key.
+
+use crate::{
+    array::{arg, error, Array},
+    bigint::BigInt,
+    error::VmError,
+    function::{Function, Method},
+    iterator::{not_iterable, Iterate},
+    machine::Machine,
+    object::{Object, ObjectAddon},
+    object_pool::ObjectPool,
+    value::Value,
+};
+use std::{any::Any, cell::RefCell, collections::HashMap};
+
+/// Hashable identity of map key
+///
+/// Integers are the same key whatever their width, so are floats equal to them. Strings are compared by
+/// content and other objects by identity.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Key
+{
+    Integer(i64),
+    Big(String),
+    Float(u64),
+    Str(String),
+    Bool(bool),
+    Char(char),
+    Null,
+    Object(usize),
+}
+
+impl Key
+{
+    pub fn new(m: &Machine, value: Value) -> Key
+    {
+        let float = |d: f64| {
+            if d.fract() == 0.0 && d >= i64::MIN as f64 && d < i64::MAX as f64 {
+                Key::Integer(d as i64)
+            } else {
+                Key::Float(d.to_bits())
+            }
+        };
+        match value {
+            Value::Int(i) => Key::Integer(i64::from(i)),
+            Value::Long(l) => Key::Integer(l),
+            Value::Float(f) => float(f64::from(f)),
+            Value::Double(d) => float(d),
+            Value::Bool(b) => Key::Bool(b),
+            Value::Char(c) => Key::Char(c),
+            Value::Null => Key::Null,
+            Value::Object(id) => {
+                if let Some(s) = m.pool.get_direct_typed::<String>(id) {
+                    Key::Str(s.clone())
+                } else if let Some(big) = m.pool.get_direct_typed::<BigInt>(id) {
+                    big.to_i64()
+                        .map_or_else(|| Key::Big(big.0.to_string()), Key::Integer)
+                } else {
+                    Key::Object(id)
+                }
+            }
+        }
+    }
+}
+
+/// Associative container, iterates in insertion order
+#[derive(Clone, Default)]
+pub struct Map
+{
+    entries: RefCell<Vec<(Value, Value)>>,
+    index: RefCell<HashMap<Key, usize>>,
+}
+
+impl Map
+{
+    pub fn new() -> Map
+    {
+        Map::default()
+    }
+
+    pub fn get(&self, m: &Machine, key: Value) -> Option<Value>
+    {
+        let idx = *self.index.borrow().get(&Key::new(m, key))?;
+        Some(self.entries.borrow()[idx].1)
+    }
+
+    /// Replace value of `key` keeping its position or append new entry
+    pub fn set(&self, m: &Machine, key: Value, value: Value)
+    {
+        let mut entries = self.entries.borrow_mut();
+        let len = entries.len();
+        let idx = *self.index.borrow_mut().entry(Key::new(m, key)).or_insert(len);
+        if idx == len {
+            entries.push((key, value));
+        } else {
+            entries[idx].1 = value;
+        }
+    }
+
+    /// Remove `key` and return its value
+    pub fn remove(&self, m: &Machine, key: Value) -> Option<Value>
+    {
+        let mut index = self.index.borrow_mut();
+        let idx = index.remove(&Key::new(m, key))?;
+        for i in index.values_mut() {
+            if *i > idx {
+                *i -= 1;
+            }
+        }
+        Some(self.entries.borrow_mut().remove(idx).1)
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.entries.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+
+    pub fn keys(&self) -> Vec<Value>
+    {
+        self.entries.borrow().iter().map(|e| e.0).collect()
+    }
+
+    pub fn values(&self) -> Vec<Value>
+    {
+        self.entries.borrow().iter().map(|e| e.1).collect()
+    }
+}
+
+/// Keys of map `id` in insertion order, keys added during iteration are visited as well
+struct Keys
+{
+    id: usize,
+    index: usize,
+}
+
+impl Iterate for Keys
+{
+    fn next(&mut self, m: &mut Machine) -> Result<Option<Value>, VmError>
+    {
+        let map = match m.pool.get_direct_typed::<Map>(self.id) {
+            Some(map) => map,
+            None => return Ok(None),
+        };
+        let key = map.entries.borrow().get(self.index).map(|e| e.0);
+        self.index += 1;
+        Ok(key)
+    }
+}
+
+/// `Map` passed as `this`
+fn this_map<'a>(m: &'a Machine, args: &[Value], method: &str) -> Result<&'a Map, VmError>
+{
+    let map = match args[0] {
+        Value::Object(id) => m.pool.get_direct_typed::<Map>(id),
+        _ => None,
+    };
+    map.ok_or_else(|| error(format!("Map::{} expects Map as this", method)))
+}
+
+pub fn map_keys(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let keys = this_map(m, args, "keys")?.keys();
+    Ok(Array::allocate(m, keys))
+}
+
+pub fn map_values(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let values = this_map(m, args, "values")?.values();
+    Ok(Array::allocate(m, values))
+}
+
+pub fn map_has(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let map = this_map(m, args, "has")?;
+    Ok(Value::Bool(map.get(m, arg(args, 0)).is_some()))
+}
+
+/// `m.get(k)`: value of `k` or `null` if there is no such key
+pub fn map_get(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let map = this_map(m, args, "get")?;
+    Ok(map.get(m, arg(args, 0)).unwrap_or(Value::Null))
+}
+
+pub fn map_set(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let map = this_map(m, args, "set")?;
+    map.set(m, arg(args, 0), arg(args, 1));
+    Ok(Value::Null)
+}
+
+/// `m.remove(k)`: value of removed key or `null` if there was no such key
+pub fn map_remove(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let map = this_map(m, args, "remove")?;
+    Ok(map.remove(m, arg(args, 0)).unwrap_or(Value::Null))
+}
+
+pub fn map_size(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let map = this_map(m, args, "size")?;
+    Ok(Value::Int(map.len() as i32))
+}
+
+/// Method `name` of Map
+pub fn map_method(name: &str) -> Option<Method>
+{
+    let method: Method = match name {
+        "keys" => map_keys,
+        "values" => map_values,
+        "has" => map_has,
+        "get" => map_get,
+        "set" => map_set,
+        "remove" => map_remove,
+        "size" => map_size,
+        _ => return None,
+    };
+    Some(method)
+}
+
+impl ObjectAddon for Map
+{
+    fn typename(&self, _: &mut Machine) -> String
+    {
+        String::from("Map")
+    }
+
+    fn to_String(&self, m: &mut Machine) -> String
+    {
+        let entries = self.entries.borrow().clone();
+        let entries: Vec<String> = entries
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k.to_String(m), v.to_String(m)))
+            .collect();
+        format!("{{{}}}", entries.join(", "))
+    }
+}
+
+impl Object for Map
+{
+    fn initialize(&mut self, _: &mut ObjectPool)
+    {
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        self as &dyn Any
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    {
+        self as &mut dyn Any
+    }
+
+    fn get_children(&self) -> Vec<usize>
+    {
+        let mut children = vec![];
+        for (k, v) in self.entries.borrow().iter() {
+            for value in [k, v].iter() {
+                if let Value::Object(id) = value {
+                    children.push(*id);
+                }
+            }
+        }
+        children
+    }
+
+    /// `for k in map` iterates over keys
+    fn iterator(&self, m: &mut Machine, this: Value) -> Result<Box<dyn Iterate>, VmError>
+    {
+        match this {
+            Value::Object(id) => Ok(Box::new(Keys { id, index: 0 })),
+            _ => Err(not_iterable(self.typename(m))),
+        }
+    }
+
+    /// Entry of key, method of the same name if there is no such key and `null` otherwise
+    fn load_at(&self, m: &mut Machine, args: Vec<Value>, rindex: usize)
+    {
+        let key = args[1];
+        if let Some(value) = self.get(m, key) {
+            m.set(rindex, value);
+            return;
+        }
+        let method = match key {
+            Value::Object(id) => m
+                .pool
+                .get_direct_typed::<String>(id)
+                .and_then(|name| map_method(name)),
+            _ => None,
+        };
+        let value = match method {
+            Some(method) => Value::Object(m.pool.allocate(Box::new(Function::from_method(method)))),
+            None => Value::Null,
+        };
+        m.set(rindex, value);
+    }
+
+    fn store_at(&self, m: &mut Machine, args: Vec<Value>, _rindex: usize)
+    {
+        self.set(m, args[1], args[2]);
+    }
+}
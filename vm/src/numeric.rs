@@ -0,0 +1,405 @@
+//! Numeric tower
+//!
+//! Every arithmetic and comparison instruction converts its operands to a common type first:
+//!
+//! | left \ right | Int    | Long   | BigInt | Float  | Double |
+//! |--------------|--------|--------|--------|--------|--------|
+//! | Int          | Int    | Long   | BigInt | Float  | Double |
+//! | Long         | Long   | Long   | BigInt | Double | Double |
+//! | BigInt       | BigInt | BigInt | BigInt | Double | Double |
+//! | Float        | Float  | Double | Double | Float  | Double |
+//! | Double       | Double | Double | Double | Double | Double |
+//!
+//! `BigInt` is an object, see `bigint`. Result of arithmetic has common type of operands, except:
+//!
+//! - integer result that doesn't fit into its type is promoted: `Int` to `Long` and `Long` to `BigInt`
+//! - integer raised to negative power is `Double`
+//! - division, integer division and remainder by zero are errors for every type
+//!
+//! `/` of integers rounds toward zero like `div`, remainder takes sign of dividend.
+//!
+//! Bitwise operators accept integers only and follow the same promotion, shifts keep type of left operand
+//! (promoted if `<<` overflows) and require non-negative shift count.
+//!
+//! `Bool`, `Null` and other objects are not numbers: arithmetic and ordering with them is an error. `==`
+//! compares them by value instead, strings by content and other objects by identity, and they are never
+//! equal to numbers. Strings are ordered lexicographically by characters and `Char`s by code points, `+`
+//! of two strings or string and `Char` is handled by `Add` instruction itself (see `string::concat`).
+
+use crate::{bigint::BigInt, error::VmError, machine::Machine, opcodes::Cmp, value::Value};
+use num_bigint::BigInt as Big;
+use num_traits::{Signed, ToPrimitive, Zero};
+use std::convert::TryFrom;
+
+/// Arithmetic operator of binary instruction
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Arith
+{
+    Add,
+    Sub,
+    Mul,
+    Div,
+    IntDiv,
+    Rem,
+    Pow,
+}
+
+impl Arith
+{
+    /// Operator as written in source, used in error messages
+    pub fn symbol(self) -> &'static str
+    {
+        match self {
+            Arith::Add => "+",
+            Arith::Sub => "-",
+            Arith::Mul => "*",
+            Arith::Div => "/",
+            Arith::IntDiv => "div",
+            Arith::Rem => "%",
+            Arith::Pow => "**",
+        }
+    }
+}
+
+/// Bitwise operator of binary instruction
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Bits
+{
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+}
+
+impl Bits
+{
+    pub fn symbol(self) -> &'static str
+    {
+        match self {
+            Bits::And => "&",
+            Bits::Or => "|",
+            Bits::Xor => "^",
+            Bits::Shl => "<<",
+            Bits::Shr => ">>",
+        }
+    }
+}
+
+/// Operands converted to common type
+#[derive(Clone, Debug, PartialEq)]
+pub enum Numbers
+{
+    Int(i32, i32),
+    Long(i64, i64),
+    Big(Big, Big),
+    Float(f32, f32),
+    Double(f64, f64),
+}
+
+/// Value of integer of any width
+fn big(m: &Machine, v: Value) -> Option<Big>
+{
+    match v {
+        Value::Int(i) => Some(Big::from(i)),
+        Value::Long(l) => Some(Big::from(l)),
+        v => BigInt::get(m, v).map(|b| b.0.clone()),
+    }
+}
+
+fn double(m: &Machine, v: Value) -> Option<f64>
+{
+    match v {
+        Value::Int(i) => Some(f64::from(i)),
+        Value::Long(l) => Some(l as f64),
+        Value::Float(f) => Some(f64::from(f)),
+        Value::Double(d) => Some(d),
+        v => BigInt::get(m, v).map(|b| b.0.to_f64().unwrap_or(f64::NAN)),
+    }
+}
+
+/// Common type of `v1` and `v2`, `None` if one of them is not a number
+pub fn promote(m: &Machine, v1: Value, v2: Value) -> Option<Numbers>
+{
+    Some(match (v1, v2) {
+        (Value::Int(a), Value::Int(b)) => Numbers::Int(a, b),
+        (Value::Int(a), Value::Long(b)) => Numbers::Long(i64::from(a), b),
+        (Value::Long(a), Value::Int(b)) => Numbers::Long(a, i64::from(b)),
+        (Value::Long(a), Value::Long(b)) => Numbers::Long(a, b),
+        (Value::Float(a), Value::Float(b)) => Numbers::Float(a, b),
+        (Value::Int(a), Value::Float(b)) => Numbers::Float(a as f32, b),
+        (Value::Float(a), Value::Int(b)) => Numbers::Float(a, b as f32),
+        (a, b) => match (big(m, a), big(m, b)) {
+            (Some(a), Some(b)) => Numbers::Big(a, b),
+            _ => Numbers::Double(double(m, a)?, double(m, b)?),
+        },
+    })
+}
+
+fn not_numbers(op: &str, v1: Value, v2: Value) -> VmError
+{
+    VmError::RuntimeError(format!("`{}` cannot be applied to {:?} and {:?}", op, v1, v2))
+}
+
+/// Integer `a <op> b`, `None` on overflow
+///
+/// Divisor is not zero and exponent is not negative
+fn integer(op: Arith, a: i64, b: i64) -> Option<i64>
+{
+    match op {
+        Arith::Add => a.checked_add(b),
+        Arith::Sub => a.checked_sub(b),
+        Arith::Mul => a.checked_mul(b),
+        Arith::Div | Arith::IntDiv => a.checked_div(b),
+        // only `MIN % -1` wraps and its remainder is 0 anyway
+        Arith::Rem => Some(a.wrapping_rem(b)),
+        Arith::Pow => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+    }
+}
+
+/// `BigInt` `a <op> b`, same conditions as for `integer`
+fn big_integer(op: Arith, a: Big, b: Big) -> Result<Big, VmError>
+{
+    Ok(match op {
+        Arith::Add => a + b,
+        Arith::Sub => a - b,
+        Arith::Mul => a * b,
+        Arith::Div | Arith::IntDiv => a / b,
+        Arith::Rem => a % b,
+        Arith::Pow => match b.to_u32() {
+            Some(b) => a.pow(b),
+            None => return Err(VmError::RuntimeError(String::from("exponent is too large"))),
+        },
+    })
+}
+
+/// Floating point `a <op> b`, `Float` operands are computed in `f64` and rounded back
+fn float(op: Arith, a: f64, b: f64) -> f64
+{
+    match op {
+        Arith::Add => a + b,
+        Arith::Sub => a - b,
+        Arith::Mul => a * b,
+        Arith::Div => a / b,
+        Arith::IntDiv => (a / b).trunc(),
+        Arith::Rem => a % b,
+        Arith::Pow => a.powf(b),
+    }
+}
+
+/// `Long` if `value` fits into it, `BigInt` otherwise
+fn long_or_big(m: &mut Machine, value: Option<i64>, big: impl FnOnce() -> Result<Big, VmError>) -> Result<Value, VmError>
+{
+    match value {
+        Some(l) => Ok(Value::Long(l)),
+        None => Ok(BigInt::allocate(m, big()?)),
+    }
+}
+
+/// `v1 <op> v2`
+pub fn arith(m: &mut Machine, op: Arith, v1: Value, v2: Value) -> Result<Value, VmError>
+{
+    let numbers = promote(m, v1, v2).ok_or_else(|| not_numbers(op.symbol(), v1, v2))?;
+    let divides = matches!(op, Arith::Div | Arith::IntDiv | Arith::Rem);
+    let zero = match &numbers {
+        Numbers::Int(_, b) => *b == 0,
+        Numbers::Long(_, b) => *b == 0,
+        Numbers::Big(_, b) => b.is_zero(),
+        Numbers::Float(_, b) => *b == 0.0,
+        Numbers::Double(_, b) => *b == 0.0,
+    };
+    if divides && zero {
+        return Err(VmError::RuntimeError(String::from("division by zero")));
+    }
+
+    let pow = op == Arith::Pow;
+    match numbers {
+        Numbers::Int(a, b) if pow && b < 0 => Ok(Value::Double(f64::from(a).powi(b))),
+        Numbers::Long(a, b) if pow && b < 0 => Ok(Value::Double((a as f64).powf(b as f64))),
+        Numbers::Big(a, b) if pow && b.is_negative() => {
+            let (a, b) = (a.to_f64().unwrap_or(f64::NAN), b.to_f64().unwrap_or(f64::NAN));
+            Ok(Value::Double(a.powf(b)))
+        }
+        Numbers::Int(a, b) => {
+            let (a, b) = (i64::from(a), i64::from(b));
+            match integer(op, a, b) {
+                Some(result) => Ok(i32::try_from(result).map_or(Value::Long(result), Value::Int)),
+                None => long_or_big(m, None, || big_integer(op, Big::from(a), Big::from(b))),
+            }
+        }
+        Numbers::Long(a, b) => long_or_big(m, integer(op, a, b), || big_integer(op, Big::from(a), Big::from(b))),
+        Numbers::Big(a, b) => {
+            let result = big_integer(op, a, b)?;
+            Ok(BigInt::allocate(m, result))
+        }
+        Numbers::Float(a, b) => Ok(Value::Float(float(op, f64::from(a), f64::from(b)) as f32)),
+        Numbers::Double(a, b) => Ok(Value::Double(float(op, a, b))),
+    }
+}
+
+/// Shift count of `v`
+fn shift_count(m: &Machine, op: Bits, v1: Value, v: Value) -> Result<u32, VmError>
+{
+    match big(m, v) {
+        Some(count) if count.is_negative() => {
+            Err(VmError::RuntimeError(format!("negative shift count in `{}`", op.symbol())))
+        }
+        Some(count) => count
+            .to_u32()
+            .ok_or_else(|| VmError::RuntimeError(String::from("shift count is too large"))),
+        None => Err(not_numbers(op.symbol(), v1, v)),
+    }
+}
+
+/// `a << n` without loss of bits
+fn shl(a: i64, n: u32) -> Option<i64>
+{
+    let result = a.checked_shl(n)?;
+    if result >> n == a {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+/// `v1 <op> v2` for bitwise operators
+pub fn bits(m: &mut Machine, op: Bits, v1: Value, v2: Value) -> Result<Value, VmError>
+{
+    if op == Bits::Shl || op == Bits::Shr {
+        let n = shift_count(m, op, v1, v2)?;
+        return match (op, v1) {
+            (Bits::Shl, Value::Int(a)) => match shl(i64::from(a), n) {
+                Some(result) => Ok(i32::try_from(result).map_or(Value::Long(result), Value::Int)),
+                None => Ok(BigInt::allocate(m, Big::from(a) << n as usize)),
+            },
+            (Bits::Shl, Value::Long(a)) => long_or_big(m, shl(a, n), || Ok(Big::from(a) << n as usize)),
+            (Bits::Shr, Value::Int(a)) => Ok(Value::Int(a >> n.min(31))),
+            (Bits::Shr, Value::Long(a)) => Ok(Value::Long(a >> n.min(63))),
+            (_, v) => match BigInt::get(m, v) {
+                Some(a) if op == Bits::Shl => {
+                    let result = &a.0 << n as usize;
+                    Ok(BigInt::allocate(m, result))
+                }
+                Some(a) => {
+                    let result = &a.0 >> n as usize;
+                    Ok(BigInt::allocate(m, result))
+                }
+                None => Err(not_numbers(op.symbol(), v1, v2)),
+            },
+        };
+    }
+
+    let error = || not_numbers(op.symbol(), v1, v2);
+    match promote(m, v1, v2).ok_or_else(error)? {
+        Numbers::Int(a, b) => Ok(Value::Int(match op {
+            Bits::And => a & b,
+            Bits::Or => a | b,
+            _ => a ^ b,
+        })),
+        Numbers::Long(a, b) => Ok(Value::Long(match op {
+            Bits::And => a & b,
+            Bits::Or => a | b,
+            _ => a ^ b,
+        })),
+        Numbers::Big(a, b) => {
+            let result = match op {
+                Bits::And => a & b,
+                Bits::Or => a | b,
+                _ => a ^ b,
+            };
+            Ok(BigInt::allocate(m, result))
+        }
+        Numbers::Float(_, _) | Numbers::Double(_, _) => Err(error()),
+    }
+}
+
+/// `~v`
+pub fn bit_not(m: &mut Machine, v: Value) -> Result<Value, VmError>
+{
+    match v {
+        Value::Int(i) => Ok(Value::Int(!i)),
+        Value::Long(l) => Ok(Value::Long(!l)),
+        v => match BigInt::get(m, v) {
+            Some(b) => {
+                let result = !&b.0;
+                Ok(BigInt::allocate(m, result))
+            }
+            None => Err(VmError::RuntimeError(format!("`~` cannot be applied to {:?}", v))),
+        },
+    }
+}
+
+/// `-v`
+pub fn neg(m: &mut Machine, v: Value) -> Result<Value, VmError>
+{
+    match v {
+        Value::Int(i) => Ok(i.checked_neg().map_or(Value::Long(-i64::from(i)), Value::Int)),
+        Value::Long(l) => long_or_big(m, l.checked_neg(), || Ok(-Big::from(l))),
+        Value::Float(f) => Ok(Value::Float(-f)),
+        Value::Double(d) => Ok(Value::Double(-d)),
+        v => match BigInt::get(m, v) {
+            Some(b) => {
+                let result = -&b.0;
+                Ok(BigInt::allocate(m, result))
+            }
+            None => Err(VmError::RuntimeError(format!("`-` cannot be applied to {:?}", v))),
+        },
+    }
+}
+
+/// String stored in `v`
+fn string(m: &Machine, v: Value) -> Option<&String>
+{
+    match v {
+        Value::Object(id) => m.pool.get_direct_typed::<String>(id),
+        _ => None,
+    }
+}
+
+/// `v1 == v2` for values which are neither both numbers nor both strings
+fn same(v1: Value, v2: Value) -> bool
+{
+    match (v1, v2) {
+        (Value::Bool(a), Value::Bool(b)) => a == b,
+        (Value::Char(a), Value::Char(b)) => a == b,
+        (Value::Null, Value::Null) => true,
+        (Value::Object(a), Value::Object(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// `v1 <cmp> v2`
+pub fn compare(m: &Machine, cmp: Cmp, v1: Value, v2: Value) -> Result<bool, VmError>
+{
+    let ordering = match promote(m, v1, v2) {
+        Some(Numbers::Int(a, b)) => i64::from(a).partial_cmp(&i64::from(b)),
+        Some(Numbers::Long(a, b)) => a.partial_cmp(&b),
+        Some(Numbers::Big(a, b)) => a.partial_cmp(&b),
+        Some(Numbers::Float(a, b)) => a.partial_cmp(&b),
+        Some(Numbers::Double(a, b)) => a.partial_cmp(&b),
+        None => match (v1, v2, string(m, v1), string(m, v2)) {
+            (Value::Char(a), Value::Char(b), _, _) => a.partial_cmp(&b),
+            (_, _, Some(a), Some(b)) => a.partial_cmp(b),
+            _ => match cmp {
+                Cmp::Eq => return Ok(same(v1, v2)),
+                Cmp::Neq => return Ok(!same(v1, v2)),
+                Cmp::Gt => return Err(not_numbers(">", v1, v2)),
+                Cmp::Lt => return Err(not_numbers("<", v1, v2)),
+                Cmp::Ge => return Err(not_numbers(">=", v1, v2)),
+                Cmp::Le => return Err(not_numbers("<=", v1, v2)),
+            },
+        },
+    };
+    // NaN is unordered, only `!=` holds for it
+    Ok(match ordering {
+        Some(ordering) => match cmp {
+            Cmp::Gt => ordering.is_gt(),
+            Cmp::Lt => ordering.is_lt(),
+            Cmp::Ge => ordering.is_ge(),
+            Cmp::Le => ordering.is_le(),
+            Cmp::Eq => ordering.is_eq(),
+            Cmp::Neq => ordering.is_ne(),
+        },
+        None => cmp == Cmp::Neq,
+    })
+}
@@ -1,4 +1,10 @@
-use crate::{machine::Machine, object_pool::ObjectPool, value::Value};
+use crate::{
+    error::VmError,
+    iterator::{not_iterable, Iterate},
+    machine::Machine,
+    object_pool::ObjectPool,
+    value::Value,
+};
 use std::any::Any;
 pub trait Object: Send + ObjectAddon
 {
@@ -7,20 +13,30 @@ pub trait Object: Send + ObjectAddon
     fn initialize(&mut self, _: &mut ObjectPool)
     {
     }
-    fn call(&self, _m: &mut Machine, _args: Vec<Value>) -> Value
+    /// Call object, `args[0]` is `this` value
+    fn call(&self, _m: &mut Machine, _args: &[Value]) -> Value
     {
         Value::Null
     }
 
-    fn store_at(&self, _m: &mut Machine, _args: Vec<Value>, _rindex: usize)
+    /// `this[key] = value` and `this.key = value`, raises error by default
+    fn store_at(&self, m: &mut Machine, _args: Vec<Value>, _rindex: usize)
     {
-        println!("{:?}", _args);
-        panic!("Cannot store_at, {}", self.to_String(_m));
+        let message = format!("{} doesn't support assignment to fields or elements", self.typename(m));
+        m.raise(VmError::RuntimeError(message));
     }
 
-    fn load_at(&self, _m: &mut Machine, _args: Vec<Value>, _rindex: usize)
+    /// `this[key]` and `this.key`, raises error by default
+    fn load_at(&self, m: &mut Machine, _args: Vec<Value>, _rindex: usize)
     {
-        panic!("Cannot load_at. {:?}", self.to_String(_m));
+        let message = format!("{} doesn't have fields or elements", self.typename(m));
+        m.raise(VmError::RuntimeError(message));
+    }
+
+    /// Start iteration over object, `this` is value of the object
+    fn iterator(&self, m: &mut Machine, _this: Value) -> Result<Box<dyn Iterate>, VmError>
+    {
+        Err(not_iterable(self.typename(m)))
     }
 
     fn as_any(&self) -> &dyn Any;
@@ -131,10 +131,7 @@ where
 {
     pub fn downcast_from(other: ObjectHandle<'a>) -> Option<TypedObjectHandle<'a, T>>
     {
-        let value = match other.object.as_any().downcast_ref::<T>() {
-            Some(v) => v,
-            None => return None,
-        };
+        let value = other.object.as_any().downcast_ref::<T>()?;
         Some(TypedObjectHandle {
             _handle: other,
             value,
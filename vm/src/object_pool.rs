@@ -53,6 +53,12 @@ impl ObjectPool
         pool.push(id);
     }
 
+    /// Whether object at `id` is allocated
+    pub fn contains(&self, id: usize) -> bool
+    {
+        matches!(self.objects.get(id), Some(Some(_)))
+    }
+
     /// Gets a handle to the object at `id`.
     ///
     /// The handle can be passed around safely and
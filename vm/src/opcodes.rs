@@ -1,6 +1,35 @@
 use colored;
 
 use self::colored::Colorize;
+use std::fmt;
+
+/// Comparison performed by fused compare-and-jump instructions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cmp
+{
+    Gt,
+    Lt,
+    Ge,
+    Le,
+    Eq,
+    Neq,
+}
+
+impl fmt::Display for Cmp
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let s = match self {
+            Cmp::Gt => "Gt",
+            Cmp::Lt => "Lt",
+            Cmp::Ge => "Ge",
+            Cmp::Le => "Le",
+            Cmp::Eq => "Eq",
+            Cmp::Neq => "Neq",
+        };
+        write!(f, "{}", s)
+    }
+}
 
 #[derive(Clone)]
 pub enum Instruction
@@ -28,6 +57,10 @@ pub enum Instruction
     ///
     /// Loading double value B to register A
     LoadDouble(usize, f64),
+    /// LoadChar R(A) = B
+    ///
+    /// Loading character B to register A
+    LoadChar(usize, char),
 
     /// LoadConst R(A) = C(B)
     ///
@@ -69,23 +102,52 @@ pub enum Instruction
     Goto(usize),
     GotoF(usize, usize),
 
-    /// Push value from R(A) to arguments stack
-    LoadArg(usize),
-    /// R(A) = B(Args), C - Arg count, args poped from arg stack
+    /// JumpCmpF (R(B) <A> R(C) == false ? ip = D : continue)
+    JumpCmpF(Cmp, usize, usize, usize),
+    /// JumpCmpIF (R(B) <A> C == false ? ip = D : continue)
+    ///
+    /// Same as JumpCmpF, but compares with Long immediate
+    JumpCmpIF(Cmp, usize, i64, usize),
+
+    /// Call R(A) = R(B)(R(B + 2)..R(B + C + 1))
+    ///
+    /// R(B + 1) holds `this` value and C is arguments count. Callee frame starts at R(B + 1),
+    /// so everything above R(B) may be overwritten by call
     Call(usize, usize, usize),
+    /// TailCall return R(A)(R(A + 2)..R(A + B + 1))
+    ///
+    /// Same window layout as `Call`, but callee reuses current frame and returns straight to its caller
+    TailCall(usize, usize),
 
     Isa(usize,usize,usize),
 
     Not(usize, usize),
     ///Add R(A) = R(B) + R(C)
     Add(usize, usize, usize),
+    ///AddI R(A) = R(B) + C
+    AddI(usize, usize, i64),
+    ///Inc R(A) = R(A) + 1
+    Inc(usize),
     ///Sub R(A) = R(B) - R(C)
     Sub(usize, usize, usize),
     ///Mul R(A) = R(B) * R(C)
     Mul(usize, usize, usize),
     ///Div R(A) = R(B) / R(C)
     Div(usize, usize, usize),
+    ///Rem R(A) = R(B) % R(C)
+    ///
+    /// Sign of remainder follows R(B), so `Rem` pairs with `IntDiv`
     Rem(usize, usize, usize),
+    ///IntDiv R(A) = R(B) div R(C)
+    ///
+    /// Quotient rounded toward zero, floats stay floats
+    IntDiv(usize, usize, usize),
+    ///Pow R(A) = R(B) ** R(C)
+    Pow(usize, usize, usize),
+    ///Neg R(A) = -R(B)
+    Neg(usize, usize),
+    ///BitNot R(A) = ~R(B)
+    BitNot(usize, usize),
     ///Gt R(A) = R(B) > R(C)
     Gt(usize, usize, usize),
     ///Lt R(A) = R(B) < R(C)
@@ -106,6 +168,10 @@ pub enum Instruction
     ///
     /// return value from R(A)
     Ret(usize),
+    /// Yield R(A)
+    ///
+    /// suspend generator and pass value from R(A) to its caller, see `generator`
+    Yield(usize),
 
     /// Create label with id A
     Label(usize),
@@ -115,11 +181,8 @@ pub enum Instruction
     BitOr(usize, usize, usize),
     BitXor(usize, usize, usize),
     BitAnd(usize, usize, usize),
-    And(usize, usize, usize),
-    Or(usize, usize, usize),
 }
 
-use std::fmt;
 impl fmt::Display for Instruction
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
@@ -135,6 +198,10 @@ impl fmt::Display for Instruction
             Div(r3, r1, r2) => write!(f, "Div {} {} {}", r3, r1, r2),
             Mul(r3, r1, r2) => write!(f, "Mul {} {} {}", r3, r1, r2),
             Rem(r3, r1, r2) => write!(f, "Rem {} {} {}", r3, r1, r2),
+            IntDiv(r3, r1, r2) => write!(f, "IntDiv {} {} {}", r3, r1, r2),
+            Pow(r3, r1, r2) => write!(f, "Pow {} {} {}", r3, r1, r2),
+            Neg(r1, r2) => write!(f, "Neg {} {}", r1, r2),
+            BitNot(r1, r2) => write!(f, "BitNot {} {}", r1, r2),
             Gt(r3, r1, r2) => write!(f, "Gt {} {} {} ", r3, r1, r2),
             Lt(r3, r1, r2) => write!(f, "Lt {} {} {}", r3, r1, r2),
             Le(r3, r1, r2) => write!(f, "Le {} {} {}", r3, r1, r2),
@@ -142,8 +209,13 @@ impl fmt::Display for Instruction
             Eq(r3, r1, r2) => write!(f, "Eq {} {} {}", r3, r1, r2),
             Ret0 => write!(f, "Ret0"),
             Ret(r1) => write!(f, "Ret {}", r1),
+            Yield(r1) => write!(f, "Yield {}", r1),
             Goto(label_id) => write!(f, "Goto {}", label_id),
             GotoF(r1, label_id) => write!(f, "GotoF {} {}", r1, label_id),
+            JumpCmpF(cmp, r1, r2, ip) => write!(f, "JumpCmpF {} {} {} {}", cmp, r1, r2, ip),
+            JumpCmpIF(cmp, r1, imm, ip) => write!(f, "JumpCmpIF {} {} {} {}", cmp, r1, imm, ip),
+            AddI(r3, r1, imm) => write!(f, "AddI {} {} {}", r3, r1, imm),
+            Inc(r1) => write!(f, "Inc {}", r1),
             Jump(ip) => write!(f, "Jump {}", ip),
             JumpF(r1, ip) => write!(f, "JumpF {} {}", r1, ip),
             LoadConst(r1, object_id) => write!(f, "LoadConst {} {}", r1, object_id),
@@ -152,6 +224,7 @@ impl fmt::Display for Instruction
             LoadLong(r1, long) => write!(f, "LoadLong {} {}", r1, long),
             LoadFloat(r1, float) => write!(f, "LoadFloat {} {}", r1, float),
             LoadDouble(r1, double) => write!(f, "LoadDouble {} {}", r1, double),
+            LoadChar(r1, c) => write!(f, "LoadChar {} {:?}", r1, c),
             LoadBool(r1, bool) => write!(f, "LoadBool {} {}", r1, bool),
             LoadString(r1, str) => write!(f, "LoadString {} \"{}\"", r1, str),
             StoreGlobal(r1, global) => write!(f, "StoreGlobal {} {}", r1, global),
@@ -161,13 +234,11 @@ impl fmt::Display for Instruction
             BitAnd(r3, r1, r2) => write!(f, "BitAnd {} {} {}", r3, r1, r2),
             BitOr(r3, r1, r2) => write!(f, "BitOr {} {} {}", r3, r1, r2),
             BitXor(r3, r1, r2) => write!(f, "BitXor {} {} {}", r3, r1, r2),
-            Or(r3, r1, r2) => write!(f, "Or {} {} {}", r3, r1, r2),
-            And(r3, r1, r2) => write!(f, "And {} {} {}", r3, r1, r2),
             Shr(r3, r1, r2) => write!(f, "Shr {} {} {}", r3, r1, r2),
             Shl(r3, r1, r2) => write!(f, "Shl {} {} {}", r3, r1, r2),
             Label(id) => write!(f, "Label {}", id),
             Call(r3, r2, r1) => write!(f, "Call {} {} {}", r3, r2, r1),
-            LoadArg(r1) => write!(f, "LoadArg {}", r1),
+            TailCall(r2, r1) => write!(f, "TailCall {} {}", r2, r1),
             Move(r1, r2) => write!(f, "Move {} {}", r1, r2),
             LoadSuper(r3, r2, r1) => write!(f, "LoadSuper {} {} {}", r3, r2, r1),
         }
@@ -189,6 +260,10 @@ impl fmt::Debug for Instruction
             Div(r3, r1, r2) => write!(f, "Div {} {} {}", r3, r1, r2),
             Mul(r3, r1, r2) => write!(f, "Mul {} {} {}", r3, r1, r2),
             Rem(r3, r1, r2) => write!(f, "Rem {} {} {}", r3, r1, r2),
+            IntDiv(r3, r1, r2) => write!(f, "IntDiv {} {} {}", r3, r1, r2),
+            Pow(r3, r1, r2) => write!(f, "Pow {} {} {}", r3, r1, r2),
+            Neg(r1, r2) => write!(f, "Neg {} {}", r1, r2),
+            BitNot(r1, r2) => write!(f, "BitNot {} {}", r1, r2),
             Gt(r3, r1, r2) => write!(f, "Gt {} {} {} ", r3, r1, r2),
             Lt(r3, r1, r2) => write!(f, "Lt {} {} {}", r3, r1, r2),
             Le(r3, r1, r2) => write!(f, "Le {} {} {}", r3, r1, r2),
@@ -196,8 +271,13 @@ impl fmt::Debug for Instruction
             Eq(r3, r1, r2) => write!(f, "Eq {} {} {}", r3, r1, r2),
             Ret0 => write!(f, "Ret0"),
             Ret(r1) => write!(f, "Ret {}", r1),
+            Yield(r1) => write!(f, "Yield {}", r1),
             Goto(label_id) => write!(f, "Goto {}", label_id),
             GotoF(r1, label_id) => write!(f, "GotoF {} {}", r1, label_id),
+            JumpCmpF(cmp, r1, r2, ip) => write!(f, "JumpCmpF {} {} {} {}", cmp, r1, r2, ip),
+            JumpCmpIF(cmp, r1, imm, ip) => write!(f, "JumpCmpIF {} {} {} {}", cmp, r1, imm, ip),
+            AddI(r3, r1, imm) => write!(f, "AddI {} {} {}", r3, r1, imm),
+            Inc(r1) => write!(f, "Inc {}", r1),
             Jump(ip) => write!(f, "Jump {}", ip),
             JumpF(r1, ip) => write!(f, "JumpF {} {}", r1, ip),
             LoadConst(r1, object_id) => write!(f, "LoadConst {} {}", r1, object_id),
@@ -206,6 +286,7 @@ impl fmt::Debug for Instruction
             LoadLong(r1, long) => write!(f, "LoadLong {} {}", r1, long),
             LoadFloat(r1, float) => write!(f, "LoadFloat {} {}", r1, float),
             LoadDouble(r1, double) => write!(f, "LoadDouble {} {}", r1, double),
+            LoadChar(r1, c) => write!(f, "LoadChar {} {:?}", r1, c),
             LoadBool(r1, bool) => write!(f, "LoadBool {} {}", r1, bool),
             LoadString(r1, str) => write!(f, "LoadString {} \"{}\"", r1, str),
             StoreGlobal(r1, global) => write!(f, "StoreGlobal {} {}", r1, global),
@@ -215,13 +296,11 @@ impl fmt::Debug for Instruction
             BitAnd(r3, r1, r2) => write!(f, "BitAnd {} {} {}", r3, r1, r2),
             BitOr(r3, r1, r2) => write!(f, "BitOr {} {} {}", r3, r1, r2),
             BitXor(r3, r1, r2) => write!(f, "BitXor {} {} {}", r3, r1, r2),
-            Or(r3, r1, r2) => write!(f, "Or {} {} {}", r3, r1, r2),
-            And(r3, r1, r2) => write!(f, "And {} {} {}", r3, r1, r2),
             Shr(r3, r1, r2) => write!(f, "Shr {} {} {}", r3, r1, r2),
             Shl(r3, r1, r2) => write!(f, "Shl {} {} {}", r3, r1, r2),
             Label(id) => write!(f, "Label {}", id),
             Call(r3, r2, r1) => write!(f, "Call {} {} {}", r3, r2, r1),
-            LoadArg(r1) => write!(f, "LoadArg {}", r1),
+            TailCall(r2, r1) => write!(f, "TailCall {} {}", r2, r1),
             Move(r1, r2) => write!(f, "Move {} {}", r1, r2),
             LoadSuper(r3, r2, r1) => write!(f, "LoadSuper {} {} {}", r3, r2, r1),
         }
@@ -229,7 +308,6 @@ impl fmt::Debug for Instruction
 }
 
 ///Trait used for print Vec\<Instruction\>
-
 pub trait DebugCode
 {
     #[allow(non_snake_case)]
@@ -241,13 +319,224 @@ impl DebugCode for Vec<Instruction>
     fn toString(&self) -> String
     {
         let mut str = String::new();
-        for i in 0..self.len() {
-            str.push_str(&format!("{:04} {}", i, format!("{}", self[i]).white()));
+        for (i, ins) in self.iter().enumerate() {
+            str.push_str(&format!("{:04} {}", i, format!("{}", ins).white()));
             str.push('\n');
         }
         str
     }
 }
+
+impl Instruction
+{
+    /// Registers accessed by instruction, `Call` accesses whole window of callee
+    pub fn registers(&self) -> Vec<usize>
+    {
+        use self::Instruction::*;
+
+        match *self {
+            LoadString(r, _)
+            | LoadBool(r, _)
+            | LoadInt(r, _)
+            | LoadLong(r, _)
+            | LoadFloat(r, _)
+            | LoadDouble(r, _)
+            | LoadChar(r, _)
+            | LoadConst(r, _)
+            | LoadGlobal(r, _)
+            | StoreGlobal(r, _)
+            | JumpF(r, _)
+            | GotoF(r, _)
+            | JumpCmpIF(_, r, _, _)
+            | Inc(r)
+            | Ret(r)
+            | Yield(r) => vec![r],
+            Move(r1, r2) | Not(r1, r2) | Neg(r1, r2) | BitNot(r1, r2) | JumpCmpF(_, r1, r2, _) | AddI(r1, r2, _) => {
+                vec![r1, r2]
+            }
+            Call(dest, r, argc) => {
+                let mut regs = vec![dest];
+                regs.extend(r..r + argc + 2);
+                regs
+            }
+            TailCall(r, argc) => (r..r + argc + 2).collect(),
+            LoadAt(r1, r2, r3)
+            | LoadSuper(r1, r2, r3)
+            | Store(r1, r2, r3)
+            | StoreAt(r1, r2, r3)
+            | Isa(r1, r2, r3)
+            | Add(r1, r2, r3)
+            | Sub(r1, r2, r3)
+            | Mul(r1, r2, r3)
+            | Div(r1, r2, r3)
+            | Rem(r1, r2, r3)
+            | IntDiv(r1, r2, r3)
+            | Pow(r1, r2, r3)
+            | Gt(r1, r2, r3)
+            | Lt(r1, r2, r3)
+            | Ge(r1, r2, r3)
+            | Le(r1, r2, r3)
+            | Eq(r1, r2, r3)
+            | Neq(r1, r2, r3)
+            | Shr(r1, r2, r3)
+            | Shl(r1, r2, r3)
+            | BitOr(r1, r2, r3)
+            | BitXor(r1, r2, r3)
+            | BitAnd(r1, r2, r3) => vec![r1, r2, r3],
+            Jump(_) | Goto(_) | Label(_) | Ret0 => vec![],
+        }
+    }
+
+    /// Registers read by instruction
+    pub fn reads(&self) -> Vec<usize>
+    {
+        use self::Instruction::*;
+
+        match *self {
+            StoreGlobal(r, _) | JumpF(r, _) | GotoF(r, _) | JumpCmpIF(_, r, _, _) | Ret(r) | Yield(r) => vec![r],
+            Move(_, r) | Not(_, r) | Neg(_, r) | BitNot(_, r) | AddI(_, r, _) | Inc(r) => vec![r],
+            Call(_, r, argc) | TailCall(r, argc) => (r..r + argc + 2).collect(),
+            JumpCmpF(_, r1, r2, _) => vec![r1, r2],
+            StoreAt(r1, r2, r3) | Store(r1, r2, r3) => vec![r1, r2, r3],
+            LoadAt(_, r1, r2)
+            | LoadSuper(_, r1, r2)
+            | Isa(_, r1, r2)
+            | Add(_, r1, r2)
+            | Sub(_, r1, r2)
+            | Mul(_, r1, r2)
+            | Div(_, r1, r2)
+            | Rem(_, r1, r2)
+            | IntDiv(_, r1, r2)
+            | Pow(_, r1, r2)
+            | Gt(_, r1, r2)
+            | Lt(_, r1, r2)
+            | Ge(_, r1, r2)
+            | Le(_, r1, r2)
+            | Eq(_, r1, r2)
+            | Neq(_, r1, r2)
+            | Shr(_, r1, r2)
+            | Shl(_, r1, r2)
+            | BitOr(_, r1, r2)
+            | BitXor(_, r1, r2)
+            | BitAnd(_, r1, r2) => vec![r1, r2],
+            _ => vec![],
+        }
+    }
+
+    /// Register written by instruction
+    ///
+    /// `Call(A, B, C)` also overwrites every register above R(B) because callee frame is placed there
+    pub fn writes(&self) -> Option<usize>
+    {
+        use self::Instruction::*;
+
+        match *self {
+            StoreGlobal(_, _)
+            | StoreAt(_, _, _)
+            | Store(_, _, _)
+            | Jump(_)
+            | JumpF(_, _)
+            | Goto(_)
+            | GotoF(_, _)
+            | JumpCmpF(_, _, _, _)
+            | JumpCmpIF(_, _, _, _)
+            | Label(_)
+            | Ret(_)
+            | Ret0
+            | Yield(_)
+            | TailCall(_, _) => None,
+            _ => self.registers().first().cloned(),
+        }
+    }
+
+    /// Rename every register operand with `f`
+    ///
+    /// Window of `Call` and `TailCall` is renamed by its first register, the rest of window follows it
+    pub fn rename_registers(&mut self, f: impl Fn(usize) -> usize)
+    {
+        use self::Instruction::*;
+
+        match self {
+            LoadString(r, _)
+            | LoadBool(r, _)
+            | LoadInt(r, _)
+            | LoadLong(r, _)
+            | LoadFloat(r, _)
+            | LoadDouble(r, _)
+            | LoadChar(r, _)
+            | LoadConst(r, _)
+            | LoadGlobal(r, _)
+            | StoreGlobal(r, _)
+            | JumpF(r, _)
+            | GotoF(r, _)
+            | JumpCmpIF(_, r, _, _)
+            | Inc(r)
+            | Ret(r)
+            | Yield(r)
+            | TailCall(r, _) => *r = f(*r),
+            Move(r1, r2)
+            | Not(r1, r2)
+            | Neg(r1, r2)
+            | BitNot(r1, r2)
+            | JumpCmpF(_, r1, r2, _)
+            | AddI(r1, r2, _)
+            | Call(r1, r2, _) => {
+                *r1 = f(*r1);
+                *r2 = f(*r2);
+            }
+            LoadAt(r1, r2, r3)
+            | LoadSuper(r1, r2, r3)
+            | Store(r1, r2, r3)
+            | StoreAt(r1, r2, r3)
+            | Isa(r1, r2, r3)
+            | Add(r1, r2, r3)
+            | Sub(r1, r2, r3)
+            | Mul(r1, r2, r3)
+            | Div(r1, r2, r3)
+            | Rem(r1, r2, r3)
+            | IntDiv(r1, r2, r3)
+            | Pow(r1, r2, r3)
+            | Gt(r1, r2, r3)
+            | Lt(r1, r2, r3)
+            | Ge(r1, r2, r3)
+            | Le(r1, r2, r3)
+            | Eq(r1, r2, r3)
+            | Neq(r1, r2, r3)
+            | Shr(r1, r2, r3)
+            | Shl(r1, r2, r3)
+            | BitOr(r1, r2, r3)
+            | BitXor(r1, r2, r3)
+            | BitAnd(r1, r2, r3) => {
+                *r1 = f(*r1);
+                *r2 = f(*r2);
+                *r3 = f(*r3);
+            }
+            Jump(_) | Goto(_) | Label(_) | Ret0 => {}
+        }
+    }
+
+    /// Jump target if instruction is a jump by instruction index
+    pub fn jump_target(&self) -> Option<usize>
+    {
+        use self::Instruction::*;
+
+        match *self {
+            Jump(t) | JumpF(_, t) | JumpCmpF(_, _, _, t) | JumpCmpIF(_, _, _, t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Change jump target of instruction returned by `jump_target`
+    pub fn set_jump_target(&mut self, target: usize)
+    {
+        use self::Instruction::*;
+
+        match self {
+            Jump(t) | JumpF(_, t) | JumpCmpF(_, _, _, t) | JumpCmpIF(_, _, _, t) => *t = target,
+            _ => {}
+        }
+    }
+}
 /// Stores instructions
 #[derive(Clone, Debug)]
 pub struct CodeBlock
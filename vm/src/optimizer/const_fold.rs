@@ -0,0 +1,146 @@
+use super::{leaders, Pass};
+use crate::opcodes::{Cmp, Instruction};
+use std::{collections::HashMap, convert::TryFrom};
+
+/// Constant folding and propagation inside basic blocks
+///
+/// Only `Long` and `Bool` constants are tracked, arithmetic that overflows is left for runtime
+pub struct ConstFold;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Const
+{
+    Long(i64),
+    Bool(bool),
+}
+
+impl Const
+{
+    fn load(self, dest: usize) -> Instruction
+    {
+        match self {
+            Const::Long(l) => Instruction::LoadLong(dest, l),
+            Const::Bool(b) => Instruction::LoadBool(dest, b),
+        }
+    }
+}
+
+fn compare(cmp: Cmp, a: i64, b: i64) -> bool
+{
+    match cmp {
+        Cmp::Gt => a > b,
+        Cmp::Lt => a < b,
+        Cmp::Ge => a >= b,
+        Cmp::Le => a <= b,
+        Cmp::Eq => a == b,
+        Cmp::Neq => a != b,
+    }
+}
+
+/// Comparison with swapped operands: `a <cmp> b` == `b <swap(cmp)> a`
+fn swap(cmp: Cmp) -> Cmp
+{
+    match cmp {
+        Cmp::Gt => Cmp::Lt,
+        Cmp::Lt => Cmp::Gt,
+        Cmp::Ge => Cmp::Le,
+        Cmp::Le => Cmp::Ge,
+        cmp => cmp,
+    }
+}
+
+/// Folded form of `ins` if its operands are known
+fn fold(ins: &Instruction, i: usize, known: &HashMap<usize, Const>) -> Option<Instruction>
+{
+    use self::Instruction::*;
+
+    let long = |r: usize| match known.get(&r) {
+        Some(Const::Long(l)) => Some(*l),
+        _ => None,
+    };
+    let boolean = |r: usize| match known.get(&r) {
+        Some(Const::Bool(b)) => Some(*b),
+        _ => None,
+    };
+    // conditional jump that is never taken becomes jump to the next instruction
+    let branch = |taken: bool, t: usize| if taken { Jump(t) } else { Jump(i + 1) };
+
+    Some(match *ins {
+        Move(d, s) => known.get(&s)?.load(d),
+        Add(d, a, b) => LoadLong(d, long(a)?.checked_add(long(b)?)?),
+        Sub(d, a, b) => LoadLong(d, long(a)?.checked_sub(long(b)?)?),
+        Mul(d, a, b) => LoadLong(d, long(a)?.checked_mul(long(b)?)?),
+        Div(d, a, b) => LoadLong(d, long(a)?.checked_div(long(b)?)?),
+        IntDiv(d, a, b) => LoadLong(d, long(a)?.checked_div(long(b)?)?),
+        Rem(d, a, b) => LoadLong(d, long(a)?.checked_rem(long(b)?)?),
+        Pow(d, a, b) => LoadLong(d, long(a)?.checked_pow(u32::try_from(long(b)?).ok()?)?),
+        Neg(d, s) => LoadLong(d, long(s)?.checked_neg()?),
+        BitNot(d, s) => LoadLong(d, !long(s)?),
+        AddI(d, a, imm) => LoadLong(d, long(a)?.checked_add(imm)?),
+        Inc(r) => LoadLong(r, long(r)?.checked_add(1)?),
+        Gt(d, a, b) => LoadBool(d, long(a)? > long(b)?),
+        Lt(d, a, b) => LoadBool(d, long(a)? < long(b)?),
+        Ge(d, a, b) => LoadBool(d, long(a)? >= long(b)?),
+        Le(d, a, b) => LoadBool(d, long(a)? <= long(b)?),
+        Eq(d, a, b) => LoadBool(d, long(a)? == long(b)?),
+        Neq(d, a, b) => LoadBool(d, long(a)? != long(b)?),
+        Not(d, s) => match known.get(&s)? {
+            Const::Bool(b) => LoadBool(d, !b),
+            Const::Long(l) => LoadBool(d, *l == 0),
+        },
+        JumpF(r, t) => branch(!boolean(r)?, t),
+        JumpCmpIF(cmp, r, imm, t) => branch(!compare(cmp, long(r)?, imm), t),
+        JumpCmpF(cmp, a, b, t) => match (long(a), long(b)) {
+            (Some(a), Some(b)) => branch(!compare(cmp, a, b), t),
+            (None, Some(b)) => JumpCmpIF(cmp, a, b, t),
+            (Some(a), None) => JumpCmpIF(swap(cmp), b, a, t),
+            (None, None) => return None,
+        },
+        _ => return None,
+    })
+}
+
+impl Pass for ConstFold
+{
+    fn name(&self) -> &'static str
+    {
+        "const-fold"
+    }
+
+    fn run(&self, code: &mut Vec<Instruction>) -> bool
+    {
+        use self::Instruction::*;
+
+        let leaders = leaders(code);
+        let mut known: HashMap<usize, Const> = HashMap::new();
+        let mut changed = false;
+
+        for i in 0..code.len() {
+            if leaders[i] {
+                known.clear();
+            }
+            if let Some(folded) = fold(&code[i], i, &known) {
+                if folded.to_string() != code[i].to_string() {
+                    code[i] = folded;
+                    changed = true;
+                }
+            }
+
+            match code[i] {
+                LoadLong(d, l) => {
+                    known.insert(d, Const::Long(l));
+                }
+                LoadBool(d, b) => {
+                    known.insert(d, Const::Bool(b));
+                }
+                Call(dest, r, _) => known.retain(|&k, _| k <= r && k != dest),
+                ref ins => {
+                    if let Some(d) = ins.writes() {
+                        known.remove(&d);
+                    }
+                }
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,60 @@
+use super::{leaders, remove, rename_reads, Pass};
+use crate::opcodes::Instruction;
+use std::collections::HashMap;
+
+/// Copy propagation inside basic blocks and `Move` elimination
+///
+/// After `Move(A, B)` reads of R(A) are replaced by reads of R(B) until either register is written.
+/// Moves into the same register are removed, moves that become dead are removed by `DeadStores`
+pub struct CopyProp;
+
+impl Pass for CopyProp
+{
+    fn name(&self) -> &'static str
+    {
+        "copy-prop"
+    }
+
+    fn run(&self, code: &mut Vec<Instruction>) -> bool
+    {
+        use self::Instruction::*;
+
+        let leaders = leaders(code);
+        let mut copies: HashMap<usize, usize> = HashMap::new();
+        let mut keep = vec![true; code.len()];
+        let mut changed = false;
+
+        for i in 0..code.len() {
+            if leaders[i] {
+                copies.clear();
+            }
+
+            let before = code[i].to_string();
+            rename_reads(&mut code[i], |r| *copies.get(&r).unwrap_or(&r));
+            if code[i].to_string() != before {
+                changed = true;
+            }
+
+            match code[i] {
+                Move(d, s) if d == s => {
+                    keep[i] = false;
+                    changed = true;
+                }
+                Call(dest, r, _) => {
+                    copies.retain(|&k, &mut v| k <= r && k != dest && v <= r && v != dest);
+                }
+                ref ins => {
+                    if let Some(d) = ins.writes() {
+                        copies.retain(|&k, &mut v| k != d && v != d);
+                        if let Move(d, s) = *ins {
+                            copies.insert(d, s);
+                        }
+                    }
+                }
+            }
+        }
+
+        remove(code, &keep);
+        changed
+    }
+}
@@ -0,0 +1,114 @@
+use super::{remove, successors, Pass};
+use crate::opcodes::Instruction;
+use std::collections::HashSet;
+
+/// Removes instructions unreachable from function entry and jumps to the next instruction
+pub struct Unreachable;
+
+impl Pass for Unreachable
+{
+    fn name(&self) -> &'static str
+    {
+        "unreachable"
+    }
+
+    fn run(&self, code: &mut Vec<Instruction>) -> bool
+    {
+        if code.is_empty() {
+            return false;
+        }
+        let mut keep = vec![false; code.len()];
+        let mut work = vec![0];
+        while let Some(i) = work.pop() {
+            if i >= code.len() || keep[i] {
+                continue;
+            }
+            keep[i] = true;
+            work.extend(successors(code, i));
+        }
+
+        for (i, ins) in code.iter().enumerate() {
+            if ins.jump_target() == Some(i + 1) {
+                keep[i] = false;
+            }
+        }
+
+        let changed = keep.iter().any(|k| !k);
+        remove(code, &keep);
+        changed
+    }
+}
+
+/// Removes loads and moves into registers that are never read afterwards
+pub struct DeadStores;
+
+/// Instruction that has no effect other than writing its destination register
+fn is_pure(ins: &Instruction) -> bool
+{
+    use self::Instruction::*;
+
+    matches!(
+        *ins,
+        LoadBool(_, _)
+            | LoadInt(_, _)
+            | LoadLong(_, _)
+            | LoadFloat(_, _)
+            | LoadDouble(_, _)
+            | LoadChar(_, _)
+            | LoadString(_, _)
+            | LoadConst(_, _)
+            | Move(_, _)
+    )
+}
+
+impl Pass for DeadStores
+{
+    fn name(&self) -> &'static str
+    {
+        "dead-stores"
+    }
+
+    fn run(&self, code: &mut Vec<Instruction>) -> bool
+    {
+        // live registers at entry of each instruction
+        let mut live: Vec<HashSet<usize>> = vec![HashSet::new(); code.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in (0..code.len()).rev() {
+                let mut set = HashSet::new();
+                for s in successors(code, i) {
+                    if s < code.len() {
+                        set.extend(live[s].iter().cloned());
+                    }
+                }
+                if let Some(d) = code[i].writes() {
+                    set.remove(&d);
+                }
+                set.extend(code[i].reads());
+                if set != live[i] {
+                    live[i] = set;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut keep = vec![true; code.len()];
+        for i in 0..code.len() {
+            if !is_pure(&code[i]) {
+                continue;
+            }
+            let d = code[i].writes().unwrap();
+            let used = successors(code, i)
+                .into_iter()
+                .any(|s| s < code.len() && live[s].contains(&d));
+            if !used {
+                keep[i] = false;
+            }
+        }
+
+        let changed = keep.iter().any(|k| !k);
+        remove(code, &keep);
+        changed
+    }
+}
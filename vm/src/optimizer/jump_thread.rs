@@ -0,0 +1,52 @@
+use super::Pass;
+use crate::opcodes::Instruction;
+
+/// Jump threading
+///
+/// Jump to unconditional jump goes straight to its target, jump to `Ret` becomes that `Ret`, and
+/// `JumpF` to `JumpF` testing the same register goes to the target of the second one
+pub struct JumpThread;
+
+impl Pass for JumpThread
+{
+    fn name(&self) -> &'static str
+    {
+        "jump-thread"
+    }
+
+    fn run(&self, code: &mut Vec<Instruction>) -> bool
+    {
+        use self::Instruction::*;
+
+        let mut changed = false;
+        for i in 0..code.len() {
+            let target = match code[i].jump_target() {
+                Some(t) => t,
+                None => continue,
+            };
+
+            // follow chain of jumps, but not around a loop
+            let mut t = target;
+            for _ in 0..code.len() {
+                match code.get(t) {
+                    Some(&Jump(u)) if u != t => t = u,
+                    Some(&JumpF(r2, u)) if u != t && matches!(code[i], JumpF(r, _) if r == r2) => t = u,
+                    _ => break,
+                }
+            }
+
+            if let Jump(_) = code[i] {
+                if let Some(ret @ Ret(_)) | Some(ret @ Ret0) = code.get(t) {
+                    code[i] = ret.clone();
+                    changed = true;
+                    continue;
+                }
+            }
+            if t != target {
+                code[i].set_jump_target(t);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
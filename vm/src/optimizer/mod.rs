@@ -0,0 +1,215 @@
+//! Bytecode optimizer
+//!
+//! Passes rewrite instructions of a single function. Labels are resolved before passes run,
+//! so every pass sees jumps by instruction index only.
+//!
+//! Optimization levels:
+//! - 0: code is left as is
+//! - 1: constant folding and propagation, jump threading, unreachable code removal
+//! - 2: level 1 plus copy propagation and dead store elimination
+
+mod const_fold;
+mod copy_prop;
+mod dce;
+mod jump_thread;
+
+pub use self::{
+    const_fold::ConstFold,
+    copy_prop::CopyProp,
+    dce::{DeadStores, Unreachable},
+    jump_thread::JumpThread,
+};
+
+use crate::opcodes::Instruction;
+use std::collections::HashMap;
+
+/// Passes are repeated until code stops changing, but not more than this count of times
+const MAX_ROUNDS: usize = 16;
+
+/// Optimization pass over instructions of a function
+pub trait Pass
+{
+    fn name(&self) -> &'static str;
+    /// Rewrite `code`, returns true if code was changed
+    fn run(&self, code: &mut Vec<Instruction>) -> bool;
+}
+
+/// Passes enabled at optimization `level`
+pub fn passes(level: u8) -> Vec<Box<dyn Pass>>
+{
+    let mut passes: Vec<Box<dyn Pass>> = vec![];
+    if level >= 1 {
+        passes.push(Box::new(ConstFold));
+        passes.push(Box::new(JumpThread));
+        passes.push(Box::new(Unreachable));
+    }
+    if level >= 2 {
+        passes.push(Box::new(CopyProp));
+        passes.push(Box::new(DeadStores));
+    }
+    passes
+}
+
+/// Optimize `code` at optimization `level`
+///
+/// Code that uses undefined labels is returned unchanged
+pub fn optimize(code: Vec<Instruction>, level: u8) -> Vec<Instruction>
+{
+    if level == 0 {
+        return code;
+    }
+    let mut code = match resolve_labels(&code) {
+        Some(code) => code,
+        None => return code,
+    };
+
+    let passes = passes(level);
+    for _ in 0..MAX_ROUNDS {
+        let mut changed = false;
+        for pass in passes.iter() {
+            changed |= pass.run(&mut code);
+        }
+        if !changed {
+            break;
+        }
+    }
+    code
+}
+
+/// Replace `Goto`/`GotoF` by `Jump`/`JumpF` and remove labels
+pub fn resolve_labels(code: &[Instruction]) -> Option<Vec<Instruction>>
+{
+    use self::Instruction::*;
+
+    let mut labels = HashMap::new();
+    for (i, ins) in code.iter().enumerate() {
+        if let Label(id) = ins {
+            labels.insert(*id, i);
+        }
+    }
+
+    let mut code: Vec<Instruction> = code
+        .iter()
+        .map(|ins| {
+            Some(match *ins {
+                Goto(id) => Jump(*labels.get(&id)?),
+                GotoF(r, id) => JumpF(r, *labels.get(&id)?),
+                ref ins => ins.clone(),
+            })
+        })
+        .collect::<Option<_>>()?;
+    let keep: Vec<bool> = code.iter().map(|ins| !matches!(ins, Label(_))).collect();
+    remove(&mut code, &keep);
+    Some(code)
+}
+
+/// Remove instructions which aren't marked in `keep`, jumps to removed instruction go to the next kept one
+pub fn remove(code: &mut Vec<Instruction>, keep: &[bool])
+{
+    let mut index = Vec::with_capacity(code.len() + 1);
+    let mut count = 0;
+    for &k in keep.iter() {
+        index.push(count);
+        if k {
+            count += 1;
+        }
+    }
+    index.push(count);
+
+    let old = std::mem::take(code);
+    for (mut ins, &k) in old.into_iter().zip(keep.iter()) {
+        if !k {
+            continue;
+        }
+        if let Some(t) = ins.jump_target() {
+            ins.set_jump_target(index[t.min(index.len() - 1)]);
+        }
+        code.push(ins);
+    }
+}
+
+/// Whether execution doesn't continue to the next instruction after `ins`
+pub fn ends_block(ins: &Instruction) -> bool
+{
+    use self::Instruction::*;
+
+    matches!(*ins, Jump(_) | Ret(_) | Ret0 | TailCall(_, _))
+}
+
+/// Instructions that may execute after instruction at `i`, index `code.len()` means end of code
+pub fn successors(code: &[Instruction], i: usize) -> Vec<usize>
+{
+    let ins = &code[i];
+    let mut succs = vec![];
+    if !ends_block(ins) {
+        succs.push(i + 1);
+    }
+    if let Some(t) = ins.jump_target() {
+        succs.push(t);
+    }
+    succs
+}
+
+/// First instructions of basic blocks
+pub fn leaders(code: &[Instruction]) -> Vec<bool>
+{
+    let mut leaders = vec![false; code.len() + 1];
+    leaders[0] = true;
+    for (i, ins) in code.iter().enumerate() {
+        if let Some(t) = ins.jump_target() {
+            leaders[t.min(code.len())] = true;
+            leaders[i + 1] = true;
+        }
+        if ends_block(ins) {
+            leaders[i + 1] = true;
+        }
+    }
+    leaders
+}
+
+/// Rename registers read by `ins` with `f`
+///
+/// Operands of `Call` and `TailCall` are never renamed: they describe a window of consecutive registers
+pub fn rename_reads(ins: &mut Instruction, f: impl Fn(usize) -> usize)
+{
+    use self::Instruction::*;
+
+    match ins {
+        StoreGlobal(r, _) | JumpF(r, _) | GotoF(r, _) | JumpCmpIF(_, r, _, _) | Ret(r) | Yield(r) => *r = f(*r),
+        Move(_, r) | Not(_, r) | Neg(_, r) | BitNot(_, r) | AddI(_, r, _) => *r = f(*r),
+        JumpCmpF(_, r1, r2, _) => {
+            *r1 = f(*r1);
+            *r2 = f(*r2);
+        }
+        StoreAt(r1, r2, r3) | Store(r1, r2, r3) => {
+            *r1 = f(*r1);
+            *r2 = f(*r2);
+            *r3 = f(*r3);
+        }
+        LoadAt(_, r1, r2)
+        | LoadSuper(_, r1, r2)
+        | Isa(_, r1, r2)
+        | Add(_, r1, r2)
+        | Sub(_, r1, r2)
+        | Mul(_, r1, r2)
+        | Div(_, r1, r2)
+        | Rem(_, r1, r2)
+        | IntDiv(_, r1, r2)
+        | Pow(_, r1, r2)
+        | Gt(_, r1, r2)
+        | Lt(_, r1, r2)
+        | Ge(_, r1, r2)
+        | Le(_, r1, r2)
+        | Eq(_, r1, r2)
+        | Neq(_, r1, r2)
+        | Shr(_, r1, r2)
+        | Shl(_, r1, r2)
+        | BitOr(_, r1, r2)
+        | BitXor(_, r1, r2)
+        | BitAnd(_, r1, r2) => {
+            *r1 = f(*r1);
+            *r2 = f(*r2);
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,196 @@
+//! Ranges of integers
+//!
+//! `a..b` goes from `a` up to `b` excluding it, `a..=b` includes `b`. `r.step(n)` is the same range counting
+//! by `n`, negative step counts down: `(5..0).step(-1)` is 5, 4, 3, 2, 1. Range is empty if `end` can't be
+//! reached from `start` in direction of step.
+
+use crate::{
+    array::{arg, error, Array},
+    error::VmError,
+    function::{Function, Method},
+    iterator::Iterate,
+    machine::Machine,
+    object::{Object, ObjectAddon},
+    object_pool::ObjectPool,
+    value::Value,
+};
+use std::any::Any;
+
+#[derive(Clone, Debug)]
+pub struct Range
+{
+    pub start: i64,
+    pub end: i64,
+    pub inclusive: bool,
+    pub step: i64,
+}
+
+/// `v` as bound or step of range
+fn integer(v: Value, what: &str) -> Result<i64, VmError>
+{
+    match v {
+        Value::Int(i) => Ok(i64::from(i)),
+        Value::Long(l) => Ok(l),
+        v => Err(error(format!("{} must be integer, found {:?}", what, v))),
+    }
+}
+
+/// `Range` passed as `this`
+fn this_range<'a>(m: &'a Machine, args: &[Value], method: &str) -> Result<&'a Range, VmError>
+{
+    let range = match args[0] {
+        Value::Object(id) => m.pool.get_direct_typed::<Range>(id),
+        _ => None,
+    };
+    range.ok_or_else(|| error(format!("Range::{} expects Range as this", method)))
+}
+
+/// `r.step(n)`: range with the same bounds counting by `n`
+pub fn range_step(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let mut range = this_range(m, args, "step")?.clone();
+    range.step = integer(arg(args, 0), "range step")?;
+    if range.step == 0 {
+        return Err(error(String::from("range step must not be zero")));
+    }
+    Ok(Value::Object(m.pool.allocate(Box::new(range))))
+}
+
+/// `r.toArray()`: array of elements of range
+pub fn range_to_array(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let mut elements = Vec::new();
+    let mut numbers = this_range(m, args, "toArray")?.numbers();
+    while let Some(v) = numbers.next(m)? {
+        elements.push(v);
+    }
+    Ok(Array::allocate(m, elements))
+}
+
+/// Method `name` of Range
+pub fn range_method(name: &str) -> Option<Method>
+{
+    let method: Method = match name {
+        "step" => range_step,
+        "toArray" => range_to_array,
+        _ => return None,
+    };
+    Some(method)
+}
+
+impl Range
+{
+    /// Store range from `start` to `end` counting by one in object pool, bounds must be integers
+    pub fn allocate(m: &mut Machine, start: Value, end: Value, inclusive: bool) -> Result<Value, VmError>
+    {
+        let range = Range {
+            start: integer(start, "range bound")?,
+            end: integer(end, "range bound")?,
+            inclusive,
+            step: 1,
+        };
+        Ok(Value::Object(m.pool.allocate(Box::new(range))))
+    }
+
+    fn numbers(&self) -> Numbers
+    {
+        Numbers {
+            next: Some(self.start),
+            range: self.clone(),
+        }
+    }
+}
+
+/// Elements of range in order
+struct Numbers
+{
+    /// `None` once it overflows
+    next: Option<i64>,
+    range: Range,
+}
+
+impl Iterate for Numbers
+{
+    fn next(&mut self, _: &mut Machine) -> Result<Option<Value>, VmError>
+    {
+        let n = match self.next {
+            Some(n) => n,
+            None => return Ok(None),
+        };
+        let end = self.range.end;
+        let within = match (self.range.step > 0, self.range.inclusive) {
+            (true, false) => n < end,
+            (true, true) => n <= end,
+            (false, false) => n > end,
+            (false, true) => n >= end,
+        };
+        if !within {
+            return Ok(None);
+        }
+        self.next = n.checked_add(self.range.step);
+        Ok(Some(Value::Long(n)))
+    }
+}
+
+impl ObjectAddon for Range
+{
+    fn typename(&self, _: &mut Machine) -> String
+    {
+        String::from("Range")
+    }
+
+    fn to_String(&self, _: &mut Machine) -> String
+    {
+        let dots = if self.inclusive { "..=" } else { ".." };
+        let range = format!("{}{}{}", self.start, dots, self.end);
+        if self.step == 1 {
+            range
+        } else {
+            format!("({}).step({})", range, self.step)
+        }
+    }
+}
+
+impl Object for Range
+{
+    fn initialize(&mut self, _: &mut ObjectPool)
+    {
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        self as &dyn Any
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    {
+        self as &mut dyn Any
+    }
+
+    fn get_children(&self) -> Vec<usize>
+    {
+        Vec::new()
+    }
+
+    fn iterator(&self, _: &mut Machine, _: Value) -> Result<Box<dyn Iterate>, VmError>
+    {
+        Ok(Box::new(self.numbers()))
+    }
+
+    fn load_at(&self, m: &mut Machine, args: Vec<Value>, rindex: usize)
+    {
+        let name = args[1].to_String(m);
+        match range_method(&name) {
+            Some(method) => {
+                let function = Value::Object(m.pool.allocate(Box::new(Function::from_method(method))));
+                m.set(rindex, function);
+            }
+            None => m.raise(error(format!("Range has no method `{}`", name))),
+        }
+    }
+
+    fn store_at(&self, m: &mut Machine, _: Vec<Value>, _: usize)
+    {
+        m.raise(error(String::from("range is immutable")));
+    }
+}
@@ -1,4 +1,231 @@
-use crate::{machine::Machine, object::*, object_pool::ObjectPool};
+//! Strings
+//!
+//! Strings are indexed by Unicode scalar values: `s[i]`, `len`, `substring` and `indexOf` count characters,
+//! not bytes. `s[i]`, elements of `s.chars()` and of `for c in s` are `Char`s.
+
+use crate::{
+    array::{arg, error, index, Array},
+    bigint::BigInt,
+    error::VmError,
+    function::{Function, Method},
+    iterator::Iterate,
+    machine::Machine,
+    object::*,
+    object_pool::ObjectPool,
+    value::Value,
+};
+use std::convert::TryFrom;
+
+/// String passed as `this`
+fn this_str<'a>(m: &'a Machine, args: &[Value], method: &str) -> Result<&'a str, VmError>
+{
+    str_arg(m, args[0]).ok_or_else(|| error(format!("Str::{} expects Str as this", method)))
+}
+
+/// String stored in `v`
+fn str_arg(m: &Machine, v: Value) -> Option<&str>
+{
+    match v {
+        Value::Object(id) => m.pool.get_direct_typed::<String>(id).map(|s| s.as_str()),
+        _ => None,
+    }
+}
+
+/// Argument `n` of method which must be string
+fn string_arg<'a>(m: &'a Machine, args: &[Value], n: usize, method: &str) -> Result<&'a str, VmError>
+{
+    let v = arg(args, n);
+    str_arg(m, v).ok_or_else(|| error(format!("Str::{} expects Str argument, found {:?}", method, v)))
+}
+
+/// Store `s` in object pool
+pub fn allocate(m: &mut Machine, s: String) -> Value
+{
+    Value::Object(m.pool.allocate(Box::new(s)))
+}
+
+/// `v1 + v2` if both values are strings or one of them is `Char`
+pub fn concat(m: &mut Machine, v1: Value, v2: Value) -> Option<Value>
+{
+    let result = match (v1, v2) {
+        (Value::Char(_), Value::Char(_)) => return None,
+        (Value::Char(a), v2) => format!("{}{}", a, str_arg(m, v2)?),
+        (v1, Value::Char(b)) => format!("{}{}", str_arg(m, v1)?, b),
+        _ => format!("{}{}", str_arg(m, v1)?, str_arg(m, v2)?),
+    };
+    Some(allocate(m, result))
+}
+
+/// Number of characters before byte offset `byte`
+fn char_index(s: &str, byte: usize) -> i32
+{
+    s[..byte].chars().count() as i32
+}
+
+pub fn str_len(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let s = this_str(m, args, "len")?;
+    Ok(Value::Int(s.chars().count() as i32))
+}
+
+/// `s.substring(start, end)`: characters `start..end`, `end` is length of string by default
+pub fn str_substring(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let chars: Vec<char> = this_str(m, args, "substring")?.chars().collect();
+    let start = index(arg(args, 0), chars.len(), true, "Str")?;
+    let end = match arg(args, 1) {
+        Value::Null => chars.len(),
+        v => index(v, chars.len(), true, "Str")?,
+    };
+    if start > end {
+        return Err(error(format!("substring start {} is greater than end {}", start, end)));
+    }
+    let result = chars[start..end].iter().collect();
+    Ok(allocate(m, result))
+}
+
+/// `s.indexOf(sub)`: index of first character of first occurrence of `sub` or -1
+pub fn str_index_of(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let s = this_str(m, args, "indexOf")?;
+    let sub = string_arg(m, args, 0, "indexOf")?;
+    Ok(Value::Int(s.find(sub).map_or(-1, |byte| char_index(s, byte))))
+}
+
+/// `s.split(sep)`: array of parts between occurrences of `sep`
+///
+/// Without `sep` string is split by whitespace, empty `sep` splits it into characters
+pub fn str_split(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let s = this_str(m, args, "split")?;
+    let parts: Vec<String> = match arg(args, 0) {
+        Value::Null => s.split_whitespace().map(String::from).collect(),
+        _ => match string_arg(m, args, 0, "split")? {
+            "" => s.chars().map(String::from).collect(),
+            sep => s.split(sep).map(String::from).collect(),
+        },
+    };
+    let parts = parts.into_iter().map(|part| allocate(m, part)).collect();
+    Ok(Array::allocate(m, parts))
+}
+
+/// `s.replace(from, to)`: replace every occurrence of `from`
+pub fn str_replace(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let s = this_str(m, args, "replace")?;
+    let from = string_arg(m, args, 0, "replace")?;
+    let to = string_arg(m, args, 1, "replace")?;
+    if from.is_empty() {
+        return Err(error(String::from("Str::replace expects non-empty pattern")));
+    }
+    let result = s.replace(from, to);
+    Ok(allocate(m, result))
+}
+
+pub fn str_trim(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let result = this_str(m, args, "trim")?.trim().to_string();
+    Ok(allocate(m, result))
+}
+
+pub fn str_to_upper(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let result = this_str(m, args, "toUpper")?.to_uppercase();
+    Ok(allocate(m, result))
+}
+
+pub fn str_to_lower(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let result = this_str(m, args, "toLower")?.to_lowercase();
+    Ok(allocate(m, result))
+}
+
+pub fn str_starts_with(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let s = this_str(m, args, "startsWith")?;
+    Ok(Value::Bool(s.starts_with(string_arg(m, args, 0, "startsWith")?)))
+}
+
+pub fn str_ends_with(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let s = this_str(m, args, "endsWith")?;
+    Ok(Value::Bool(s.ends_with(string_arg(m, args, 0, "endsWith")?)))
+}
+
+/// `s.chars()`: array of characters
+pub fn str_chars(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let chars = this_str(m, args, "chars")?.chars().map(Value::Char).collect();
+    Ok(Array::allocate(m, chars))
+}
+
+/// `s.repeat(n)`: `s` repeated `n` times
+pub fn str_repeat(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let s = this_str(m, args, "repeat")?;
+    let n = match arg(args, 0) {
+        Value::Int(i) => usize::try_from(i).ok(),
+        Value::Long(l) => usize::try_from(l).ok(),
+        _ => None,
+    };
+    let n = n.ok_or_else(|| error(format!("Str::repeat expects non-negative integer, found {:?}", arg(args, 0))))?;
+    let result = s.repeat(n);
+    Ok(allocate(m, result))
+}
+
+/// `s.parseInt()`: decimal integer, `BigInt` if it doesn't fit into `Long` and `null` if `s` is not an integer
+pub fn str_parse_int(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let big = match BigInt::parse(this_str(m, args, "parseInt")?) {
+        Some(big) => big,
+        None => return Ok(Value::Null),
+    };
+    Ok(match big.to_i64() {
+        Some(l) => Value::Long(l),
+        None => BigInt::allocate(m, big.0),
+    })
+}
+
+/// `s.parseFloat()`: `Double` or `null` if `s` is not a number
+pub fn str_parse_float(m: &mut Machine, args: &[Value]) -> Result<Value, VmError>
+{
+    let s = this_str(m, args, "parseFloat")?;
+    Ok(s.trim().parse::<f64>().map_or(Value::Null, Value::Double))
+}
+
+/// Method `name` of Str
+pub fn str_method(name: &str) -> Option<Method>
+{
+    let method: Method = match name {
+        "len" => str_len,
+        "substring" => str_substring,
+        "indexOf" => str_index_of,
+        "split" => str_split,
+        "replace" => str_replace,
+        "trim" => str_trim,
+        "toUpper" => str_to_upper,
+        "toLower" => str_to_lower,
+        "startsWith" => str_starts_with,
+        "endsWith" => str_ends_with,
+        "chars" => str_chars,
+        "repeat" => str_repeat,
+        "parseInt" => str_parse_int,
+        "parseFloat" => str_parse_float,
+        _ => return None,
+    };
+    Some(method)
+}
+
+/// Characters of string
+struct Chars(std::vec::IntoIter<char>);
+
+impl Iterate for Chars
+{
+    fn next(&mut self, _: &mut Machine) -> Result<Option<Value>, VmError>
+    {
+        Ok(self.0.next().map(Value::Char))
+    }
+}
 
 impl ObjectAddon for String
 {
@@ -9,20 +236,20 @@ impl ObjectAddon for String
 
     fn to_double(&self, _: &mut Machine) -> f64
     {
-        self.parse::<f64>().unwrap()
+        self.trim().parse::<f64>().unwrap_or(f64::NAN)
     }
 
     fn to_float(&self, _: &mut Machine) -> f32
     {
-        self.parse::<f32>().unwrap()
+        self.trim().parse::<f32>().unwrap_or(f32::NAN)
     }
     fn to_int(&self, _: &mut Machine) -> i32
     {
-        self.parse::<i32>().unwrap()
+        self.trim().parse::<i32>().unwrap_or(0)
     }
     fn to_long(&self, _: &mut Machine) -> i64
     {
-        self.parse::<i64>().unwrap()
+        self.trim().parse::<i64>().unwrap_or(0)
     }
     fn typename(&self,_m: &mut Machine) -> String
     {
@@ -51,4 +278,38 @@ impl Object for String
     {
         self as &mut dyn Any
     }
+
+    fn iterator(&self, _: &mut Machine, _: Value) -> Result<Box<dyn Iterate>, VmError>
+    {
+        let chars: Vec<char> = self.chars().collect();
+        Ok(Box::new(Chars(chars.into_iter())))
+    }
+
+    /// Method by name or character by index
+    fn load_at(&self, m: &mut Machine, args: Vec<Value>, rindex: usize)
+    {
+        if let Some(name) = str_arg(m, args[1]) {
+            match str_method(name) {
+                Some(method) => {
+                    let function = Value::Object(m.pool.allocate(Box::new(Function::from_method(method))));
+                    m.set(rindex, function);
+                }
+                None => {
+                    let e = error(format!("Str has no method `{}`", name));
+                    m.raise(e);
+                }
+            }
+            return;
+        }
+        let len = self.chars().count();
+        match index(args[1], len, false, "Str") {
+            Ok(idx) => m.set(rindex, Value::Char(self.chars().nth(idx).unwrap())),
+            Err(e) => m.raise(e),
+        }
+    }
+
+    fn store_at(&self, m: &mut Machine, _: Vec<Value>, _: usize)
+    {
+        m.raise(error(String::from("strings are immutable")));
+    }
 }
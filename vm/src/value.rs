@@ -15,6 +15,8 @@ pub enum Value
     Null,
     /// Boolean
     Bool(bool),
+    /// Unicode scalar value
+    Char(char),
 }
 
 use crate::{machine::Machine, object::ObjectAddon};
@@ -35,14 +37,14 @@ impl ObjectAddon for Value
     fn typename(&self,m: &mut Machine) -> String {
         match self {
             Value::Bool(_) => String::from("Bool"),
-            Value::Double(_) => String::from("Float"),
-            Value::Long(_) => String::from("Int"),
+            Value::Char(_) => String::from("Char"),
+            Value::Float(_) | Value::Double(_) => String::from("Float"),
+            Value::Int(_) | Value::Long(_) => String::from("Int"),
             Value::Object(id) => {
                 let obj = m.pool.get(*id);
                 obj.typename(m)
             }
             Value::Null => String::from("null"),
-            _ => unimplemented!()
         }
     }
     fn to_double(&self, m: &mut Machine) -> f64
@@ -58,6 +60,7 @@ impl ObjectAddon for Value
                 }
             }
             Value::Int(i) => f64::from(*i),
+            Value::Char(c) => f64::from(u32::from(*c)),
             Value::Long(i) => *i as f64,
             Value::Null => 0.0,
             Value::Object(id) => {
@@ -80,6 +83,7 @@ impl ObjectAddon for Value
                 }
             }
             Value::Int(i) => *i as f32,
+            Value::Char(c) => u32::from(*c) as f32,
             Value::Long(i) => *i as f32,
             Value::Null => 0.0,
             Value::Object(id) => {
@@ -102,6 +106,7 @@ impl ObjectAddon for Value
                 }
             }
             Value::Int(i) => *i,
+            Value::Char(c) => u32::from(*c) as i32,
             Value::Long(i) => *i as i32,
             Value::Null => 0,
             Value::Object(id) => {
@@ -124,6 +129,7 @@ impl ObjectAddon for Value
                 }
             }
             Value::Int(i) => i64::from(*i),
+            Value::Char(c) => i64::from(u32::from(*c)),
             Value::Long(i) => *i,
             Value::Null => 0,
             Value::Object(id) => {
@@ -147,6 +153,7 @@ impl ObjectAddon for Value
             }
             Value::Int(i) => i.to_string(),
             Value::Long(i) => i.to_string(),
+            Value::Char(c) => c.to_string(),
             Value::Null => "null".to_string(),
             Value::Object(id) => {
                 let obj = m.pool.get(*id);
@@ -170,6 +177,7 @@ impl ObjectAddon for Value
             }
             Value::Int(i) => i.to_string(),
             Value::Long(i) => i.to_string(),
+            Value::Char(c) => c.to_string(),
             Value::Null => "null".to_string(),
             Value::Object(id) => {
                 let obj = m.pool.get(*id);
@@ -178,7 +186,7 @@ impl ObjectAddon for Value
         }
     }
 
-    fn not(&self, _m: &mut Machine) -> bool
+    fn not(&self, m: &mut Machine) -> bool
     {
         match self {
             Value::Null => true,
@@ -195,7 +203,22 @@ impl ObjectAddon for Value
                 *f == 0.0
             }
             Value::Bool(b) => !b,
-            _ => unimplemented!(),
+            Value::Char(c) => *c == '\0',
+            Value::Object(id) => {
+                let obj = m.pool.get(*id);
+                obj.not(m)
+            }
+        }
+    }
+
+    fn isa(&self, s: String, m: &mut Machine) -> bool
+    {
+        match self {
+            Value::Object(id) => {
+                let obj = m.pool.get(*id);
+                obj.isa(s, m)
+            }
+            v => v.typename(m) == s,
         }
     }
 }
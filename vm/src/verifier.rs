@@ -0,0 +1,206 @@
+//! Bytecode verifier
+//!
+//! Checks code before it's executed: register operands fit into a frame, jumps and labels point
+//! into code, constants exist in object pool, calls to known functions pass right count of
+//! arguments and execution can't run past the last instruction.
+
+use crate::{
+    function::Function,
+    machine::Machine,
+    opcodes::Instruction,
+    value::Value,
+};
+use std::{collections::HashMap, fmt};
+
+/// Count of registers code may use in a frame
+pub const MAX_FRAME_REGISTERS: usize = 256;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError
+{
+    /// Code has no instructions
+    Empty,
+    /// Register operand is not less than `MAX_FRAME_REGISTERS`
+    RegisterOutOfBounds { ip: usize, register: usize },
+    /// `Goto`/`GotoF` to label which isn't defined
+    UnknownLabel { ip: usize, label: usize },
+    /// Label defined more than once
+    DuplicateLabel { ip: usize, label: usize },
+    /// `Jump`-like instruction to index past the end of code
+    JumpOutOfBounds { ip: usize, target: usize },
+    /// `LoadConst` of object which isn't in object pool
+    UnknownConstant { ip: usize, id: usize },
+    /// Call of function with different count of arguments than it expects
+    ArgumentCount { ip: usize, expected: usize, found: usize },
+    /// Execution can run past the last instruction after `ip` without `Ret`
+    MissingRet { ip: usize },
+}
+
+impl fmt::Display for VerifyError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            VerifyError::Empty => write!(f, "Code is empty"),
+            VerifyError::RegisterOutOfBounds { ip, register } => write!(
+                f,
+                "{:04}: register {} is out of bounds (max {})",
+                ip,
+                register,
+                MAX_FRAME_REGISTERS - 1
+            ),
+            VerifyError::UnknownLabel { ip, label } => write!(f, "{:04}: label `{}` not found", ip, label),
+            VerifyError::DuplicateLabel { ip, label } => {
+                write!(f, "{:04}: label `{}` is already defined", ip, label)
+            }
+            VerifyError::JumpOutOfBounds { ip, target } => {
+                write!(f, "{:04}: jump target {} is out of code", ip, target)
+            }
+            VerifyError::UnknownConstant { ip, id } => write!(f, "{:04}: constant `{}` not found", ip, id),
+            VerifyError::ArgumentCount { ip, expected, found } => write!(
+                f,
+                "{:04}: function expects {} arguments, {} given",
+                ip, expected, found
+            ),
+            VerifyError::MissingRet { ip } => write!(f, "{:04}: execution runs past the end of code", ip),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Argument count of virtual function stored in `value`
+fn function_argc(m: &Machine, value: Value) -> Option<usize>
+{
+    match value {
+        Value::Object(id) if m.pool.contains(id) => {
+            match m.pool.get_direct(id).as_any().downcast_ref::<Function>() {
+                Some(Function::Virtual(vf)) => Some(vf.argc),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Verify `code` which is going to be executed by `m`
+pub fn verify(code: &[Instruction], m: &Machine) -> Result<(), VerifyError>
+{
+    use self::Instruction::*;
+
+    if code.is_empty() {
+        return Err(VerifyError::Empty);
+    }
+
+    let mut labels = HashMap::new();
+    for (ip, ins) in code.iter().enumerate() {
+        if let Label(label) = ins {
+            if labels.insert(*label, ip).is_some() {
+                return Err(VerifyError::DuplicateLabel { ip, label: *label });
+            }
+        }
+    }
+
+    let mut targets = vec![false; code.len() + 1];
+    for t in code.iter().filter_map(Instruction::jump_target) {
+        if t <= code.len() {
+            targets[t] = true;
+        }
+    }
+
+    let mut succs = Vec::with_capacity(code.len());
+    // argument count of function last loaded into register, valid until next jump target
+    let mut known: HashMap<usize, usize> = HashMap::new();
+    for (ip, ins) in code.iter().enumerate() {
+        if targets[ip] {
+            known.clear();
+        }
+        for register in ins.registers() {
+            if register >= MAX_FRAME_REGISTERS {
+                return Err(VerifyError::RegisterOutOfBounds { ip, register });
+            }
+        }
+
+        let label = |label: usize| {
+            labels
+                .get(&label)
+                .cloned()
+                .ok_or(VerifyError::UnknownLabel { ip, label })
+        };
+        let target = |target: usize| {
+            if target > code.len() {
+                Err(VerifyError::JumpOutOfBounds { ip, target })
+            } else {
+                Ok(target)
+            }
+        };
+
+        let next = match *ins {
+            Goto(l) => vec![label(l)?],
+            GotoF(_, l) => vec![ip + 1, label(l)?],
+            Jump(t) => vec![target(t)?],
+            JumpF(_, t) | JumpCmpF(_, _, _, t) | JumpCmpIF(_, _, _, t) => vec![ip + 1, target(t)?],
+            Ret(_) | Ret0 | TailCall(_, _) => vec![],
+            _ => vec![ip + 1],
+        };
+        succs.push(next);
+
+        if let LoadConst(_, id) = *ins {
+            if !m.pool.contains(id) {
+                return Err(VerifyError::UnknownConstant { ip, id });
+            }
+        }
+
+        match *ins {
+            Call(_, r, argc) | TailCall(r, argc) => {
+                if let Some(&expected) = known.get(&r) {
+                    if expected != argc {
+                        return Err(VerifyError::ArgumentCount {
+                            ip,
+                            expected,
+                            found: argc,
+                        });
+                    }
+                }
+            }
+            Label(_) => known.clear(),
+            _ => {}
+        }
+        let function = match *ins {
+            LoadConst(r, id) => Some((r, function_argc(m, Value::Object(id)))),
+            LoadGlobal(r, g) => Some((r, m.globals.get(&g).and_then(|v| function_argc(m, *v)))),
+            _ => None,
+        };
+        match *ins {
+            Call(dest, r, _) => {
+                // callee frame may overwrite every register above R(r)
+                known.retain(|&k, _| k < r && k != dest);
+            }
+            _ => {
+                if let Some(d) = ins.writes() {
+                    known.remove(&d);
+                }
+            }
+        }
+        if let Some((r, Some(argc))) = function {
+            known.insert(r, argc);
+        }
+    }
+
+    // every instruction reachable from entry must not fall through the end of code
+    let mut reachable = vec![false; code.len()];
+    let mut work = vec![0];
+    while let Some(ip) = work.pop() {
+        if reachable[ip] {
+            continue;
+        }
+        reachable[ip] = true;
+        for &next in &succs[ip] {
+            if next >= code.len() {
+                return Err(VerifyError::MissingRet { ip });
+            }
+            work.push(next);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,360 @@
+extern crate jazz_vm;
+
+use jazz_vm::{
+    error::VmError,
+    machine::Machine,
+    object::ObjectAddon,
+    opcodes::{Cmp, Instruction},
+    value::Value,
+};
+
+type Binary = fn(usize, usize, usize) -> Instruction;
+
+/// Run `ins` with R(1) = `a` and R(2) = `b`, result is R(3)
+fn run(m: &mut Machine, ins: Instruction, a: Value, b: Value) -> Result<Value, VmError>
+{
+    let load = |r, v| match v {
+        Value::Int(i) => Instruction::LoadInt(r, i),
+        Value::Long(l) => Instruction::LoadLong(r, l),
+        Value::Float(f) => Instruction::LoadFloat(r, f),
+        Value::Double(d) => Instruction::LoadDouble(r, d),
+        Value::Bool(b) => Instruction::LoadBool(r, b),
+        Value::Char(c) => Instruction::LoadChar(r, c),
+        Value::Object(id) => Instruction::LoadConst(r, id),
+        Value::Null => Instruction::Move(r, 0),
+    };
+    m.run_code(vec![load(1, a), load(2, b), ins, Instruction::Ret(3)])
+}
+
+fn binary(op: Binary, a: Value, b: Value) -> Result<Value, VmError>
+{
+    run(&mut Machine::new(), op(3, 1, 2), a, b)
+}
+
+fn unary(op: fn(usize, usize) -> Instruction, a: Value) -> Result<Value, VmError>
+{
+    run(&mut Machine::new(), op(3, 1), a, Value::Null)
+}
+
+fn show(v: Result<Value, VmError>) -> String
+{
+    match v {
+        Ok(v) => format!("{:?}", v),
+        Err(e) => format!("error: {}", e),
+    }
+}
+
+fn is_number(v: Value) -> bool
+{
+    matches!(v, Value::Int(_) | Value::Long(_) | Value::Float(_) | Value::Double(_))
+}
+
+/// Name of variant of `v`
+fn kind(v: Value) -> String
+{
+    let debug = format!("{:?}", v);
+    debug.split('(').next().unwrap().to_string()
+}
+
+fn double(v: Value) -> f64
+{
+    match v {
+        Value::Int(i) => f64::from(i),
+        Value::Long(l) => l as f64,
+        Value::Float(f) => f64::from(f),
+        Value::Double(d) => d,
+        v => panic!("not a number: {:?}", v),
+    }
+}
+
+/// Common type of numbers, see `jazz_vm::numeric`
+fn common(a: Value, b: Value) -> &'static str
+{
+    let rank = |v| match v {
+        Value::Int(_) => 0,
+        Value::Long(_) => 1,
+        Value::Float(_) => 2,
+        _ => 3,
+    };
+    const TABLE: [[&str; 4]; 4] = [
+        ["Int", "Long", "Float", "Double"],
+        ["Long", "Long", "Double", "Double"],
+        ["Float", "Double", "Float", "Double"],
+        ["Double", "Double", "Double", "Double"],
+    ];
+    TABLE[rank(a)][rank(b)]
+}
+
+#[test]
+fn arithmetic_matrix()
+{
+    let mut m = Machine::new();
+    let string = |m: &mut Machine| Value::Object(m.pool.allocate(Box::new(String::from("6"))));
+    let (s1, s2) = (string(&mut m), string(&mut m));
+    let lefts = [
+        Value::Int(6),
+        Value::Long(6),
+        Value::Float(6.0),
+        Value::Double(6.0),
+        Value::Bool(true),
+        Value::Null,
+        s1,
+    ];
+    let rights = [
+        Value::Int(4),
+        Value::Long(4),
+        Value::Float(4.0),
+        Value::Double(4.0),
+        Value::Bool(true),
+        Value::Null,
+        s2,
+    ];
+    let ops: [(Binary, f64, f64); 7] = [
+        (Instruction::Add, 10.0, 10.0),
+        (Instruction::Sub, 2.0, 2.0),
+        (Instruction::Mul, 24.0, 24.0),
+        (Instruction::Div, 1.0, 1.5),
+        (Instruction::IntDiv, 1.0, 1.0),
+        (Instruction::Rem, 2.0, 2.0),
+        (Instruction::Pow, 1296.0, 1296.0),
+    ];
+
+    for &a in lefts.iter() {
+        for &b in rights.iter() {
+            for &(op, integer, float) in ops.iter() {
+                let ins = op(3, 1, 2);
+                let result = run(&mut m, ins.clone(), a, b);
+                if let (Value::Object(_), Value::Object(_), Instruction::Add(..)) = (a, b, &ins) {
+                    let result = result.unwrap();
+                    assert_eq!("66", result.to_String(&mut m), "{} with {:?} and {:?}", ins, a, b);
+                    continue;
+                }
+                if !is_number(a) || !is_number(b) {
+                    assert!(result.is_err(), "{} with {:?} and {:?}", ins, a, b);
+                    continue;
+                }
+                let result = result.unwrap();
+                let ty = common(a, b);
+                assert_eq!(ty, kind(result), "{} with {:?} and {:?}", ins, a, b);
+                let expected = if ty == "Int" || ty == "Long" { integer } else { float };
+                assert_eq!(expected, double(result), "{} with {:?} and {:?}", ins, a, b);
+            }
+        }
+    }
+}
+
+#[test]
+fn comparison_matrix()
+{
+    let mut m = Machine::new();
+    let strings: Vec<(usize, &str)> = ["a", "a", "b"]
+        .iter()
+        .map(|s| (m.pool.allocate(Box::new(s.to_string())), *s))
+        .collect();
+    let text = |id: usize| strings.iter().find(|s| s.0 == id).unwrap().1;
+    let values = [
+        Value::Int(4),
+        Value::Long(4),
+        Value::Float(4.0),
+        Value::Double(4.0),
+        Value::Int(6),
+        Value::Long(6),
+        Value::Float(6.0),
+        Value::Double(6.0),
+        Value::Bool(true),
+        Value::Bool(false),
+        Value::Null,
+        Value::Object(strings[0].0),
+        Value::Object(strings[1].0),
+        Value::Object(strings[2].0),
+        Value::Char('a'),
+        Value::Char('b'),
+    ];
+    let cmps = [Cmp::Gt, Cmp::Lt, Cmp::Ge, Cmp::Le, Cmp::Eq, Cmp::Neq];
+
+    for &a in values.iter() {
+        for &b in values.iter() {
+            for &cmp in cmps.iter() {
+                let ins = match cmp {
+                    Cmp::Gt => Instruction::Gt(3, 1, 2),
+                    Cmp::Lt => Instruction::Lt(3, 1, 2),
+                    Cmp::Ge => Instruction::Ge(3, 1, 2),
+                    Cmp::Le => Instruction::Le(3, 1, 2),
+                    Cmp::Eq => Instruction::Eq(3, 1, 2),
+                    Cmp::Neq => Instruction::Neq(3, 1, 2),
+                };
+                let result = run(&mut m, ins.clone(), a, b);
+                let expected = if is_number(a) && is_number(b) {
+                    let (a, b) = (double(a), double(b));
+                    match cmp {
+                        Cmp::Gt => a > b,
+                        Cmp::Lt => a < b,
+                        Cmp::Ge => a >= b,
+                        Cmp::Le => a <= b,
+                        Cmp::Eq => a == b,
+                        Cmp::Neq => a != b,
+                    }
+                } else if let (Value::Char(a), Value::Char(b)) = (a, b) {
+                    match cmp {
+                        Cmp::Gt => a > b,
+                        Cmp::Lt => a < b,
+                        Cmp::Ge => a >= b,
+                        Cmp::Le => a <= b,
+                        Cmp::Eq => a == b,
+                        Cmp::Neq => a != b,
+                    }
+                } else if let (Value::Object(a), Value::Object(b)) = (a, b) {
+                    // strings are ordered lexicographically
+                    let (a, b) = (text(a), text(b));
+                    match cmp {
+                        Cmp::Gt => a > b,
+                        Cmp::Lt => a < b,
+                        Cmp::Ge => a >= b,
+                        Cmp::Le => a <= b,
+                        Cmp::Eq => a == b,
+                        Cmp::Neq => a != b,
+                    }
+                } else {
+                    let same = match (a, b) {
+                        (Value::Bool(a), Value::Bool(b)) => a == b,
+                        (Value::Null, Value::Null) => true,
+                        _ => false,
+                    };
+                    match cmp {
+                        Cmp::Eq => same,
+                        Cmp::Neq => !same,
+                        _ => {
+                            assert!(result.is_err(), "{} with {:?} and {:?}", ins, a, b);
+                            continue;
+                        }
+                    }
+                };
+                assert_eq!(
+                    format!("Bool({})", expected),
+                    show(result),
+                    "{} with {:?} and {:?}",
+                    ins,
+                    a,
+                    b
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn int_overflow_is_promoted()
+{
+    assert_eq!("Long(2147483648)", show(binary(Instruction::Add, Value::Int(i32::MAX), Value::Int(1))));
+    assert_eq!("Long(-2147483649)", show(binary(Instruction::Sub, Value::Int(i32::MIN), Value::Int(1))));
+    assert_eq!("Long(4294967296)", show(binary(Instruction::Mul, Value::Int(65536), Value::Int(65536))));
+    assert_eq!("Long(2147483648)", show(binary(Instruction::Pow, Value::Int(2), Value::Int(31))));
+    assert_eq!("Long(2147483648)", show(binary(Instruction::Div, Value::Int(i32::MIN), Value::Int(-1))));
+    assert_eq!("Long(2147483648)", show(binary(Instruction::IntDiv, Value::Int(i32::MIN), Value::Int(-1))));
+    assert_eq!("Long(2147483648)", show(unary(Instruction::Neg, Value::Int(i32::MIN))));
+    assert_eq!("Int(0)", show(binary(Instruction::Rem, Value::Int(i32::MIN), Value::Int(-1))));
+}
+
+#[test]
+fn long_overflow_is_promoted()
+{
+    let big = |ins: Instruction, a: Value, b: Value| {
+        let mut m = Machine::new();
+        let v = run(&mut m, ins, a, b).unwrap();
+        assert!(matches!(v, Value::Object(_)), "{:?}", v);
+        v.to_String(&mut m)
+    };
+    assert_eq!("9223372036854775808", big(Instruction::Add(3, 1, 2), Value::Long(i64::MAX), Value::Int(1)));
+    assert_eq!("-9223372036854775809", big(Instruction::Sub(3, 1, 2), Value::Long(i64::MIN), Value::Long(1)));
+    assert_eq!("18446744073709551614", big(Instruction::Mul(3, 1, 2), Value::Long(i64::MAX), Value::Long(2)));
+    assert_eq!("9223372036854775808", big(Instruction::Div(3, 1, 2), Value::Long(i64::MIN), Value::Long(-1)));
+    assert_eq!("18446744073709551616", big(Instruction::Pow(3, 1, 2), Value::Long(2), Value::Long(64)));
+    assert_eq!("18446744073709551616", big(Instruction::Pow(3, 1, 2), Value::Int(2), Value::Int(64)));
+    assert_eq!("9223372036854775808", big(Instruction::Neg(3, 1), Value::Long(i64::MIN), Value::Null));
+
+    let mut m = Machine::new();
+    let code = vec![Instruction::LoadLong(1, i64::MAX), Instruction::Inc(1), Instruction::Ret(1)];
+    let result = m.run_code(code).unwrap();
+    assert_eq!("9223372036854775808", result.to_String(&mut m));
+    let code = vec![Instruction::LoadInt(1, i32::MAX), Instruction::AddI(1, 1, 1), Instruction::Ret(1)];
+    assert_eq!("Long(2147483648)", show(m.run_code(code)));
+}
+
+#[test]
+fn division_by_zero()
+{
+    let zeros = [Value::Int(0), Value::Long(0), Value::Float(0.0), Value::Double(-0.0)];
+    for &zero in zeros.iter() {
+        for &op in [Instruction::Div, Instruction::IntDiv, Instruction::Rem].iter() {
+            assert_eq!(
+                "error: Runtime Error: `division by zero`",
+                show(binary(op, Value::Double(1.0), zero))
+            );
+            assert_eq!(
+                "error: Runtime Error: `division by zero`",
+                show(binary(op, Value::Int(1), zero))
+            );
+        }
+    }
+}
+
+#[test]
+fn rounding()
+{
+    assert_eq!("Int(-2)", show(binary(Instruction::Div, Value::Int(-7), Value::Int(3))));
+    assert_eq!("Int(-2)", show(binary(Instruction::IntDiv, Value::Int(-7), Value::Int(3))));
+    assert_eq!("Int(-1)", show(binary(Instruction::Rem, Value::Int(-7), Value::Int(3))));
+    assert_eq!("Float(-3.0)", show(binary(Instruction::IntDiv, Value::Float(-7.5), Value::Int(2))));
+    assert_eq!("Double(-1.5)", show(binary(Instruction::Rem, Value::Double(-7.5), Value::Long(2))));
+    assert_eq!("Double(0.25)", show(binary(Instruction::Pow, Value::Int(2), Value::Int(-2))));
+    assert_eq!("Double(0.5)", show(binary(Instruction::Pow, Value::Long(2), Value::Long(-1))));
+}
+
+#[test]
+fn nan_is_unordered()
+{
+    let nan = Value::Double(f64::NAN);
+    assert_eq!("Bool(false)", show(binary(Instruction::Eq, nan, nan)));
+    assert_eq!("Bool(true)", show(binary(Instruction::Neq, nan, nan)));
+    assert_eq!("Bool(false)", show(binary(Instruction::Ge, nan, Value::Int(1))));
+    assert_eq!("Bool(false)", show(binary(Instruction::Lt, nan, Value::Int(1))));
+}
+
+#[test]
+fn non_numeric_operands()
+{
+    match binary(Instruction::Rem, Value::Bool(true), Value::Int(2)) {
+        Err(VmError::RuntimeError(cause)) => {
+            assert_eq!("`%` cannot be applied to Bool(true) and Int(2)", cause)
+        }
+        v => panic!("{}", show(v)),
+    }
+    match binary(Instruction::Le, Value::Null, Value::Int(2)) {
+        Err(VmError::RuntimeError(cause)) => assert_eq!("`<=` cannot be applied to Null and Int(2)", cause),
+        v => panic!("{}", show(v)),
+    }
+    assert!(unary(Instruction::Neg, Value::Bool(false)).is_err());
+    assert!(unary(Instruction::BitNot, Value::Float(1.0)).is_err());
+}
+
+#[test]
+fn unary_operators()
+{
+    assert_eq!("Int(-5)", show(unary(Instruction::Neg, Value::Int(5))));
+    assert_eq!("Long(5)", show(unary(Instruction::Neg, Value::Long(-5))));
+    assert_eq!("Float(-0.5)", show(unary(Instruction::Neg, Value::Float(0.5))));
+    assert_eq!("Double(0.5)", show(unary(Instruction::Neg, Value::Double(-0.5))));
+    assert_eq!("Int(-6)", show(unary(Instruction::BitNot, Value::Int(5))));
+    assert_eq!("Long(0)", show(unary(Instruction::BitNot, Value::Long(-1))));
+}
+
+#[test]
+fn typenames()
+{
+    let mut m = Machine::new();
+    assert_eq!("Int", Value::Int(1).typename(&mut m));
+    assert_eq!("Int", Value::Long(1).typename(&mut m));
+    assert_eq!("Float", Value::Float(1.0).typename(&mut m));
+    assert_eq!("Float", Value::Double(1.0).typename(&mut m));
+    assert_eq!("Char", Value::Char('a').typename(&mut m));
+}
@@ -0,0 +1,148 @@
+extern crate jazz_vm;
+
+use jazz_vm::{
+    bigint::BigInt,
+    error::VmError,
+    machine::Machine,
+    object::ObjectAddon,
+    opcodes::Instruction,
+    value::Value,
+};
+
+type Binary = fn(usize, usize, usize) -> Instruction;
+
+/// Operand loaded into register, `Big` is decimal `BigInt`
+#[derive(Copy, Clone)]
+enum Num
+{
+    Int(i32),
+    Long(i64),
+    Big(&'static str),
+    Double(f64),
+}
+
+fn load(m: &mut Machine, r: usize, n: Num) -> Instruction
+{
+    match n {
+        Num::Int(i) => Instruction::LoadInt(r, i),
+        Num::Long(l) => Instruction::LoadLong(r, l),
+        Num::Big(s) => {
+            let big = BigInt::parse(s).unwrap();
+            match BigInt::allocate(m, big.0) {
+                Value::Object(id) => Instruction::LoadConst(r, id),
+                _ => unreachable!(),
+            }
+        }
+        Num::Double(d) => Instruction::LoadDouble(r, d),
+    }
+}
+
+/// Result of `op` as `<typename> <value>` or error message
+fn binary(op: Binary, a: Num, b: Num) -> String
+{
+    let mut m = Machine::new();
+    let code = vec![load(&mut m, 1, a), load(&mut m, 2, b), op(3, 1, 2), Instruction::Ret(3)];
+    show(&mut m, code)
+}
+
+fn unary(op: fn(usize, usize) -> Instruction, a: Num) -> String
+{
+    let mut m = Machine::new();
+    let code = vec![load(&mut m, 1, a), op(3, 1), Instruction::Ret(3)];
+    show(&mut m, code)
+}
+
+fn show(m: &mut Machine, code: Vec<Instruction>) -> String
+{
+    let result: Result<Value, VmError> = m.run_code(code);
+    match result {
+        Ok(v @ Value::Object(_)) => format!("Big {}", v.to_String(m)),
+        Ok(v) => format!("{:?}", v),
+        Err(e) => format!("error: {}", e),
+    }
+}
+
+const HUGE: &str = "100000000000000000000";
+
+#[test]
+fn parse()
+{
+    assert_eq!("12", BigInt::parse("12").unwrap().0.to_string());
+    assert_eq!("-12", BigInt::parse(" -12\n").unwrap().0.to_string());
+    assert_eq!("12", BigInt::parse("+12").unwrap().0.to_string());
+    assert_eq!(HUGE, BigInt::parse(HUGE).unwrap().0.to_string());
+    for s in ["", "-", "1.5", "1e5", "12a", "--1", "0x10"].iter() {
+        assert!(BigInt::parse(s).is_none(), "{:?}", s);
+    }
+}
+
+#[test]
+fn arithmetic()
+{
+    let huge = Num::Big(HUGE);
+    assert_eq!("Big 100000000000000000001", binary(Instruction::Add, huge, Num::Int(1)));
+    assert_eq!("Big -99999999999999999999", binary(Instruction::Sub, Num::Long(1), huge));
+    assert_eq!("Big 10000000000000000000000000000000000000000", binary(Instruction::Mul, huge, huge));
+    assert_eq!("Big 1", binary(Instruction::Sub, huge, Num::Big("99999999999999999999")));
+    assert_eq!("Big 33333333333333333333", binary(Instruction::Div, huge, Num::Int(3)));
+    assert_eq!("Big -33333333333333333333", binary(Instruction::IntDiv, huge, Num::Int(-3)));
+    assert_eq!("Big -1", binary(Instruction::Rem, Num::Big("-100000000000000000000"), Num::Int(3)));
+    assert_eq!("Big 10000000000000000000000000000000000000000", binary(Instruction::Pow, huge, Num::Int(2)));
+    assert_eq!("Double(1e-20)", binary(Instruction::Pow, huge, Num::Int(-1)));
+    assert_eq!("Double(1.5e20)", binary(Instruction::Mul, huge, Num::Double(1.5)));
+    assert_eq!("Big -100000000000000000000", unary(Instruction::Neg, huge));
+    assert_eq!("error: Runtime Error: `division by zero`", binary(Instruction::Rem, huge, Num::Long(0)));
+    assert_eq!("error: Runtime Error: `exponent is too large`", binary(Instruction::Pow, Num::Int(2), huge));
+}
+
+#[test]
+fn comparison()
+{
+    let huge = Num::Big(HUGE);
+    assert_eq!("Bool(true)", binary(Instruction::Gt, huge, Num::Long(i64::MAX)));
+    assert_eq!("Bool(true)", binary(Instruction::Lt, Num::Big("-100000000000000000000"), Num::Int(0)));
+    assert_eq!("Bool(true)", binary(Instruction::Eq, huge, Num::Big(HUGE)));
+    assert_eq!("Bool(true)", binary(Instruction::Eq, Num::Big("5"), Num::Int(5)));
+    assert_eq!("Bool(false)", binary(Instruction::Neq, huge, Num::Double(1e20)));
+    assert_eq!("Bool(true)", binary(Instruction::Le, huge, Num::Double(1e20)));
+}
+
+#[test]
+fn bitwise()
+{
+    let huge = Num::Big(HUGE);
+    assert_eq!("Int(4)", binary(Instruction::BitAnd, Num::Int(12), Num::Int(6)));
+    assert_eq!("Long(14)", binary(Instruction::BitOr, Num::Int(12), Num::Long(6)));
+    assert_eq!("Big 10", binary(Instruction::BitXor, Num::Big("12"), Num::Int(6)));
+    assert_eq!("Big 0", binary(Instruction::BitAnd, huge, Num::Int(1)));
+    assert_eq!("Big -100000000000000000001", unary(Instruction::BitNot, huge));
+
+    assert_eq!("Int(1073741824)", binary(Instruction::Shl, Num::Int(1), Num::Int(30)));
+    assert_eq!("Long(2147483648)", binary(Instruction::Shl, Num::Int(1), Num::Int(31)));
+    assert_eq!("Big 18446744073709551616", binary(Instruction::Shl, Num::Long(1), Num::Int(64)));
+    assert_eq!("Long(-9223372036854775808)", binary(Instruction::Shl, Num::Long(-1), Num::Int(63)));
+    assert_eq!("Big 6250000000000000000", binary(Instruction::Shr, huge, Num::Int(4)));
+    assert_eq!("Long(-1)", binary(Instruction::Shr, Num::Long(-5), Num::Int(100)));
+    assert_eq!("Int(0)", binary(Instruction::Shr, Num::Int(5), Num::Long(40)));
+
+    assert_eq!(
+        "error: Runtime Error: `negative shift count in `<<``",
+        binary(Instruction::Shl, Num::Int(1), Num::Int(-1))
+    );
+    assert!(binary(Instruction::BitAnd, Num::Double(1.0), Num::Int(1)).starts_with("error"));
+    assert!(binary(Instruction::Shl, Num::Int(1), Num::Double(1.0)).starts_with("error"));
+}
+
+#[test]
+fn typename_and_conversions()
+{
+    let mut m = Machine::new();
+    let huge = BigInt::allocate(&mut m, BigInt::parse(HUGE).unwrap().0);
+    assert_eq!("Int", huge.typename(&mut m));
+    assert_eq!(HUGE, huge.to_String(&mut m));
+    assert_eq!(1e20, huge.to_double(&mut m));
+    assert_eq!(i64::MAX, huge.to_long(&mut m));
+    assert_eq!(i32::MAX, huge.to_int(&mut m));
+    assert_eq!(Some(-5), BigInt::parse("-5").unwrap().to_i64());
+    assert_eq!(None, BigInt::parse(HUGE).unwrap().to_i64());
+}
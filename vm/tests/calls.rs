@@ -0,0 +1,114 @@
+extern crate jazz_vm;
+
+use self::opcodes::Instruction;
+use jazz_vm::*;
+
+use self::{function::Function, value::Value};
+
+#[test]
+fn native_receives_window()
+{
+    let mut machine = machine::Machine::new();
+
+    use self::Instruction::*;
+
+    let sum = Function::from_native(Box::new(|_: &mut machine::Machine, args: &[Value]| {
+        assert_eq!(args.len(), 3);
+        let mut acc = 0;
+        for arg in &args[1..] {
+            if let Value::Long(l) = arg {
+                acc += l;
+            }
+        }
+        Value::Long(acc)
+    }));
+    let sum_v = Value::Object(machine.pool.allocate(Box::new(sum)));
+    machine.globals.insert(1, sum_v);
+
+    let main_code = vec![
+        LoadGlobal(1, 1),
+        Move(2, 1),
+        LoadLong(3, 40),
+        LoadLong(4, 2),
+        Call(1, 1, 2),
+        Ret(1),
+    ];
+
+    let fun = Function::from(main_code);
+    let fun_v = Value::Object(machine.pool.allocate(Box::new(fun)));
+    let v = machine.invoke(fun_v, &[]).unwrap();
+    let long = if let Value::Long(l) = v {
+        l
+    } else {
+        panic!("");
+    };
+    assert_eq!(42, long);
+}
+
+#[test]
+fn caller_registers_survive_call()
+{
+    let mut machine = machine::Machine::new();
+
+    use self::Instruction::*;
+
+    // Callee writes into registers above its arguments
+    let callee = vec![LoadLong(2, 1), LoadLong(3, 1), Add(1, 1, 2), Ret(1)];
+    let fun = Function::from_instructions(callee, 1);
+    let fun_v = Value::Object(machine.pool.allocate(Box::new(fun)));
+    machine.globals.insert(1, fun_v);
+
+    let main_code = vec![
+        LoadLong(1, 100),
+        LoadGlobal(2, 1),
+        Move(3, 2),
+        LoadLong(4, 5),
+        Call(5, 2, 1),
+        Add(1, 1, 5),
+        Ret(1),
+    ];
+
+    let fun = Function::from(main_code);
+    let fun_v = Value::Object(machine.pool.allocate(Box::new(fun)));
+    let v = machine.invoke(fun_v, &[]).unwrap();
+    let long = if let Value::Long(l) = v {
+        l
+    } else {
+        panic!("");
+    };
+    assert_eq!(106, long);
+}
+
+#[test]
+fn native_raises_error()
+{
+    let mut machine = machine::Machine::new();
+
+    use self::Instruction::*;
+
+    let fail = Function::from_native(Box::new(|m: &mut machine::Machine, _: &[Value]| {
+        m.raise(error::VmError::RuntimeError(String::from("first")));
+        m.raise(error::VmError::RuntimeError(String::from("second")));
+        Value::Null
+    }));
+    let fail_v = Value::Object(machine.pool.allocate(Box::new(fail)));
+    machine.globals.insert(1, fail_v);
+
+    let code = vec![LoadGlobal(1, 1), Move(2, 1), Call(1, 1, 0), LoadLong(1, 1), Ret(1)];
+    let fun_v = Value::Object(machine.pool.allocate(Box::new(Function::from(code))));
+    for _ in 0..2 {
+        match machine.invoke(fun_v, &[]) {
+            Err(e) => assert_eq!("Runtime Error: `first`", e.to_string()),
+            Ok(v) => panic!("{:?}", v),
+        }
+        assert!(machine.stack.is_empty());
+    }
+    match machine.invoke(fail_v, &[Value::Null]) {
+        Err(e) => assert_eq!("Runtime Error: `first`", e.to_string()),
+        Ok(v) => panic!("{:?}", v),
+    }
+    match machine.invoke(Value::Long(1), &[]) {
+        Err(e) => assert_eq!("Runtime Error: `Not callable Long(1)`", e.to_string()),
+        Ok(v) => panic!("{:?}", v),
+    }
+}
@@ -1,6 +1,3 @@
-#![feature(test)]
-extern crate test;
-
 extern crate jazz_vm;
 
 use self::opcodes::Instruction;
@@ -22,32 +19,30 @@ fn factorial()
         LoadInt(2, 1),
         Ret(2),
         Label(1),
-        LoadGlobal(2, 3),
+        LoadGlobal(3, 2),
+        Move(4, 3),
         LoadInt(5, 1),
         Sub(5, 1, 5),
-        PushArg(5),
-        PushArg(3),
         Call(3, 3, 1),
         Mul(3, 3, 1),
         Ret(3),
     ];
 
-    let fun = Function::from(factorial_code);
+    let fun = Function::from_instructions(factorial_code, 1);
     let fun_v = Value::Object(machine.pool.allocate(Box::new(fun)));
     machine.globals.insert(2, fun_v);
 
     let main_code = vec![
-        LoadLong(1, 12),
-        PushArg(1),
-        LoadGlobal(2, 2),
-        PushArg(2),
-        Call(2, 2, 1),
-        Ret(2),
+        LoadGlobal(1, 2),
+        Move(2, 1),
+        LoadLong(3, 12),
+        Call(1, 1, 1),
+        Ret(1),
     ];
 
     let fun = Function::from(main_code);
     let fun_v = Value::Object(machine.pool.allocate(Box::new(fun)));
-    let v = machine.invoke(fun_v, vec![]);
+    let v = machine.invoke(fun_v, &[]).unwrap();
     let int = if let Value::Long(i) = v {
         i
     } else {
@@ -0,0 +1,55 @@
+extern crate jazz_vm;
+
+use self::opcodes::Instruction;
+use jazz_vm::*;
+
+use self::{error::VmError, function::Function, value::Value};
+
+/// Generator that yields `n`, `n + 1`, ... where `n` is its argument
+fn counter(machine: &mut machine::Machine) -> Value
+{
+    use self::Instruction::*;
+
+    let code = vec![Label(0), Yield(1), Inc(1), Goto(0)];
+    let fun = Function::from_generator(code, 1);
+    Value::Object(machine.pool.allocate(Box::new(fun)))
+}
+
+#[test]
+fn call_creates_generator_and_resume_continues_frame()
+{
+    let mut machine = machine::Machine::new();
+
+    use self::Instruction::*;
+
+    let counter = counter(&mut machine);
+    machine.globals.insert(1, counter);
+    let main_code = vec![LoadGlobal(1, 1), Move(2, 1), LoadLong(3, 40), Call(1, 1, 1), Ret(1)];
+    let main = Value::Object(machine.pool.allocate(Box::new(Function::from(main_code))));
+    let generator = machine.invoke(main, &[Value::Null]).unwrap();
+
+    let next = Function::from_method(generator::generator_next);
+    let next = Value::Object(machine.pool.allocate(Box::new(next)));
+    for expected in 40..43 {
+        match machine.invoke(next, &[generator]).unwrap() {
+            Value::Long(l) => assert_eq!(expected, l),
+            v => panic!("{:?}", v),
+        }
+    }
+    assert!(machine.stack.is_empty());
+}
+
+#[test]
+fn yield_outside_of_generator()
+{
+    let mut machine = machine::Machine::new();
+
+    use self::Instruction::*;
+
+    let result = machine.run_code(vec![LoadLong(1, 1), Yield(1), Ret(1)]);
+    match result {
+        Err(VmError::RuntimeError(e)) => assert_eq!("yield outside of generator", e),
+        v => panic!("{:?}", v),
+    }
+    assert!(machine.stack.is_empty());
+}
@@ -0,0 +1,181 @@
+extern crate jazz_vm;
+
+use self::opcodes::{Cmp, Instruction};
+use jazz_vm::*;
+
+use self::{
+    function::{Function, VirtualFunction},
+    jit::JIT_THRESHOLD,
+    value::Value,
+};
+
+use self::Instruction::*;
+
+/// sum(n) = 0 + 1 + .. + (n - 1)
+fn sum_code() -> Vec<Instruction>
+{
+    vec![
+        LoadLong(2, 0),
+        LoadLong(3, 0),
+        JumpCmpF(Cmp::Lt, 3, 1, 6),
+        Add(2, 2, 3),
+        Inc(3),
+        Jump(2),
+        Ret(2),
+    ]
+}
+
+/// collatz(n) = count of steps to reach 1, uses `Mul`, labels and plain compare instructions
+fn collatz_code() -> Vec<Instruction>
+{
+    vec![
+        LoadLong(2, 0),
+        Label(1),
+        LoadLong(3, 1),
+        Gt(4, 1, 3),
+        GotoF(4, 2),
+        Inc(2),
+        // R(6) = n % 2
+        Move(6, 1),
+        Label(3),
+        LoadLong(7, 2),
+        Ge(8, 6, 7),
+        GotoF(8, 4),
+        AddI(6, 6, -2),
+        Goto(3),
+        Label(4),
+        LoadLong(7, 0),
+        Eq(8, 6, 7),
+        GotoF(8, 6),
+        // n = n / 2
+        Move(9, 1),
+        LoadLong(1, 0),
+        Label(5),
+        LoadLong(7, 0),
+        Gt(8, 9, 7),
+        GotoF(8, 1),
+        Inc(1),
+        AddI(9, 9, -2),
+        Goto(5),
+        // n = 3 * n + 1
+        Label(6),
+        LoadLong(7, 3),
+        Mul(1, 1, 7),
+        Inc(1),
+        Goto(1),
+        Label(2),
+        Ret(2),
+    ]
+}
+
+/// Call `code` with `arg` from interpreted code `times` times, returns last result
+fn call(jit: bool, code: Vec<Instruction>, arg: Value, times: usize) -> Value
+{
+    let mut machine = machine::Machine::new();
+    machine.jit = jit;
+
+    let fun = Function::from_instructions(code, 1);
+    let fun_id = machine.pool.allocate(Box::new(fun));
+    machine.globals.insert(1, Value::Object(fun_id));
+
+    let main_code = vec![
+        LoadGlobal(1, 1),
+        Move(2, 1),
+        Move(3, 0),
+        Call(1, 1, 1),
+        Ret(1),
+    ];
+    let main = Function::from(main_code);
+    let main_v = Value::Object(machine.pool.allocate(Box::new(main)));
+
+    let mut result = Value::Null;
+    for _ in 0..times {
+        result = machine.invoke(main_v, &[arg]).unwrap();
+    }
+
+    let obj = machine.pool.get(fun_id);
+    if let Some(Function::Virtual(vf)) = obj.as_any().downcast_ref::<Function>() {
+        assert_eq!(jit && times > JIT_THRESHOLD, vf.jit.compiled());
+    }
+    result
+}
+
+fn long(v: Value) -> i64
+{
+    if let Value::Long(l) = v {
+        l
+    } else {
+        panic!("Expected Long, found {:?}", v);
+    }
+}
+
+#[test]
+fn differential_sum()
+{
+    for n in &[0, 1, 2, 10, 100] {
+        let interpreted = call(false, sum_code(), Value::Long(*n), JIT_THRESHOLD + 2);
+        let compiled = call(true, sum_code(), Value::Long(*n), JIT_THRESHOLD + 2);
+        assert_eq!(long(interpreted), long(compiled));
+        assert_eq!(n * (n - 1) / 2, long(compiled));
+    }
+}
+
+#[test]
+fn differential_collatz()
+{
+    for n in 1..12 {
+        let interpreted = call(false, collatz_code(), Value::Long(n), 1);
+        let compiled = call(true, collatz_code(), Value::Long(n), JIT_THRESHOLD + 2);
+        assert_eq!(long(interpreted), long(compiled), "collatz({})", n);
+    }
+}
+
+#[test]
+fn compiles_numeric_functions()
+{
+    assert!(jit::compile(&VirtualFunction::new(sum_code(), 1)).is_some());
+    assert!(jit::compile(&VirtualFunction::new(collatz_code(), 1)).is_some());
+}
+
+#[test]
+fn rejects_non_numeric_functions()
+{
+    // object access
+    let code = vec![LoadString(2, "a".into()), Ret(2)];
+    assert!(jit::compile(&VirtualFunction::new(code, 1)).is_none());
+    // reads register that may be null
+    let code = vec![JumpCmpIF(Cmp::Eq, 1, 0, 2), LoadLong(2, 1), Ret(2)];
+    assert!(jit::compile(&VirtualFunction::new(code, 1)).is_none());
+    // falls off the end
+    let code = vec![Inc(1)];
+    assert!(jit::compile(&VirtualFunction::new(code, 1)).is_none());
+    // register used as both Long and Bool
+    let code = vec![LoadBool(1, true), Ret(1)];
+    assert!(jit::compile(&VirtualFunction::new(code, 1)).is_none());
+}
+
+#[test]
+fn guard_falls_back_to_interpreter()
+{
+    // Double argument fails guard of compiled code
+    let v = call(true, sum_code(), Value::Double(3.0), JIT_THRESHOLD + 2);
+    assert_eq!(3, long(v));
+}
+
+#[test]
+fn overflow_bails_out()
+{
+    let code = vec![Mul(1, 1, 1), Ret(1)];
+    let compiled = jit::compile(&VirtualFunction::new(code, 1)).unwrap();
+    assert!(compiled.run(&[3]).is_some());
+    assert!(compiled.run(&[i64::MAX]).is_none());
+}
+
+#[test]
+fn returns_bool()
+{
+    let code = vec![LoadLong(2, 10), Lt(3, 1, 2), Ret(3)];
+    let compiled = jit::compile(&VirtualFunction::new(code, 1)).unwrap();
+    assert!(matches!(compiled.run(&[3]), Some(Value::Bool(true))));
+    assert!(matches!(compiled.run(&[30]), Some(Value::Bool(false))));
+}
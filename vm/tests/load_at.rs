@@ -19,12 +19,11 @@ fn load_at()
     let func = machine.pool.allocate(Box::new(func));
 
     let code = vec![
-        Instruction::LoadObject(2, func),
-        Instruction::LoadObject(1, string),
-        Instruction::PushArg(2),
-        Instruction::LoadAt(2, 2, 1),
-        Instruction::PushArg(2),
-        Instruction::Call(2, 2, 0),
+        Instruction::LoadConst(2, func),
+        Instruction::LoadConst(1, string),
+        Instruction::LoadAt(3, 2, 1),
+        Instruction::Move(4, 2),
+        Instruction::Call(2, 3, 0),
         Instruction::Ret(2),
     ];
 
@@ -33,12 +32,12 @@ fn load_at()
             .pool
             .allocate(Box::new(Function::from_instructions(code, 0))),
     );
-    let v = machine.invoke(func, vec![]);
+    let v = machine.invoke(func, &[]).unwrap();
     let obj = if let Value::Object(id) = v {
         machine.pool.get(id)
     } else {
         panic!("");
     };
 
-    println!("{}", obj.to_String(&mut machine));
+    assert!(obj.to_String(&mut machine).contains("Add 2 1 2"));
 }
@@ -0,0 +1,187 @@
+extern crate jazz_vm;
+
+use self::opcodes::{Cmp, Instruction};
+use jazz_vm::*;
+
+use self::{function::Function, value::Value};
+
+fn run(code: Vec<Instruction>) -> i64
+{
+    let mut machine = machine::Machine::new();
+    let fun = Function::from(code);
+    let fun_v = Value::Object(machine.pool.allocate(Box::new(fun)));
+    let v = machine.invoke(fun_v, &[]).unwrap();
+    if let Value::Long(l) = v {
+        l
+    } else {
+        panic!("expected Long, found {:?}", v);
+    }
+}
+
+/// Run `code` without optimization and at every optimization level, returns optimized code of level 2
+fn check(code: Vec<Instruction>, expected: i64) -> Vec<Instruction>
+{
+    assert_eq!(expected, run(code.clone()));
+    for level in 1..=2 {
+        let optimized = optimizer::optimize(code.clone(), level);
+        assert_eq!(expected, run(optimized), "level {}", level);
+    }
+    optimizer::optimize(code, 2)
+}
+
+#[test]
+fn constant_folding()
+{
+    use self::Instruction::*;
+
+    let code = vec![
+        LoadLong(1, 6),
+        LoadLong(2, 7),
+        Mul(3, 1, 2),
+        AddI(3, 3, -2),
+        Ret(3),
+    ];
+    let optimized = check(code, 40);
+    assert_eq!(2, optimized.len());
+}
+
+#[test]
+fn unary_and_power_folding()
+{
+    use self::Instruction::*;
+
+    // -(~(17 % (5 ** 2 div 3))) == 2
+    let code = vec![
+        LoadLong(1, 5),
+        LoadLong(2, 2),
+        Pow(3, 1, 2),
+        LoadLong(2, 3),
+        IntDiv(3, 3, 2),
+        LoadLong(1, 17),
+        Rem(3, 1, 3),
+        BitNot(3, 3),
+        Neg(3, 3),
+        Ret(3),
+    ];
+    let optimized = check(code, 2);
+    assert_eq!(2, optimized.len());
+}
+
+#[test]
+fn overflow_is_not_folded()
+{
+    use self::Instruction::*;
+
+    let code = vec![LoadLong(1, i64::MAX), Inc(1), LoadLong(2, 0), Ret(2)];
+    let optimized = optimizer::optimize(code, 1);
+    assert!(optimized.iter().any(|ins| matches!(ins, Inc(1))));
+}
+
+#[test]
+fn constant_branches()
+{
+    use self::Instruction::*;
+
+    // if 2 > 3 { r = 1 } else { r = 2 }
+    let code = vec![
+        LoadLong(1, 2),
+        LoadLong(2, 3),
+        JumpCmpF(Cmp::Gt, 1, 2, 5),
+        LoadLong(3, 1),
+        Jump(6),
+        LoadLong(3, 2),
+        Ret(3),
+    ];
+    let optimized = check(code, 2);
+    assert!(optimized.iter().all(|ins| ins.jump_target().is_none()));
+}
+
+#[test]
+fn loops_are_kept()
+{
+    use self::Instruction::*;
+
+    // i = 0; acc = 0; while i < 100 { acc = acc + i; i += 1 }
+    let code = vec![
+        LoadLong(1, 0),
+        LoadLong(2, 0),
+        LoadLong(3, 100),
+        JumpCmpF(Cmp::Lt, 1, 3, 7),
+        Add(2, 2, 1),
+        Inc(1),
+        Jump(3),
+        Ret(2),
+    ];
+    let optimized = check(code.clone(), 4950);
+    assert!(optimized.len() <= code.len());
+}
+
+#[test]
+fn copy_propagation()
+{
+    use self::Instruction::*;
+
+    let code = vec![
+        LoadLong(1, 0),
+        LoadLong(5, 10),
+        JumpCmpIF(Cmp::Lt, 1, 3, 8),
+        Move(2, 1),
+        Move(3, 2),
+        Add(4, 3, 5),
+        Move(5, 4),
+        Move(6, 6),
+        Ret(5),
+    ];
+    let optimized = check(code.clone(), 10);
+    assert!(optimized.len() < code.len());
+    assert!(!optimized.iter().any(|ins| matches!(ins, Move(_, _))));
+}
+
+#[test]
+fn jump_threading()
+{
+    use self::Instruction::*;
+
+    let code = vec![
+        LoadLong(1, 5),
+        JumpCmpIF(Cmp::Gt, 1, 0, 3),
+        Jump(4),
+        Jump(5),
+        AddI(1, 1, 1),
+        Ret(1),
+    ];
+    let optimized = check(code, 6);
+    assert!(!optimized.iter().any(|ins| matches!(ins, Jump(_))));
+}
+
+#[test]
+fn labels_are_resolved()
+{
+    use self::Instruction::*;
+
+    let code = vec![
+        LoadLong(1, 0),
+        LoadLong(3, 10),
+        Label(0),
+        Lt(2, 1, 3),
+        GotoF(2, 1),
+        Inc(1),
+        Goto(0),
+        Label(1),
+        Ret(1),
+    ];
+    let optimized = check(code, 10);
+    assert!(!optimized.iter().any(|ins| matches!(ins, Label(_) | Goto(_) | GotoF(_, _))));
+}
+
+#[test]
+fn jump_chains()
+{
+    use self::{optimizer::Pass, Instruction::*};
+
+    let mut code = vec![JumpF(1, 2), Ret(1), Jump(3), Jump(4), Ret0];
+    assert!(optimizer::JumpThread.run(&mut code));
+    assert!(matches!(code[0], JumpF(1, 4)));
+    assert!(matches!(code[2], Ret0));
+    assert!(matches!(code[3], Ret0));
+}
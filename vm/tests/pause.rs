@@ -0,0 +1,83 @@
+extern crate jazz_vm;
+
+use self::opcodes::Instruction;
+use jazz_vm::*;
+
+use self::{
+    function::Function,
+    machine::{Machine, Step},
+    value::Value,
+};
+
+/// Global 1 is native that pauses script
+fn machine() -> Machine
+{
+    let mut machine = Machine::new();
+    let pause = Function::from_native(Box::new(|m: &mut Machine, _: &[Value]| {
+        m.pause();
+        Value::Null
+    }));
+    let pause = Value::Object(machine.pool.allocate(Box::new(pause)));
+    machine.globals.insert(1, pause);
+    machine
+}
+
+fn long(step: Step) -> i64
+{
+    match step {
+        Step::Finished(Value::Long(l)) => l,
+        v => panic!("{:?}", v),
+    }
+}
+
+#[test]
+fn call_receives_value_of_resume()
+{
+    let mut machine = machine();
+
+    use self::Instruction::*;
+
+    let code = vec![
+        LoadLong(1, 40),
+        LoadGlobal(2, 1),
+        Move(3, 2),
+        Call(2, 2, 0),
+        Add(1, 1, 2),
+        Ret(1),
+    ];
+    let main = Value::Object(machine.pool.allocate(Box::new(Function::from(code))));
+    let continuation = match machine.start(main, &[Value::Null]).unwrap() {
+        Step::Suspended(continuation) => continuation,
+        v => panic!("{:?}", v),
+    };
+    assert!(machine.stack.is_empty());
+    let step = continuation.resume(&mut machine, Value::Long(2)).unwrap();
+    assert_eq!(42, long(step));
+}
+
+#[test]
+fn tail_call_returns_value_of_resume()
+{
+    let mut machine = machine();
+
+    use self::Instruction::*;
+
+    let wait = vec![LoadGlobal(1, 1), Move(2, 1), TailCall(1, 0)];
+    let wait = Value::Object(machine.pool.allocate(Box::new(Function::from(wait))));
+    machine.globals.insert(2, wait);
+    let code = vec![
+        LoadGlobal(1, 2),
+        Move(2, 1),
+        Call(1, 1, 0),
+        AddI(1, 1, 1),
+        Ret(1),
+    ];
+    let main = Value::Object(machine.pool.allocate(Box::new(Function::from(code))));
+    let continuation = match machine.start(main, &[Value::Null]).unwrap() {
+        Step::Suspended(continuation) => continuation,
+        v => panic!("{:?}", v),
+    };
+    let step = continuation.resume(&mut machine, Value::Long(41)).unwrap();
+    assert_eq!(42, long(step));
+    assert!(machine.stack.is_empty());
+}
@@ -1,5 +1,3 @@
-extern crate time;
-
 extern crate jazz_vm;
 
 use jazz_vm::*;
@@ -8,24 +6,13 @@ use std::collections::HashMap;
 
 use std::cell::RefCell;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct TestObject
 {
     pub flds: RefCell<HashMap<usize, Value>>,
     pub inited: bool,
 }
 
-impl TestObject
-{
-    pub fn new() -> TestObject
-    {
-        TestObject {
-            flds: RefCell::new(HashMap::new()),
-            inited: false,
-        }
-    }
-}
-
 use self::{
     machine::Machine, object::{Object, ObjectAddon}, object_pool::ObjectPool, value::Value
 };
@@ -96,14 +83,13 @@ fn load_at_and_store_at()
 {
     let mut m = Machine::new();
 
-    let obj = m.pool.allocate(Box::new(TestObject::new()));
+    let obj = m.pool.allocate(Box::new(TestObject::default()));
 
     let code = vec![
-        LoadObject(1, obj),
+        LoadConst(1, obj),
         LoadInt(2, 1),
         LoadFloat(3, 2.6),
         StoreAt(3, 1, 2),
-        PushArg(1),
         LoadAt(4, 1, 2),
         Ret(4),
     ];
@@ -111,7 +97,7 @@ fn load_at_and_store_at()
     let func = self::function::Function::from(code);
     let func = m.pool.allocate(Box::new(func));
 
-    let value = m.invoke(Value::Object(func), vec![]);
+    let value = m.invoke(Value::Object(func), &[]).unwrap();
 
     if let Value::Float(f) = value {
         assert_eq!(f, 2.6);
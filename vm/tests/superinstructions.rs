@@ -0,0 +1,83 @@
+extern crate jazz_vm;
+
+use self::opcodes::{Cmp, Instruction};
+use jazz_vm::*;
+
+use self::{function::Function, value::Value};
+
+fn run(machine: &mut machine::Machine, code: Vec<Instruction>) -> i64
+{
+    let fun = Function::from(code);
+    let fun_v = Value::Object(machine.pool.allocate(Box::new(fun)));
+    let v = machine.invoke(fun_v, &[]).unwrap();
+    if let Value::Long(l) = v {
+        l
+    } else {
+        panic!("");
+    }
+}
+
+#[test]
+fn fused_loop()
+{
+    let mut machine = machine::Machine::new();
+
+    use self::Instruction::*;
+
+    // for (i = 0; i < 1000; i += 1) { acc = acc + 3 }
+    let code = vec![
+        LoadLong(1, 0),
+        LoadLong(2, 0),
+        JumpCmpIF(Cmp::Lt, 1, 1000, 6),
+        AddI(2, 2, 3),
+        Inc(1),
+        Jump(2),
+        Ret(2),
+    ];
+    assert_eq!(3000, run(&mut machine, code));
+}
+
+#[test]
+fn fused_compare_registers()
+{
+    let mut machine = machine::Machine::new();
+
+    use self::Instruction::*;
+
+    // i = 10; while i > n { i = i - 1 }
+    let code = vec![
+        LoadLong(1, 10),
+        LoadLong(2, 4),
+        JumpCmpF(Cmp::Gt, 1, 2, 5),
+        AddI(1, 1, -1),
+        Jump(2),
+        Ret(1),
+    ];
+    assert_eq!(4, run(&mut machine, code));
+}
+
+#[test]
+fn labels_are_per_function()
+{
+    let mut machine = machine::Machine::new();
+
+    use self::Instruction::*;
+
+    // Uses label 1 at different position than caller
+    let callee = vec![Goto(1), LoadLong(1, 0), Label(1), LoadLong(1, 7), Ret(1)];
+    let fun = Function::from_instructions(callee, 0);
+    let fun_v = Value::Object(machine.pool.allocate(Box::new(fun)));
+    machine.globals.insert(1, fun_v);
+
+    let code = vec![
+        LoadGlobal(1, 1),
+        Move(2, 1),
+        Call(3, 1, 0),
+        Goto(1),
+        LoadLong(3, 0),
+        LoadLong(3, 0),
+        Label(1),
+        Ret(3),
+    ];
+    assert_eq!(7, run(&mut machine, code));
+}
@@ -0,0 +1,60 @@
+extern crate jazz_vm;
+
+use self::opcodes::Instruction;
+use jazz_vm::*;
+
+use self::{function::Function, value::Value};
+
+#[test]
+fn tail_call_constant_stack()
+{
+    let mut machine = machine::Machine::new();
+
+    use self::Instruction::*;
+
+    let depth = Function::from_native(Box::new(|m: &mut machine::Machine, _: &[Value]| {
+        Value::Long(m.stack.len() as i64)
+    }));
+    let depth_v = Value::Object(machine.pool.allocate(Box::new(depth)));
+    machine.globals.insert(1, depth_v);
+
+    // count(n) = n == 0 ? depth() : count(n - 1)
+    let count_code = vec![
+        LoadInt(2, 0),
+        Eq(2, 1, 2),
+        GotoF(2, 1),
+        LoadGlobal(3, 1),
+        Move(4, 3),
+        TailCall(3, 0),
+        Label(1),
+        LoadGlobal(3, 2),
+        Move(4, 3),
+        LoadInt(5, 1),
+        Sub(5, 1, 5),
+        TailCall(3, 1),
+    ];
+
+    let fun = Function::from_instructions(count_code, 1);
+    let fun_v = Value::Object(machine.pool.allocate(Box::new(fun)));
+    machine.globals.insert(2, fun_v);
+
+    let main_code = vec![
+        LoadGlobal(1, 2),
+        Move(2, 1),
+        LoadLong(3, 100_000),
+        Call(1, 1, 1),
+        Ret(1),
+    ];
+
+    let fun = Function::from(main_code);
+    let fun_v = Value::Object(machine.pool.allocate(Box::new(fun)));
+    let v = machine.invoke(fun_v, &[]).unwrap();
+    let depth = if let Value::Long(l) = v {
+        l
+    } else {
+        panic!("");
+    };
+    // main frame and single frame reused by every `count` call
+    assert_eq!(2, depth);
+    assert!(machine.stack.is_empty());
+}
@@ -0,0 +1,118 @@
+extern crate jazz_vm;
+
+use self::opcodes::{Cmp, Instruction};
+use jazz_vm::*;
+
+use self::{
+    error::VmError,
+    function::Function,
+    machine::Machine,
+    value::Value,
+    verifier::{verify, VerifyError},
+};
+
+use self::Instruction::*;
+
+#[test]
+fn accepts_valid_code()
+{
+    let m = Machine::new();
+    let code = vec![
+        LoadLong(1, 0),
+        Label(1),
+        JumpCmpIF(Cmp::Lt, 1, 10, 5),
+        Inc(1),
+        Goto(1),
+        Ret(1),
+    ];
+    assert_eq!(Ok(()), verify(&code, &m));
+}
+
+#[test]
+fn register_bounds()
+{
+    let m = Machine::new();
+    let code = vec![LoadLong(256, 0), Ret(0)];
+    assert_eq!(
+        Err(VerifyError::RegisterOutOfBounds { ip: 0, register: 256 }),
+        verify(&code, &m)
+    );
+    // call window doesn't fit into frame
+    let code = vec![Call(1, 250, 10), Ret(1)];
+    assert_eq!(
+        Err(VerifyError::RegisterOutOfBounds { ip: 0, register: 256 }),
+        verify(&code, &m)
+    );
+}
+
+#[test]
+fn labels_and_jumps()
+{
+    let m = Machine::new();
+    let code = vec![Goto(3), Ret0];
+    assert_eq!(Err(VerifyError::UnknownLabel { ip: 0, label: 3 }), verify(&code, &m));
+
+    let code = vec![Label(1), Label(1), Ret0];
+    assert_eq!(Err(VerifyError::DuplicateLabel { ip: 1, label: 1 }), verify(&code, &m));
+
+    let code = vec![LoadBool(1, true), JumpF(1, 10), Ret0];
+    assert_eq!(Err(VerifyError::JumpOutOfBounds { ip: 1, target: 10 }), verify(&code, &m));
+}
+
+#[test]
+fn constants()
+{
+    let mut m = Machine::new();
+    let code = vec![LoadConst(1, 1000), Ret(1)];
+    assert_eq!(Err(VerifyError::UnknownConstant { ip: 0, id: 1000 }), verify(&code, &m));
+
+    let id = m.pool.allocate(Box::new(String::from("constant")));
+    let code = vec![LoadConst(1, id), Ret(1)];
+    assert_eq!(Ok(()), verify(&code, &m));
+}
+
+#[test]
+fn argument_count()
+{
+    let mut m = Machine::new();
+    let fun = Function::from_instructions(vec![Ret(1)], 1);
+    let fun_v = Value::Object(m.pool.allocate(Box::new(fun)));
+    m.globals.insert(1, fun_v);
+
+    let code = vec![LoadGlobal(1, 1), Move(2, 1), Call(1, 1, 2), Ret(1)];
+    assert_eq!(
+        Err(VerifyError::ArgumentCount {
+            ip: 2,
+            expected: 1,
+            found: 2
+        }),
+        verify(&code, &m)
+    );
+
+    let code = vec![LoadGlobal(1, 1), Move(2, 1), LoadLong(3, 1), Call(1, 1, 1), Ret(1)];
+    assert_eq!(Ok(()), verify(&code, &m));
+}
+
+#[test]
+fn reachable_ret()
+{
+    let m = Machine::new();
+    assert_eq!(Err(VerifyError::Empty), verify(&[], &m));
+
+    let code = vec![LoadLong(1, 0), JumpCmpIF(Cmp::Eq, 1, 0, 3), Ret(1), Inc(1)];
+    assert_eq!(Err(VerifyError::MissingRet { ip: 3 }), verify(&code, &m));
+
+    // unreachable code may fall through
+    let code = vec![Ret0, Inc(1)];
+    assert_eq!(Ok(()), verify(&code, &m));
+}
+
+#[test]
+fn run_code_verifies()
+{
+    let mut m = Machine::new();
+    match m.run_code(vec![Goto(1), Ret0]) {
+        Err(VmError::Verify(VerifyError::UnknownLabel { ip: 0, label: 1 })) => {}
+        v => panic!("{:?}", v),
+    }
+}